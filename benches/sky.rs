@@ -0,0 +1,63 @@
+
+//
+//  Sky Model Benchmarks
+//
+
+#[macro_use]
+extern crate criterion;
+extern crate cgmath;
+extern crate sky;
+
+use sky::sky::SkyModel;
+
+use cgmath::{Vector2, Vector3};
+use criterion::Criterion;
+use std::f32::consts::FRAC_PI_2;
+use std::hint::black_box;
+
+
+/// The number of sun elevations swept through when recomputing the
+/// coefficients, from the horizon up to the zenith.
+const ELEVATIONS: usize = 90;
+
+/// The number of directions sampled along each axis of the dome.
+const DOME_RESOLUTION: usize = 64;
+
+
+/// Measures how long it takes to recompute the coefficients as the sun moves.
+fn recompute(c: &mut Criterion) {
+	let elevations = (0 .. ELEVATIONS)
+		.map(|i| i as f32 / (ELEVATIONS - 1) as f32 * FRAC_PI_2)
+		.collect::<Vec<_>>();
+	let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.0, 0.0));
+	c.bench_function("recompute sun sweep", |b| b.iter(|| {
+		for &elevation in &elevations {
+			sky.set_sun(Vector2::new(black_box(elevation), 0.0));
+		}
+	}));
+}
+
+/// Measures how long it takes to sample the whole sky dome on the CPU.
+fn sample_dome(c: &mut Criterion) {
+	let sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.5, 0.0));
+	let mut directions = Vec::with_capacity(DOME_RESOLUTION * DOME_RESOLUTION);
+	for i in 0 .. DOME_RESOLUTION {
+		let elevation = i as f32 / (DOME_RESOLUTION - 1) as f32 * FRAC_PI_2;
+		for j in 0 .. DOME_RESOLUTION {
+			let azimuth = j as f32 / DOME_RESOLUTION as f32 * 4.0 * FRAC_PI_2;
+			directions.push(Vector3::new(elevation.cos() * azimuth.sin(),
+				elevation.sin(), elevation.cos() * azimuth.cos()));
+		}
+	}
+
+	c.bench_function("sample dome", |b| b.iter(|| {
+		let mut total = Vector3::new(0.0, 0.0, 0.0);
+		for &direction in &directions {
+			total += sky.sample(black_box(direction));
+		}
+		total
+	}));
+}
+
+criterion_group!(benches, recompute, sample_dome);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+
+//
+//  Bloom
+//
+
+use gl;
+use gl::types::*;
+
+use framebuffer::Framebuffer;
+use shader::{ShaderProgram, ShaderSources};
+
+
+/// The number of progressively smaller levels the bloom is blurred at. Each
+/// level is half the size of the one before it, starting at half the size of
+/// the screen. Must match the number of `bloom` samplers in the tone mapping
+/// shader.
+pub const LEVELS: usize = 2;
+
+/// The default radius of the blur kernel, in texels. Smaller kernels are
+/// cheaper on weaker GPUs.
+const DEFAULT_RADIUS: i32 = 6;
+
+/// The default radiance above which pixels contribute to bloom.
+const DEFAULT_THRESHOLD: f32 = 1.0;
+
+/// The default strength of the bloom added to the image.
+const DEFAULT_INTENSITY: f32 = 0.3;
+
+
+/// A single level of the bloom, with a pair of framebuffers to ping-pong
+/// between for each direction of the blur.
+struct Level {
+	/// Holds the input to the level, and the final blurred result.
+	target: Framebuffer,
+	/// Holds the horizontally blurred intermediate result.
+	scratch: Framebuffer,
+}
+
+/// Extracts the brightest parts of the HDR image and blurs them, to be added
+/// back on top of the image during tone mapping.
+pub struct Bloom {
+	/// Each level of the bloom, from largest to smallest.
+	levels: Vec<Level>,
+	/// Extracts the radiance above the threshold.
+	bright_program: ShaderProgram,
+	/// Blurs an image along one axis.
+	blur_program: ShaderProgram,
+
+	/// The source texture uniform for the bright pass.
+	bright_source_uniform: GLint,
+	/// The threshold uniform for the bright pass.
+	threshold_uniform: GLint,
+	/// The source texture uniform for the blur.
+	blur_source_uniform: GLint,
+	/// The texel step uniform for the blur.
+	direction_uniform: GLint,
+	/// The kernel radius uniform for the blur.
+	radius_uniform: GLint,
+
+	/// The radiance above which pixels contribute to bloom, in linear units
+	/// before tone mapping.
+	pub threshold: f32,
+	/// The strength of the bloom added to the image.
+	pub intensity: f32,
+	/// The radius of the blur kernel, in texels.
+	pub radius: i32,
+}
+
+impl Bloom {
+	/// Creates the bloom buffers for a screen with the given dimensions, in
+	/// pixels.
+	pub fn new(width: u32, height: u32, sources: &ShaderSources) -> Bloom {
+		let levels = (0 .. LEVELS).map(|level| {
+			let (width, height) = level_size(width, height, level);
+			Level {
+				target: Framebuffer::color_only(width, height),
+				scratch: Framebuffer::color_only(width, height),
+			}
+		}).collect();
+
+		let bright_program = sources.program("post_vert.glsl", "bright_frag.glsl");
+		let blur_program = sources.program("post_vert.glsl", "blur_frag.glsl");
+		Bloom {
+			levels: levels,
+			bright_source_uniform: bright_program.uniform("source"),
+			threshold_uniform: bright_program.uniform("threshold"),
+			blur_source_uniform: blur_program.uniform("source"),
+			direction_uniform: blur_program.uniform("direction"),
+			radius_uniform: blur_program.uniform("radius"),
+			bright_program: bright_program,
+			blur_program: blur_program,
+			threshold: DEFAULT_THRESHOLD,
+			intensity: DEFAULT_INTENSITY,
+			radius: DEFAULT_RADIUS,
+		}
+	}
+
+	/// Resizes the bloom buffers for a screen with the given dimensions.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		for (index, level) in self.levels.iter_mut().enumerate() {
+			let (width, height) = level_size(width, height, index);
+			level.target.resize(width, height);
+			level.scratch.resize(width, height);
+		}
+	}
+
+	/// Renders the bloom from the contents of an HDR framebuffer.
+	///
+	/// Assumes depth testing is disabled and a VAO suitable for drawing the
+	/// fullscreen triangle is bound.
+	pub fn render(&self, source: &Framebuffer) {
+		// Extract the bright parts of the image into the first level. Every
+		// other level is downsampled from the level before it, which just
+		// copies (with a threshold of 0) into the smaller buffer
+		self.bright_program.bind();
+		for (index, level) in self.levels.iter().enumerate() {
+			let (input, threshold) = if index == 0 {
+				(source, self.threshold)
+			} else {
+				(&self.levels[index - 1].target, 0.0)
+			};
+
+			level.target.bind();
+			input.bind_color(0);
+			self.bright_program.set_uniform_i32(self.bright_source_uniform, 0);
+			self.bright_program.set_uniform_f32(self.threshold_uniform, threshold);
+			unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3); }
+		}
+
+		// Blur each level horizontally into the scratch buffer, then
+		// vertically back into the target
+		self.blur_program.bind();
+		self.blur_program.set_uniform_i32(self.blur_source_uniform, 0);
+		self.blur_program.set_uniform_i32(self.radius_uniform, self.radius);
+		for level in self.levels.iter() {
+			let (width, height) = level.target.size();
+			let passes = [
+				(&level.target, &level.scratch, 1.0 / width as f32, 0.0),
+				(&level.scratch, &level.target, 0.0, 1.0 / height as f32),
+			];
+			for &(input, output, x, y) in passes.iter() {
+				output.bind();
+				input.bind_color(0);
+				self.blur_program.set_uniform_vec2(self.direction_uniform, x, y);
+				unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3); }
+			}
+		}
+		Framebuffer::unbind();
+	}
+
+	/// Binds the blurred result of each level to consecutive texture units,
+	/// starting at `first_unit`.
+	pub fn bind_textures(&self, first_unit: u32) {
+		for (index, level) in self.levels.iter().enumerate() {
+			level.target.bind_color(first_unit + index as u32);
+		}
+	}
+}
+
+/// Returns the size of a bloom level for a screen with the given dimensions.
+fn level_size(width: u32, height: u32, level: usize) -> (u32, u32) {
+	let shift = level as u32 + 1;
+	((width >> shift).max(1), (height >> shift).max(1))
+}
+
+/// Sets the bloom texture sampler uniforms on the tone mapping program, which
+/// should be bound. The uniforms are named `bloom[0]`, `bloom[1]`, etc.
+pub fn set_sampler_uniforms(program: &ShaderProgram, first_unit: u32) {
+	for index in 0 .. LEVELS {
+		let uniform = program.uniform(&format!("bloom[{}]", index));
+		program.set_uniform_i32(uniform, (first_unit as usize + index) as GLint);
+	}
+}
@@ -3,175 +3,594 @@
 //  FPS Camera
 //
 
-use cgmath::{Rad, PerspectiveFov, Matrix4, Zero, EuclideanSpace, Vector3,
-	Point3, InnerSpace};
-use std::f32;
+use cgmath::{Rad, PerspectiveFov, Ortho, Matrix, Matrix4, SquareMatrix, Zero,
+	One, EuclideanSpace, Vector3, Vector4, Point3, InnerSpace, Quaternion,
+	Rotation, Rotation3};
+use config::Config;
+use math::{clamp, Handedness};
+use std::{f32, fmt};
 
 
-/// The player's field of view.
-const FOV: f32 = 70.0 * f32::consts::PI / 180.0;
+/// The minimum vertical look angle. Pitch limits can be set inside this range,
+/// but not past it.
+const MIN_ANGLE: f32 = -f32::consts::FRAC_PI_2 + f32::EPSILON;
 
-/// The distance between the eye point of the camera and the near plane.
-const NEAR: f32 = 0.1;
+/// The maximum vertical look angle.
+const MAX_ANGLE: f32 = f32::consts::FRAC_PI_2 - f32::EPSILON;
 
-/// The distance between the eye point of the camera and the far plane.
-const FAR: f32 = 1000.0;
 
+/// The narrowest vertical field of view the camera can be set to, in radians.
+const MIN_FOV: f32 = 1.0 * f32::consts::PI / 180.0;
 
-/// The speed at which the player can look around.
-const LOOK_SPEED: f32 = 0.0015;
+/// The widest vertical field of view the camera can be set to, in radians.
+/// A perspective projection breaks down as it approaches 180 degrees.
+const MAX_FOV: f32 = 170.0 * f32::consts::PI / 180.0;
 
-/// The speed at which the player can walk.
-const MOVE_SPEED: f32 = 0.1;
 
-/// The minimum vertical look angle.
-const MIN_ANGLE: f32 = -f32::consts::FRAC_PI_2 + f32::EPSILON;
+/// The forward direction when the camera has no rotation.
+const BASE_FORWARD: Vector3<f32> = Vector3 { x: 0.0, y: 0.0, z: 1.0 };
 
-/// The maximum vertical look angle.
-const MAX_ANGLE: f32 = f32::consts::FRAC_PI_2 - f32::EPSILON;
+/// The right direction when the camera has no rotation.
+const BASE_RIGHT: Vector3<f32> = Vector3 { x: -1.0, y: 0.0, z: 0.0 };
+
+/// The up direction when the camera has no rotation.
+const BASE_UP: Vector3<f32> = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+
+
+/// The kind of projection used by the camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProjectionKind {
+	/// A perspective projection with a vertical field of view, in radians.
+	Perspective { fov: f32 },
+	/// An orthographic projection with the given view volume height.
+	Orthographic { size: f32 },
+}
+
+impl ProjectionKind {
+	/// Returns the other kind of projection, covering the same area of a plane
+	/// one unit in front of the camera (where the skybox is).
+	pub fn toggled(&self) -> ProjectionKind {
+		match *self {
+			ProjectionKind::Perspective { fov } =>
+				ProjectionKind::Orthographic { size: 2.0 * (fov / 2.0).tan() },
+			ProjectionKind::Orthographic { size } =>
+				ProjectionKind::Perspective { fov: 2.0 * (size / 2.0).atan() },
+		}
+	}
+}
+
+
+/// A snapshot of where the camera is and where it's looking, which can be
+/// stored and restored later. It's plain data, so it can be written out as is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraState {
+	/// The camera's position.
+	pub position: Vector3<f32>,
+	/// The look angle around the vertical axis, in radians. It's measured in
+	/// right-handed coordinates, so the same angle looks the same way under
+	/// either handedness.
+	pub horizontal: f32,
+	/// The look angle above the horizon, in radians.
+	pub vertical: f32,
+	/// The vertical field of view, in radians. For an orthographic projection
+	/// this is the field of view covering the same area.
+	pub fov: f32,
+}
 
 
 /// A 3D first person camera which keeps track of the player's position and
 /// where they're looking.
+#[derive(Clone)]
 pub struct Camera {
-	/// The player's rotation around the vertical axis.
-	horizontal: f32,
-	/// The player's rotation around the horizontal axis.
-	vertical: f32,
-	/// The player's position.
-	pub position: Vector3<f32>,
+	/// The player's look rotation, relative to looking down the z axis.
+	rotation: Quaternion<f32>,
+	/// If true, yaw is always around the world's vertical axis and pitch is
+	/// clamped to just under straight up or down, like an FPS camera.
+	/// Otherwise the camera rotates freely around its own axes.
+	yaw_lock: bool,
+	/// The lowest and highest angles above the horizon the camera can look at
+	/// while yaw lock is enabled, in radians, or None if the pitch isn't
+	/// limited and the camera can turn over the top.
+	pitch_limits: Option<(f32, f32)>,
+	/// The coordinate convention the position and axes are given in. The
+	/// rotation is always kept in right-handed coordinates, and the view
+	/// matrices mirror the world back into them, so OpenGL sees the same scene
+	/// either way.
+	handedness: Handedness,
+	/// The player's position. Only changed through `set_position` and the
+	/// other methods, so the view matrix always matches it.
+	position: Vector3<f32>,
+	/// How far the camera's eye is lowered below `position` by crouching.
+	/// Kept separate from the position so it's undone when standing back up,
+	/// and isn't saved as part of the camera's state.
+	crouch: f32,
 
 	/// A vector pointing in the forwards direction for the player.
 	forward: Vector3<f32>,
 	/// A vector pointing to the right relative to where the player's looking.
 	right: Vector3<f32>,
-	/// A vector pointing straight up.
+	/// A vector pointing up relative to where the player's looking.
 	up: Vector3<f32>,
 
-	/// The field of view for the projection matrix.
-	fov: f32,
+	/// The kind of projection matrix to use.
+	projection_kind: ProjectionKind,
 	/// The near plane for the projection matrix.
 	near: f32,
 	/// The far plane for the projection matrix.
 	far: f32,
+	/// True if depth should be stored logarithmically, rather than with the
+	/// projection matrix's hyperbolic spread.
+	log_depth: bool,
 	/// The aspect ratio of the window.
 	aspect: f32,
 
-	/// The perspective projection matrix.
+	/// The speed at which the player can walk, in units per second.
+	move_speed: f32,
+	/// The speed at which the player can look around, in radians per pixel of
+	/// mouse movement.
+	look_speed: f32,
+
+	/// The projection matrix.
 	pub projection: Matrix4<f32>,
 	/// The orientation matrix (projection and rotation, excluding translation).
 	pub orientation: Matrix4<f32>,
 	/// The view matrix (projection, rotation, and translation).
 	pub view: Matrix4<f32>,
+
+	/// The state the camera started in, restored by `reset`.
+	initial_state: CameraState,
 }
 
 impl Camera {
 	/// Creates a new camera, in a window with the given dimensions.
-	pub fn new(width: u32, height: u32) -> Camera {
+	pub fn new(width: u32, height: u32, config: &Config) -> Camera {
 		let mut camera = Camera {
-			horizontal: f32::consts::FRAC_PI_2,
-			vertical: 0.0,
+			rotation: Quaternion::from_axis_angle(BASE_UP,
+				Rad(f32::consts::FRAC_PI_2)),
+			yaw_lock: true,
+			pitch_limits: Some((MIN_ANGLE, MAX_ANGLE)),
+			handedness: if config.left_handed { Handedness::Left } else { Handedness::Right },
 			position: Vector3::new(0.0, 0.0, 0.0),
+			crouch: 0.0,
 
 			forward: Vector3::zero(),
 			right: Vector3::zero(),
 			up: Vector3::zero(),
 
-			fov: FOV,
-			near: NEAR,
-			far: FAR,
+			projection_kind: ProjectionKind::Perspective {
+				fov: config.fov.to_radians(),
+			},
+			near: 0.0,
+			far: 0.0,
+			log_depth: config.log_depth,
 			aspect: width as f32 / height as f32,
 
+			move_speed: config.move_speed,
+			look_speed: config.look_speed,
+
 			projection: Matrix4::zero(),
 			orientation: Matrix4::zero(),
 			view: Matrix4::zero(),
+
+			initial_state: CameraState {
+				position: Vector3::zero(),
+				horizontal: 0.0,
+				vertical: 0.0,
+				fov: 0.0,
+			},
 		};
 
-		camera.update_projection();
+		// The config's checked the planes the same way when it was loaded
+		camera.set_clip_planes(config.near, config.far)
+			.unwrap_or_else(|err| panic!("Invalid clip planes: {}", err));
 		camera.update_axes();
 		camera.update_orientation();
 		camera.update_view();
+		if config.limit_pitch {
+			camera.set_pitch_limits(Some((config.min_pitch.to_radians(),
+				config.max_pitch.to_radians())));
+		} else {
+			camera.set_pitch_limits(None);
+		}
+		camera.initial_state = camera.state();
 		camera
 	}
 
+	/// Returns the camera's current position, look angles, and field of view.
+	pub fn state(&self) -> CameraState {
+		let forward = self.handedness.convert(self.forward);
+		CameraState {
+			position: self.position,
+			horizontal: forward.x.atan2(forward.z),
+			vertical: clamp(self.forward.y, -1.0, 1.0).asin(),
+			fov: self.fov(),
+		}
+	}
+
+	/// Returns the vertical field of view, in radians. For an orthographic
+	/// projection this is the field of view covering the same area of the
+	/// skybox.
+	pub fn fov(&self) -> f32 {
+		match self.projection_kind {
+			ProjectionKind::Perspective { fov } => fov,
+			ProjectionKind::Orthographic { size } => 2.0 * (size / 2.0).atan(),
+		}
+	}
+
+	/// Moves the camera to a previously saved state. Any roll built up while
+	/// rotating freely isn't part of the state, so it's removed. The kind of
+	/// projection is kept.
+	pub fn set_state(&mut self, state: CameraState) {
+		self.position = state.position;
+		self.rotation = Quaternion::from_axis_angle(BASE_UP, Rad(state.horizontal)) *
+			Quaternion::from_axis_angle(BASE_RIGHT, Rad(state.vertical));
+		self.projection_kind = match self.projection_kind {
+			ProjectionKind::Perspective { .. } =>
+				ProjectionKind::Perspective { fov: state.fov },
+			ProjectionKind::Orthographic { .. } =>
+				ProjectionKind::Perspective { fov: state.fov }.toggled(),
+		};
+
+		// Update matrices
+		self.update_projection();
+		self.update_axes();
+		self.update_orientation();
+		self.update_view();
+	}
+
+	/// Moves the camera back to where it started.
+	pub fn reset(&mut self) {
+		let state = self.initial_state;
+		self.set_state(state);
+	}
+
 	/// Update the camera's projection matrix.
 	pub fn update_projection(&mut self) {
-		self.projection = Matrix4::from(PerspectiveFov {
-			fovy: Rad(self.fov),
-			aspect: self.aspect,
-			near: self.near,
-			far: self.far,
-		});
+		self.projection = match self.projection_kind {
+			ProjectionKind::Perspective { fov } => Matrix4::from(PerspectiveFov {
+				fovy: Rad(fov),
+				aspect: self.aspect,
+				near: self.near,
+				far: self.far,
+			}),
+			ProjectionKind::Orthographic { size } => {
+				let half_height = size / 2.0;
+				let half_width = half_height * self.aspect;
+				Matrix4::from(Ortho {
+					left: -half_width,
+					right: half_width,
+					bottom: -half_height,
+					top: half_height,
+					near: self.near,
+					far: self.far,
+				})
+			},
+		};
+	}
+
+	/// Updates the aspect ratio for a window with the given dimensions,
+	/// rebuilding the projection matrix.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.aspect = width as f32 / height as f32;
+		self.update_projection();
+	}
+
+	/// Sets the kind of projection to use, rebuilding the projection matrix.
+	pub fn set_projection_kind(&mut self, kind: ProjectionKind) {
+		self.projection_kind = kind;
+		self.update_projection();
+	}
+
+	/// Sets the vertical field of view, in radians, clamped to a sensible
+	/// range, and rebuilds the projection matrix. An orthographic projection
+	/// stays orthographic, covering the same area as the field of view.
+	pub fn set_fov(&mut self, fov: f32) {
+		let fov = clamp(fov, MIN_FOV, MAX_FOV);
+		let kind = match self.projection_kind {
+			ProjectionKind::Perspective { .. } => ProjectionKind::Perspective { fov: fov },
+			ProjectionKind::Orthographic { .. } =>
+				ProjectionKind::Perspective { fov: fov }.toggled(),
+		};
+		self.set_projection_kind(kind);
+	}
+
+	/// Returns the distances from the eye to the near and far planes.
+	pub fn clip_planes(&self) -> (f32, f32) {
+		(self.near, self.far)
+	}
+
+	/// Sets the distances from the eye to the near and far planes, and
+	/// rebuilds the projection matrix. The planes are left as they were if the
+	/// near plane isn't in front of the eye, or the far plane isn't beyond it.
+	///
+	/// The sky's drawn on the far plane whatever its distance, so moving the
+	/// planes only clips the rest of the scene. A wider range spreads the depth
+	/// buffer's precision more thinly, which logarithmic depth helps with.
+	pub fn set_clip_planes(&mut self, near: f32, far: f32) -> Result<(), ClipPlaneError> {
+		check_clip_planes(near, far)?;
+		self.near = near;
+		self.far = far;
+		self.update_projection();
+		Ok(())
+	}
+
+	/// Returns true if depth should be written logarithmically by the shaders
+	/// for anything drawn in front of the sky. The depth of a point `w` units
+	/// in front of the eye is then `log2(1 + w) / log2(1 + far)`, which keeps
+	/// more precision in the distance than the projection matrix does, and
+	/// still runs up to 1 at the far plane, behind which the sky's drawn.
+	///
+	/// Set by `camera.log_depth` in the config. The orthographic projection
+	/// already spreads depth evenly, so it's never used there.
+	pub fn log_depth(&self) -> bool {
+		match self.projection_kind {
+			ProjectionKind::Perspective { .. } => self.log_depth,
+			ProjectionKind::Orthographic { .. } => false,
+		}
+	}
+
+	/// Switches between perspective and orthographic projection.
+	pub fn toggle_projection(&mut self) {
+		let kind = self.projection_kind.toggled();
+		self.set_projection_kind(kind);
 	}
 
-	/// Update the camera's axes relative to the look direction.
+	/// Update the camera's axes relative to the look direction, in the
+	/// camera's coordinate convention.
 	pub fn update_axes(&mut self) {
-		// Convert spherical coordinates to cartesian using horizontal and
-		// vertical angles, with sphere radius of 1
-		self.forward = Vector3::new(
-			self.vertical.cos() * self.horizontal.sin(),
-			self.vertical.sin(),
-			self.vertical.cos() * self.horizontal.cos()
-		);
-
-		// Right vector is always horizontal in the xz plane (as camera doesn't
-		// rotate around x axis)
-		self.right = Vector3::new(
-			(self.horizontal - f32::consts::FRAC_PI_2).sin(),
-			0.0,
-			(self.horizontal - f32::consts::FRAC_PI_2).cos()
-		);
-
-		// Up vector is cross product of forward and right vectors
-		self.up = self.right.cross(self.forward);
+		let handedness = self.handedness;
+		self.forward = handedness.convert(self.rotation.rotate_vector(BASE_FORWARD));
+		self.right = handedness.convert(self.rotation.rotate_vector(BASE_RIGHT));
+		self.up = handedness.convert(self.rotation.rotate_vector(BASE_UP));
 	}
 
 	/// Updates the camera's orientation matrix.
 	fn update_orientation(&mut self) {
+		let handedness = self.handedness;
 		self.orientation = Matrix4::look_at(
 			Point3::origin(),
-			Point3::origin() + self.forward,
-			self.up
-		);
+			Point3::origin() + handedness.convert(self.forward),
+			handedness.convert(self.up)
+		) * Matrix4::from(handedness.mirror());
 	}
 
 	/// Updates the camera's view matrix.
 	fn update_view(&mut self) {
+		// Build the view in right-handed coordinates, then mirror the world
+		// into them first
+		let handedness = self.handedness;
+		let eye = handedness.convert(self.position());
 		self.view = Matrix4::look_at(
-			Point3::from_vec(self.position),
-			Point3::from_vec(self.position + self.forward),
-			self.up
-		);
+			Point3::from_vec(eye),
+			Point3::from_vec(eye + handedness.convert(self.forward)),
+			handedness.convert(self.up)
+		) * Matrix4::from(handedness.mirror());
 	}
 
 	/// Rotates the camera by a certain amount along each axis.
 	pub fn look(&mut self, horizontal: f32, vertical: f32, delta: f32) {
-		// Vertical rotation
-		self.vertical = (self.vertical + vertical * delta * LOOK_SPEED)
-			.max(MIN_ANGLE).min(MAX_ANGLE);
+		let yaw = horizontal * delta * self.look_speed;
+		let pitch = vertical * delta * self.look_speed;
+		self.rotate(yaw, pitch);
+	}
 
-		// Horizontal rotation
-		self.horizontal = self.horizontal + horizontal * delta * LOOK_SPEED;
-		if self.horizontal < 0.0 {
-			self.horizontal += f32::consts::PI * 2.0;
-		} else if self.horizontal > f32::consts::PI * 2.0 {
-			self.horizontal -= f32::consts::PI * 2.0;
+	/// Rotates the camera by angles in radians, rather than by mouse movement
+	/// as `look` does, for turning it by a fixed amount.
+	pub fn rotate(&mut self, mut yaw: f32, mut pitch: f32) {
+		let yaw_rotation;
+		if self.yaw_lock {
+			// Clamp the pitch so we can't look past the limits
+			if self.pitch_limits.is_some() {
+				let current = clamp(self.forward.y, -1.0, 1.0).asin();
+				pitch = self.clamp_pitch(current + pitch) - current;
+			}
+
+			// Yaw around the world's vertical axis, applied after the current
+			// rotation
+			yaw_rotation = Quaternion::from_axis_angle(BASE_UP, Rad(yaw));
+			yaw = 0.0;
+		} else {
+			yaw_rotation = Quaternion::one();
 		}
 
+		// Pitch (and yaw, when not locked) around the camera's own axes
+		let local_rotation = Quaternion::from_axis_angle(BASE_UP, Rad(yaw)) *
+			Quaternion::from_axis_angle(BASE_RIGHT, Rad(pitch));
+
+		// Renormalise to stop error accumulating over many small rotations
+		self.rotation = (yaw_rotation * self.rotation * local_rotation)
+			.normalize();
+
+		// Update matrices
+		self.update_axes();
+		self.update_orientation();
+		self.update_view();
+	}
+
+	/// Turns the camera to look at a point, from where its eye currently is.
+	/// The vertical angle is clamped the same way as in `look`, and any roll is
+	/// removed. Does nothing if the point is at the eye.
+	pub fn look_at(&mut self, target: Vector3<f32>) {
+		let direction = self.handedness.convert(target - self.position());
+		let distance = direction.magnitude();
+		if distance < f32::EPSILON {
+			return;
+		}
+
+		let yaw = direction.x.atan2(direction.z);
+		let pitch = self.clamp_pitch(clamp(direction.y / distance, -1.0, 1.0).asin());
+		self.rotation = Quaternion::from_axis_angle(BASE_UP, Rad(yaw)) *
+			Quaternion::from_axis_angle(BASE_RIGHT, Rad(pitch));
+
 		// Update matrices
 		self.update_axes();
 		self.update_orientation();
 		self.update_view();
 	}
 
-	/// Moves the camera around by a certain amount along each axis.
+	/// Returns the inverse of the projection and orientation matrices
+	/// combined, which takes a point in clip space back to a direction from
+	/// the camera's eye, for working out the view ray through each pixel.
+	pub fn inverse_orientation(&self) -> Matrix4<f32> {
+		(self.projection * self.orientation).invert().unwrap_or_else(Matrix4::one)
+	}
+
+	/// Describes the camera's position, look angles, pitch limits, and matrices
+	/// over several lines, for printing while debugging. The matrices are
+	/// written a row at a time, with their columns lined up.
+	pub fn describe(&self) -> String {
+		let state = self.state();
+		let position = self.position();
+		let mut description = format!("Position {:.3} {:.3} {:.3}\n",
+			position.x, position.y, position.z);
+		description.push_str(&format!("Horizontal {:.2}\u{b0}, vertical {:.2}\u{b0}, FOV {:.1}\u{b0}\n",
+			state.horizontal.to_degrees(), state.vertical.to_degrees(),
+			state.fov.to_degrees()));
+		description.push_str(&match self.pitch_limits() {
+			Some((min, max)) => format!("Pitch limited to {:.1}\u{b0} to {:.1}\u{b0}\n",
+				min.to_degrees(), max.to_degrees()),
+			None => "Pitch unlimited\n".to_string(),
+		});
+		for &(name, matrix) in &[("Projection", &self.projection),
+				("Orientation", &self.orientation), ("View", &self.view)] {
+			description.push_str(&format_matrix(name, matrix));
+		}
+		description
+	}
+
+	/// Returns the direction the camera is looking in.
+	pub fn forward(&self) -> Vector3<f32> {
+		self.forward
+	}
+
+	/// Returns a copy of the camera moved sideways by `offset` along its right
+	/// axis (negative to move left), for one eye of a stereo pair. Crouching
+	/// and the look direction are kept.
+	pub fn eye(&self, offset: f32) -> Camera {
+		let mut eye = self.clone();
+		eye.set_position(self.position + self.right * offset);
+		eye
+	}
+
+	/// Returns the position the camera is viewing from, which is lowered
+	/// while crouching.
+	pub fn position(&self) -> Vector3<f32> {
+		self.position - Vector3::new(0.0, self.crouch, 0.0)
+	}
+
+	/// Moves the player straight to a position, keeping the direction they're
+	/// looking in. Any crouch still applies on top.
+	pub fn set_position(&mut self, position: Vector3<f32>) {
+		self.position = position;
+		self.update_view();
+	}
+
+	/// Returns how far the camera's eye is lowered by crouching.
+	pub fn crouch(&self) -> f32 {
+		self.crouch
+	}
+
+	/// Sets how far the camera's eye is lowered by crouching, without moving
+	/// the player.
+	pub fn set_crouch(&mut self, crouch: f32) {
+		self.crouch = crouch;
+		self.update_view();
+	}
+
+	/// Returns the six planes of the view frustum, in the order left, right,
+	/// bottom, top, near, far. Each plane is stored as `(a, b, c, d)` where
+	/// `ax + by + cz + d = 0`, with a unit length normal pointing inside the
+	/// frustum, so a point's signed distance from the plane is just a dot
+	/// product.
+	pub fn frustum_planes(&self) -> [Vector4<f32>; 6] {
+		// Extract the planes from the rows of the combined view-projection
+		// matrix (Gribb and Hartmann's method)
+		let matrix = self.projection * self.view;
+		let x = matrix.row(0);
+		let y = matrix.row(1);
+		let z = matrix.row(2);
+		let w = matrix.row(3);
+		let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+
+		// Normalise so the plane normals have unit length
+		for plane in planes.iter_mut() {
+			let length = plane.truncate().magnitude();
+			*plane = *plane / length;
+		}
+		planes
+	}
+
+	/// Enables or disables FPS-style yaw lock. Enabling it removes any roll
+	/// built up while rotating freely, keeping the current look direction.
+	pub fn set_yaw_lock(&mut self, yaw_lock: bool) {
+		self.yaw_lock = yaw_lock;
+		if yaw_lock {
+			// Rebuild an upright rotation from the look direction
+			let forward = self.handedness.convert(self.forward);
+			let yaw = forward.x.atan2(forward.z);
+			let pitch = self.clamp_pitch(clamp(self.forward.y, -1.0, 1.0).asin());
+			self.rotation = Quaternion::from_axis_angle(BASE_UP, Rad(yaw)) *
+				Quaternion::from_axis_angle(BASE_RIGHT, Rad(pitch));
+
+			// Update matrices
+			self.update_axes();
+			self.update_orientation();
+			self.update_view();
+		}
+	}
+
+	/// Returns true if yaw lock is enabled.
+	pub fn yaw_lock(&self) -> bool {
+		self.yaw_lock
+	}
+
+	/// Limits how far below and above the horizon the camera can look while
+	/// yaw lock is enabled, in radians, or removes the limits if None. The
+	/// limits are kept just inside straight down and up, and the current view
+	/// is clamped to them.
+	///
+	/// Panics if the lower limit isn't below the upper one.
+	pub fn set_pitch_limits(&mut self, limits: Option<(f32, f32)>) {
+		if let Some((min, max)) = limits {
+			assert!(min < max, "pitch limits {} to {} are the wrong way round", min, max);
+		}
+		self.pitch_limits = limits.map(|(min, max)| (
+			clamp(min, MIN_ANGLE, MAX_ANGLE),
+			clamp(max, MIN_ANGLE, MAX_ANGLE),
+		));
+		self.rotate(0.0, 0.0);
+	}
+
+	/// Returns the limits on how far below and above the horizon the camera
+	/// can look, in radians, or None if the pitch isn't limited.
+	pub fn pitch_limits(&self) -> Option<(f32, f32)> {
+		self.pitch_limits
+	}
+
+	/// Clamps an angle above the horizon to the pitch limits, or to just
+	/// inside straight down and up without any.
+	fn clamp_pitch(&self, pitch: f32) -> f32 {
+		let (min, max) = self.pitch_limits.unwrap_or((MIN_ANGLE, MAX_ANGLE));
+		clamp(pitch, min, max)
+	}
+
+	/// Moves the camera along each axis for `delta` seconds, where each
+	/// component is a velocity as a fraction of the walking speed.
 	pub fn walk(&mut self, x: f32, y: f32, z: f32, delta: f32) {
-		let scale = delta * MOVE_SPEED;
+		let scale = delta * self.move_speed;
+
+		// With yaw lock, movement stays in the horizontal plane regardless of
+		// where the player's looking
+		let (right, forward) = if self.yaw_lock {
+			(Vector3::new(self.right.x, 0.0, self.right.z).normalize(),
+				Vector3::new(self.forward.x, 0.0, self.forward.z).normalize())
+		} else {
+			(self.right, self.forward)
+		};
 
 		// X axis
 		if x.abs() > f32::EPSILON {
-			self.position += Vector3::new(self.right.x, 0.0, self.right.z)
-				.normalize() * x * scale;
+			self.position += right * x * scale;
 		}
 
 		// Y axis
@@ -181,11 +600,290 @@ impl Camera {
 
 		// Z axis
 		if z.abs() > f32::EPSILON {
-			self.position += Vector3::new(self.forward.x, 0.0, self.forward.z)
-				.normalize() * z * scale;
+			self.position += forward * z * scale;
 		}
 
 		// Update matrices
 		self.update_view();
 	}
 }
+
+
+/// Why a pair of clip plane distances can't be used for a projection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClipPlaneError {
+	/// The near plane isn't in front of the eye.
+	Near(f32),
+	/// The far plane isn't finite, or isn't beyond the near plane.
+	Far { near: f32, far: f32 },
+}
+
+impl fmt::Display for ClipPlaneError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ClipPlaneError::Near(near) =>
+				write!(f, "near plane {} must be positive", near),
+			ClipPlaneError::Far { near, far } =>
+				write!(f, "far plane {} must be finite and beyond the near plane {}",
+					far, near),
+		}
+	}
+}
+
+/// Checks the distances from the eye to the near and far planes can be used
+/// for a projection, as `Camera::set_clip_planes` and the config need.
+pub fn check_clip_planes(near: f32, far: f32) -> Result<(), ClipPlaneError> {
+	if near.is_nan() || near <= 0.0 {
+		return Err(ClipPlaneError::Near(near));
+	}
+	if far.is_nan() || far <= near || far.is_infinite() {
+		return Err(ClipPlaneError::Far { near: near, far: far });
+	}
+	Ok(())
+}
+
+
+/// Formats a matrix a row at a time under a heading, padding every element to
+/// the same width so the columns line up in a terminal.
+fn format_matrix(name: &str, matrix: &Matrix4<f32>) -> String {
+	let mut lines = format!("{}:\n", name);
+	for row in 0 .. 4 {
+		// The matrix is stored a column at a time
+		let elements: Vec<String> = (0 .. 4)
+			.map(|column| format!("{:>11.5}", matrix[column][row]))
+			.collect();
+		lines.push_str(&format!("  [{} ]\n", elements.join(" ")));
+	}
+	lines
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Camera, ClipPlaneError, ProjectionKind, format_matrix};
+	use config::Config;
+	use math::Handedness;
+	use sky::SkyModel;
+
+	use cgmath::{Matrix4, Vector2, Vector3, Vector4, InnerSpace};
+	use std::f32;
+	use std::f32::consts::PI;
+
+	/// Asserts the camera's looking in a direction, to within rounding error.
+	fn assert_forward(camera: &Camera, expected: Vector3<f32>) {
+		let forward = camera.forward();
+		assert!((forward - expected.normalize()).magnitude() < 1e-4,
+			"looking towards {:?} rather than {:?}", forward, expected);
+	}
+
+	#[test]
+	fn look_at_points_forward_at_target() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_position(Vector3::new(1.0, 2.0, 3.0));
+		for &target in &[Vector3::new(5.0, 2.0, 3.0), Vector3::new(-4.0, 7.0, 1.0),
+				Vector3::new(1.0, -3.0, -10.0), Vector3::new(0.0, 0.0, 0.0)] {
+			camera.look_at(target);
+			assert_forward(&camera, target - camera.position());
+		}
+	}
+
+	#[test]
+	fn inverse_orientation_recovers_view_ray() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.look_at(Vector3::new(3.0, 1.0, -2.0));
+
+		// The middle of the far plane lies straight ahead
+		let far = camera.inverse_orientation() * Vector4::new(0.0, 0.0, 1.0, 1.0);
+		let ray = far.truncate() / far.w;
+		assert_forward(&camera, ray);
+	}
+
+	#[test]
+	fn set_position_updates_view() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		let position = Vector3::new(4.0, -2.0, 7.0);
+		camera.set_position(position);
+		assert_eq!(camera.position(), position);
+
+		// The view matrix takes the new eye position to the origin
+		let eye = camera.view * Vector4::new(position.x, position.y, position.z, 1.0);
+		assert!(eye.truncate().magnitude() < 1e-4, "eye at {:?} in view space", eye);
+	}
+
+	#[test]
+	fn matrices_formatted_by_row() {
+		let translation = Matrix4::from_translation(Vector3::new(1.5, -20.0, 300.0));
+		let lines: Vec<String> = format_matrix("View", &translation).lines()
+			.map(|line| line.to_string())
+			.collect();
+		assert_eq!(lines[0], "View:");
+		assert_eq!(lines[1], "  [    1.00000     0.00000     0.00000     1.50000 ]");
+		assert_eq!(lines[2], "  [    0.00000     1.00000     0.00000   -20.00000 ]");
+		assert_eq!(lines[3], "  [    0.00000     0.00000     1.00000   300.00000 ]");
+		assert_eq!(lines[4], "  [    0.00000     0.00000     0.00000     1.00000 ]");
+	}
+
+	#[test]
+	fn pitch_limits_clamp_look() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_pitch_limits(Some((-0.2, 0.5)));
+		camera.rotate(0.0, 1.0);
+		assert!((camera.state().vertical - 0.5).abs() < 1e-4);
+		camera.rotate(0.0, -3.0);
+		assert!((camera.state().vertical + 0.2).abs() < 1e-4);
+
+		// Tightening the limits clamps the current view straight away
+		camera.set_pitch_limits(Some((-0.1, 0.1)));
+		assert!((camera.state().vertical + 0.1).abs() < 1e-4);
+		assert_eq!(camera.pitch_limits(), Some((-0.1, 0.1)));
+		assert!(camera.describe().contains("Pitch limited to -5.7\u{b0} to 5.7\u{b0}\n"));
+
+		// Without limits the camera can turn over the top
+		camera.set_pitch_limits(None);
+		camera.rotate(0.0, 2.0);
+		assert!(camera.state().vertical.abs() < PI / 2.0 - 0.2);
+		assert!(camera.describe().contains("Pitch unlimited\n"));
+	}
+
+	#[test]
+	#[should_panic]
+	fn pitch_limits_must_be_ordered() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_pitch_limits(Some((0.5, -0.5)));
+	}
+
+	#[test]
+	fn look_at_accounts_for_crouch() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_crouch(0.5);
+		camera.look_at(Vector3::new(0.0, -0.5, 10.0));
+		assert_forward(&camera, Vector3::new(0.0, 0.0, 1.0));
+	}
+
+	#[test]
+	fn look_at_clamps_straight_up() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.look_at(Vector3::new(0.0, 10.0, 0.0));
+		let forward = camera.forward();
+		assert!(forward.x.is_finite() && forward.z.is_finite());
+		assert!(forward.y > 0.999);
+
+		// The up vector comes from the rotation rather than the world, so the
+		// view matrix stays well defined
+		let view: &[f32; 16] = camera.view.as_ref();
+		assert!(view.iter().all(|value| value.is_finite()));
+	}
+
+	#[test]
+	fn sun_in_same_place_under_either_handedness() {
+		let clip_position = |left_handed: bool| {
+			let config = Config { left_handed: left_handed, ..Config::default() };
+			let mut camera = Camera::new(900, 620, &config);
+			camera.rotate(0.4, 0.2);
+			let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.3, 1.0));
+			sky.set_handedness(if left_handed { Handedness::Left } else { Handedness::Right });
+
+			let sun = camera.projection * camera.orientation * sky.sun_direction().extend(0.0);
+			assert!(sun.w > 0.0, "sun behind the camera at {:?}", sun);
+			(sun.truncate() / sun.w, sky.sun_direction(), camera.forward())
+		};
+		let (right, right_sun, right_forward) = clip_position(false);
+		let (left, left_sun, left_forward) = clip_position(true);
+		assert!((right - left).magnitude() < 1e-4, "{:?} and {:?}", right, left);
+
+		// The coordinates themselves differ in the sign of z
+		assert!((left_sun - Vector3::new(right_sun.x, right_sun.y, -right_sun.z))
+			.magnitude() < 1e-6);
+		assert!((left_forward - Vector3::new(right_forward.x, right_forward.y,
+			-right_forward.z)).magnitude() < 1e-6);
+	}
+
+	#[test]
+	fn left_handed_look_at_and_state_round_trip() {
+		let config = Config { left_handed: true, ..Config::default() };
+		let mut camera = Camera::new(900, 620, &config);
+		camera.set_position(Vector3::new(1.0, 2.0, 3.0));
+		camera.look_at(Vector3::new(-4.0, 5.0, 9.0));
+		assert_forward(&camera, Vector3::new(-5.0, 3.0, 6.0));
+
+		let state = camera.state();
+		camera.reset();
+		camera.set_state(state);
+		assert_forward(&camera, Vector3::new(-5.0, 3.0, 6.0));
+
+		// The view matrix still takes the eye to the origin, looking down -z
+		let view = camera.view;
+		let eye = view * Vector4::new(1.0, 2.0, 3.0, 1.0);
+		assert!(eye.truncate().magnitude() < 1e-4, "eye at {:?} in view space", eye);
+		let ahead = view * Vector4::new(-4.0, 5.0, 9.0, 1.0);
+		assert!(ahead.z < 0.0 && ahead.x.abs() < 1e-3 && ahead.y.abs() < 1e-3,
+			"target at {:?} in view space", ahead);
+	}
+
+	#[test]
+	fn eyes_straddle_camera() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_position(Vector3::new(1.0, 2.0, 3.0));
+		camera.set_crouch(0.5);
+		camera.rotate(0.7, 0.3);
+		let left = camera.eye(-0.05);
+		let right = camera.eye(0.05);
+		assert!((left.position() + right.position() - camera.position() * 2.0)
+			.magnitude() < 1e-5);
+		assert!(((right.position() - left.position()).magnitude() - 0.1).abs() < 1e-5);
+		assert_forward(&left, camera.forward());
+
+		// The right eye is to the right on screen
+		let offset = right.position() - camera.position();
+		let view = camera.view * offset.extend(0.0);
+		assert!(view.x > 0.0, "right eye at {:?} in view space", view);
+	}
+
+	#[test]
+	fn set_fov_clamps_and_keeps_projection_kind() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		camera.set_fov(PI / 2.0);
+		assert_eq!(camera.fov(), PI / 2.0);
+		camera.set_fov(0.0);
+		assert!(camera.fov() > 0.0);
+		camera.set_fov(PI);
+		assert!(camera.fov() < PI);
+
+		camera.toggle_projection();
+		camera.set_fov(PI / 3.0);
+		assert!((camera.fov() - PI / 3.0).abs() < 1e-5);
+		match camera.projection_kind {
+			ProjectionKind::Orthographic { .. } => {},
+			kind => panic!("projection changed to {:?}", kind),
+		}
+	}
+
+	#[test]
+	fn changing_far_rebuilds_projection() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		let before = camera.projection;
+
+		// A point 500 units ahead is inside the default far plane, and clipped
+		// once the far plane's pulled in past it
+		let point = Vector4::new(0.0, 0.0, -500.0, 1.0);
+		let clip = camera.projection * point;
+		assert!(clip.z / clip.w < 1.0);
+		camera.set_clip_planes(0.1, 200.0).unwrap();
+		assert!(camera.projection != before);
+		let clip = camera.projection * point;
+		assert!(clip.z / clip.w > 1.0);
+		assert_eq!(camera.clip_planes(), (0.1, 200.0));
+
+		// Invalid planes are rejected without touching the projection
+		let projection = camera.projection;
+		assert_eq!(camera.set_clip_planes(0.0, 100.0), Err(ClipPlaneError::Near(0.0)));
+		assert_eq!(camera.set_clip_planes(10.0, 5.0),
+			Err(ClipPlaneError::Far { near: 10.0, far: 5.0 }));
+		assert_eq!(camera.set_clip_planes(0.1, f32::INFINITY),
+			Err(ClipPlaneError::Far { near: 0.1, far: f32::INFINITY }));
+		assert!(camera.set_clip_planes(f32::NAN, 100.0).is_err());
+		assert!(camera.set_clip_planes(0.1, f32::NAN).is_err());
+		assert_eq!(camera.projection, projection);
+		assert_eq!(camera.clip_planes(), (0.1, 200.0));
+	}
+}
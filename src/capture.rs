@@ -0,0 +1,368 @@
+
+//
+//  Screen Capture
+//
+
+use gl;
+use gl::types::*;
+use png;
+
+use std::f32;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// The number of channels in a pixel read back from a framebuffer.
+const CHANNELS: usize = 4;
+
+/// The largest factor a supersampled screenshot can be rendered at.
+pub const MAX_SUPERSAMPLE: u32 = 4;
+
+/// The most pixels a supersampled screenshot can be rendered with, the same as
+/// a 1080p window at 4x. The HDR framebuffer alone needs 8 bytes for each, and
+/// as much again for each MSAA sample, and the memory used grows with the
+/// square of the factor.
+const MAX_SUPERSAMPLE_PIXELS: u64 = 7680 * 4320;
+
+
+/// Reads the contents of framebuffers back into memory, keeping its buffers
+/// between reads so repeated captures, like a turntable's frames, don't
+/// allocate each time.
+///
+/// Pixels are returned tightly packed, with the first row at the top of the
+/// image.
+pub struct Readback {
+	/// The width of the framebuffer read, in pixels.
+	width: u32,
+	/// The height of the framebuffer read, in pixels.
+	height: u32,
+	/// The buffer RGBA8 pixels are read into.
+	rgba8: Vec<u8>,
+	/// The buffer RGBA16F pixels are read into, as the bits of each half float.
+	rgba16f: Vec<u16>,
+}
+
+impl Readback {
+	/// Creates a readback for a framebuffer with the given dimensions, in
+	/// pixels. Nothing's allocated until the first read.
+	pub fn new(width: u32, height: u32) -> Readback {
+		Readback {
+			width: width,
+			height: height,
+			rgba8: Vec::new(),
+			rgba16f: Vec::new(),
+		}
+	}
+
+	/// Changes the size of the framebuffer read, in pixels, such as when the
+	/// window's resized. The buffers keep their memory, and grow on the next
+	/// read if they need to.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.width = width;
+		self.height = height;
+	}
+
+	/// Reads the framebuffer bound for reading as RGBA8 pixels.
+	///
+	/// Must be called before the buffers are swapped, since the back buffer's
+	/// contents are undefined afterwards.
+	pub fn read_rgba8(&mut self) -> &[u8] {
+		let (width, height) = (self.width, self.height);
+		read_pixels(width, height, gl::UNSIGNED_BYTE, &mut self.rgba8);
+		&self.rgba8
+	}
+
+	/// Reads the framebuffer bound for reading as RGBA16F pixels, so HDR
+	/// values aren't clamped. Each value's the bits of a half float.
+	pub fn read_rgba16f(&mut self) -> &[u16] {
+		let (width, height) = (self.width, self.height);
+		read_pixels(width, height, gl::HALF_FLOAT, &mut self.rgba16f);
+		&self.rgba16f
+	}
+}
+
+/// Reads the whole of the framebuffer bound for reading into a buffer, sizing
+/// it to fit, with `kind` the OpenGL type of each channel's value in `T`.
+fn read_pixels<T: Copy + Default>(width: u32, height: u32, kind: GLenum,
+		pixels: &mut Vec<T>) {
+	let row_size = width as usize * CHANNELS;
+	pixels.resize(row_size * height as usize, T::default());
+	unsafe {
+		// Rows are tightly packed, regardless of the width
+		gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+		gl::ReadPixels(0, 0, width as GLsizei, height as GLsizei, gl::RGBA, kind,
+			pixels.as_mut_ptr() as *mut GLvoid);
+	}
+
+	// OpenGL's origin is at the bottom left, but images start at the top
+	flip_rows(pixels, row_size);
+}
+
+/// Reads a single pixel of the framebuffer bound for reading, as floats so
+/// HDR values aren't clamped. The pixel's given with OpenGL's origin, at the
+/// bottom left.
+pub fn read_pixel(x: u32, y: u32) -> [f32; 3] {
+	let mut pixel = [0.0; 3];
+	unsafe {
+		gl::ReadPixels(x as GLint, y as GLint, 1, 1, gl::RGB, gl::FLOAT,
+			pixel.as_mut_ptr() as *mut GLvoid);
+	}
+	pixel
+}
+
+/// Returns the pixel of a `width` by `height` framebuffer under a point in the
+/// window, given in points from the top left, with `scale_factor` pixels per
+/// point. The pixel's given with OpenGL's origin at the bottom left, and kept
+/// inside the framebuffer. Returns None if the framebuffer's empty, like when
+/// the window's minimised.
+pub fn pixel_at(point: (f32, f32), scale_factor: f32, width: u32, height: u32)
+		-> Option<(u32, u32)> {
+	if width == 0 || height == 0 {
+		return None;
+	}
+	let x = (point.0 * scale_factor).max(0.0) as u32;
+	let y = (point.1 * scale_factor).max(0.0) as u32;
+	let x = x.min(width - 1);
+	let y = y.min(height - 1);
+	Some((x, height - 1 - y))
+}
+
+/// Reverses the order of the rows in an image, with `row_size` values in each
+/// row.
+fn flip_rows<T>(pixels: &mut [T], row_size: usize) {
+	let height = pixels.len() / row_size;
+	for y in 0 .. height / 2 {
+		let (top, bottom) = pixels.split_at_mut((height - y - 1) * row_size);
+		top[y * row_size .. (y + 1) * row_size]
+			.swap_with_slice(&mut bottom[.. row_size]);
+	}
+}
+
+/// Returns the factor to supersample a screenshot of a window at, given the
+/// factor asked for. It's reduced until the enlarged framebuffer is no bigger
+/// than `max_size` pixels along either side and `MAX_SUPERSAMPLE_PIXELS`
+/// overall, and is never more than `MAX_SUPERSAMPLE`.
+pub fn supersample_scale(requested: u32, width: u32, height: u32, max_size: u32)
+		-> u32 {
+	let mut scale = requested.min(MAX_SUPERSAMPLE);
+	while scale > 1 {
+		let (scaled_width, scaled_height) = (width as u64 * scale as u64,
+			height as u64 * scale as u64);
+		if scaled_width <= max_size as u64 && scaled_height <= max_size as u64 &&
+				scaled_width * scaled_height <= MAX_SUPERSAMPLE_PIXELS {
+			break;
+		}
+		scale -= 1;
+	}
+	scale.max(1)
+}
+
+/// Returns the largest width or height the driver supports for the textures
+/// and renderbuffers making up a framebuffer.
+pub fn max_framebuffer_size() -> u32 {
+	let mut texture_size = 0;
+	let mut renderbuffer_size = 0;
+	unsafe {
+		gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut texture_size);
+		gl::GetIntegerv(gl::MAX_RENDERBUFFER_SIZE, &mut renderbuffer_size);
+	}
+	texture_size.min(renderbuffer_size).max(0) as u32
+}
+
+/// Shrinks RGBA8 pixels rendered at `scale` times the given dimensions down to
+/// those dimensions, averaging each `scale` by `scale` block of pixels into
+/// one. The pixels are tonemapped already, so they're averaged as they are.
+pub fn downsample(pixels: &[u8], width: u32, height: u32, scale: u32) -> Vec<u8> {
+	let (width, height, scale) = (width as usize, height as usize, scale as usize);
+	let source_row = width * scale * CHANNELS;
+	assert_eq!(pixels.len(), source_row * height * scale,
+		"wrong number of pixels to downsample to {}x{}", width, height);
+
+	let block = (scale * scale) as u32;
+	let mut result = Vec::with_capacity(width * height * CHANNELS);
+	for y in 0 .. height {
+		for x in 0 .. width {
+			let mut sum = [0u32; CHANNELS];
+			for row in y * scale .. (y + 1) * scale {
+				let start = row * source_row + x * scale * CHANNELS;
+				let texels = &pixels[start .. start + scale * CHANNELS];
+				for (i, &value) in texels.iter().enumerate() {
+					sum[i % CHANNELS] += value as u32;
+				}
+			}
+
+			// Round to the nearest value, rather than down
+			result.extend(sum.iter().map(|&total| ((total + block / 2) / block) as u8));
+		}
+	}
+	result
+}
+
+/// Saves RGBA8 pixels to a PNG file.
+pub fn save_png<P: AsRef<Path>>(path: P, width: u32, height: u32, rgba: &[u8])
+		-> Result<(), png::EncodingError> {
+	let file = File::create(path)?;
+	let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(rgba)?;
+	writer.finish()
+}
+
+/// Saves RGBA16F pixels, given as the bits of each half float, to a PFM file,
+/// keeping their HDR values. The alpha channel's dropped.
+pub fn save_pfm<P: AsRef<Path>>(path: P, width: u32, height: u32, rgba16f: &[u16])
+		-> io::Result<()> {
+	let mut writer = BufWriter::new(File::create(path)?);
+	write_pfm(&mut writer, width, height, rgba16f)?;
+	writer.flush()
+}
+
+/// Writes RGBA16F pixels in the PFM format: a short text header, then each
+/// pixel's RGB as little endian 32 bit floats, with the bottom row first.
+fn write_pfm<W: Write>(writer: &mut W, width: u32, height: u32, rgba16f: &[u16])
+		-> io::Result<()> {
+	// A negative scale marks the floats as little endian
+	write!(writer, "PF\n{} {}\n-1.0\n", width, height)?;
+	let row_size = width as usize * CHANNELS;
+	for row in rgba16f.chunks(row_size).rev() {
+		for pixel in row.chunks(CHANNELS) {
+			for &bits in &pixel[.. 3] {
+				writer.write_all(&half_to_f32(bits).to_bits().to_le_bytes())?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Converts the bits of a half float to a float.
+fn half_to_f32(bits: u16) -> f32 {
+	let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+	let exponent = ((bits >> 10) & 0x1f) as i32;
+	let mantissa = bits & 0x3ff;
+	let magnitude = match exponent {
+		// Subnormals have no implicit leading 1
+		0 => mantissa as f32 * 2.0f32.powi(-24),
+		0x1f if mantissa == 0 => f32::INFINITY,
+		0x1f => f32::NAN,
+		_ => (1.0 + mantissa as f32 / 1024.0) * 2.0f32.powi(exponent - 15),
+	};
+	sign * magnitude
+}
+
+/// Returns a timestamped path for a new screenshot, with the given extension,
+/// that won't overwrite any existing file.
+pub fn screenshot_path(extension: &str) -> PathBuf {
+	let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or(0);
+
+	// Add a counter if multiple screenshots are taken in the same second
+	let mut path = PathBuf::from(format!("screenshot-{}.{}", timestamp, extension));
+	let mut counter = 1;
+	while path.exists() {
+		path = PathBuf::from(format!("screenshot-{}-{}.{}", timestamp, counter,
+			extension));
+		counter += 1;
+	}
+	path
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{downsample, flip_rows, half_to_f32, pixel_at, supersample_scale,
+		write_pfm, MAX_SUPERSAMPLE};
+
+	#[test]
+	fn downsample_averages_blocks() {
+		// A 4x2 image shrunk to 2x1, with each 2x2 block a different mix
+		let pixels = [
+			0, 0, 0, 255,   255, 255, 255, 255,   10, 20, 30, 255,   10, 20, 30, 255,
+			255, 0, 0, 255,   0, 255, 0, 255,   10, 20, 30, 255,   10, 20, 30, 255,
+		];
+		assert_eq!(downsample(&pixels, 2, 1, 2), vec![
+			128, 128, 64, 255,   10, 20, 30, 255,
+		]);
+
+		// A factor of 1 leaves the image as it is
+		assert_eq!(downsample(&pixels, 4, 2, 1), pixels.to_vec());
+	}
+
+	#[test]
+	fn flip_rows_reverses_rows() {
+		// Three rows of two pixels, so the middle row stays put
+		let mut pixels: Vec<u8> = (0 .. 24).collect();
+		flip_rows(&mut pixels, 8);
+		let expected: Vec<u8> = (16 .. 24).chain(8 .. 16).chain(0 .. 8).collect();
+		assert_eq!(pixels, expected);
+
+		// Half floats are flipped a row at a time, not a byte at a time
+		let mut halves = [1u16, 2, 3, 4, 5, 6, 7, 8];
+		flip_rows(&mut halves, 4);
+		assert_eq!(halves, [5, 6, 7, 8, 1, 2, 3, 4]);
+
+		// A single row is left as it is
+		let mut row = [9u8, 8, 7, 6];
+		flip_rows(&mut row, 4);
+		assert_eq!(row, [9, 8, 7, 6]);
+	}
+
+	#[test]
+	fn half_floats_are_converted() {
+		assert_eq!(half_to_f32(0x0000), 0.0);
+		assert_eq!(half_to_f32(0x3c00), 1.0);
+		assert_eq!(half_to_f32(0xc000), -2.0);
+		assert_eq!(half_to_f32(0x3555), 0.333_251_95);
+		assert_eq!(half_to_f32(0x7bff), 65504.0);
+		assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+		assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+		assert!(half_to_f32(0x7e00).is_nan());
+	}
+
+	#[test]
+	fn pfm_starts_from_the_bottom_row() {
+		// A 1x2 image, top row 1.0 and bottom row 2.0, with alpha dropped
+		let pixels = [0x3c00, 0x3c00, 0x3c00, 0x3c00, 0x4000, 0x4000, 0x4000, 0x3c00];
+		let mut pfm = Vec::new();
+		write_pfm(&mut pfm, 1, 2, &pixels).unwrap();
+
+		let header = b"PF\n1 2\n-1.0\n";
+		assert_eq!(&pfm[.. header.len()], &header[..]);
+		let values: Vec<f32> = pfm[header.len() ..].chunks(4)
+			.map(|bytes| f32::from_bits(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])))
+			.collect();
+		assert_eq!(values, vec![2.0, 2.0, 2.0, 1.0, 1.0, 1.0]);
+	}
+
+	#[test]
+	fn pixel_at_flips_origin() {
+		// The top left point is in the top row, which OpenGL puts last
+		assert_eq!(pixel_at((0.0, 0.0), 1.0, 800, 600), Some((0, 599)));
+		assert_eq!(pixel_at((400.0, 300.0), 1.0, 800, 600), Some((400, 299)));
+		assert_eq!(pixel_at((400.0, 300.0), 2.0, 1600, 1200), Some((800, 599)));
+
+		// Points outside the window are kept inside the framebuffer
+		assert_eq!(pixel_at((-5.0, 900.0), 1.0, 800, 600), Some((0, 0)));
+		assert_eq!(pixel_at((1000.0, -5.0), 1.0, 800, 600), Some((799, 599)));
+
+		// A minimised window has no pixels at all
+		assert_eq!(pixel_at((0.0, 0.0), 1.0, 0, 0), None);
+		assert_eq!(pixel_at((0.0, 0.0), 1.0, 800, 0), None);
+	}
+
+	#[test]
+	fn supersample_scale_is_bounded() {
+		assert_eq!(supersample_scale(2, 1280, 720, 16384), 2);
+		assert_eq!(supersample_scale(64, 1280, 720, 16384), MAX_SUPERSAMPLE);
+		assert_eq!(supersample_scale(4, 1920, 1080, 16384), 4);
+		assert_eq!(supersample_scale(4, 1920, 1080, 4096), 2);
+		assert_eq!(supersample_scale(4, 3840, 2160, 16384), 2);
+		assert_eq!(supersample_scale(4, 8192, 4096, 4096), 1);
+		assert_eq!(supersample_scale(0, 1280, 720, 16384), 1);
+	}
+}
@@ -0,0 +1,679 @@
+
+//
+//  Configuration
+//
+
+use camera::{self, ClipPlaneError};
+use sky;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+
+/// The default atmospheric turbidity.
+const DEFAULT_TURBIDITY: f32 = 4.0;
+
+/// The default ground albedo, for each colour channel.
+const DEFAULT_ALBEDO: [f32; 3] = [0.1, 0.1, 0.1];
+
+/// The default angular radius of the sun's disk, in degrees.
+const DEFAULT_SUN_RADIUS: f32 = 0.2666;
+
+/// The default vertical field of view, in degrees.
+const DEFAULT_FOV: f32 = 70.0;
+
+/// The default walking speed, in units per second.
+const DEFAULT_MOVE_SPEED: f32 = 6.0;
+
+/// The default speed at which the player can look around, in radians per
+/// pixel of mouse movement.
+const DEFAULT_LOOK_SPEED: f32 = 0.0015;
+
+/// The default longest frame delta used for camera movement, in seconds.
+const DEFAULT_MAX_DELTA: f32 = 0.1;
+
+/// The default limits on how far below and above the horizon the camera can
+/// look, in degrees.
+const DEFAULT_MIN_PITCH: f32 = -90.0;
+const DEFAULT_MAX_PITCH: f32 = 90.0;
+
+/// The default distance between the eyes in the stereo view, in units. About
+/// the distance between a person's eyes, with one unit to a metre.
+const DEFAULT_EYE_SEPARATION: f32 = 0.065;
+
+/// The default distance from the camera to the near plane, in units.
+const DEFAULT_NEAR: f32 = 0.1;
+
+/// The default distance from the camera to the far plane, in units.
+const DEFAULT_FAR: f32 = 1000.0;
+
+/// The default density of the fog over the ground, per unit.
+const DEFAULT_FOG_DENSITY: f32 = 0.01;
+
+/// The default distance between the lines of the ground grid, in units.
+const DEFAULT_GRID_SPACING: f32 = 10.0;
+
+/// The default size of a gamepad stick's deadzone, as a fraction of its full
+/// range.
+const DEFAULT_GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// The default average luminance the automatic exposure maps the image to.
+/// Chosen so the midday sky is exposed about the same as it is with a fixed
+/// exposure.
+const DEFAULT_KEY_VALUE: f32 = 0.8;
+
+/// The default rate at which the automatic exposure adapts to changes in
+/// brightness, per second.
+const DEFAULT_ADAPTATION_SPEED: f32 = 1.5;
+
+/// The default window width, in points.
+const DEFAULT_WIDTH: u32 = 900;
+
+/// The default window height, in points.
+const DEFAULT_HEIGHT: u32 = 620;
+
+/// The default colour behind the sky, as linear radiance for each channel.
+const DEFAULT_CLEAR_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+
+
+/// Tunable settings for the sky and renderer, loaded at startup so they can be
+/// changed without rebuilding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+	/// The atmospheric turbidity, between 1 and 10.
+	pub turbidity: f32,
+	/// The ground albedo for each colour channel, between 0 and 1.
+	pub albedo: [f32; 3],
+	/// The angular radius of the sun's disk, in degrees.
+	pub sun_radius: f32,
+	/// True if the sky's radiance is given in absolute units, rather than
+	/// normalised for display.
+	pub absolute_radiance: bool,
+	/// How far below the horizon the sky's gradient is extended, in degrees.
+	pub atmosphere_thickness: f32,
+	/// How much of the sun's disk is blocked, from 0 for none to 1 for all of
+	/// it.
+	pub sun_occlusion: f32,
+	/// True if the sky dims along with the sun as it's occluded.
+	pub occlusion_dims_sky: bool,
+	/// True if the turbidity's snapped to the nearest level in the datasets,
+	/// rather than interpolated. Only for debugging.
+	pub nearest_turbidity: bool,
+	/// The camera's vertical field of view, in degrees.
+	pub fov: f32,
+	/// The speed at which the player can walk, in units per second.
+	pub move_speed: f32,
+	/// The speed at which the player can look around, in radians per pixel of
+	/// mouse movement.
+	pub look_speed: f32,
+	/// The longest time between frames used for camera movement, in seconds.
+	/// Longer frames are clamped so a hitch doesn't jump the view.
+	pub max_delta: f32,
+	/// A multiplier applied to mouse movement.
+	pub sensitivity: f32,
+	/// True if moving the mouse up should look down.
+	pub invert_y: bool,
+	/// True if the camera's pitch is limited to between `min_pitch` and
+	/// `max_pitch`, rather than turning freely over the top.
+	pub limit_pitch: bool,
+	/// The lowest the camera can look below the horizon, in degrees.
+	pub min_pitch: f32,
+	/// The highest the camera can look above the horizon, in degrees.
+	pub max_pitch: f32,
+	/// True if positions and directions are given in left-handed coordinates,
+	/// rather than right-handed.
+	pub left_handed: bool,
+	/// The distance between the eyes in the stereo view, in units.
+	pub eye_separation: f32,
+	/// The distance from the camera to the near plane, in units.
+	pub near: f32,
+	/// The distance from the camera to the far plane, in units.
+	pub far: f32,
+	/// True if the ground's depth is stored logarithmically, keeping its
+	/// precision over huge view distances.
+	pub log_depth: bool,
+	/// How quickly the ground fades into the sky with distance, per unit.
+	pub fog_density: f32,
+	/// The distance between the lines of the grid drawn over the ground.
+	pub grid_spacing: f32,
+	/// How far a gamepad stick has to be pushed before it registers, as a
+	/// fraction of its full range.
+	pub gamepad_deadzone: f32,
+	/// True if pushing the gamepad's right stick up should look down.
+	pub gamepad_invert_y: bool,
+	/// The average luminance the automatic exposure maps the image to.
+	pub key_value: f32,
+	/// How quickly the automatic exposure adapts to changes in brightness, per
+	/// second. Higher values adapt faster.
+	pub adaptation_speed: f32,
+	/// The initial width of the window, in points.
+	pub width: u32,
+	/// The initial height of the window, in points.
+	pub height: u32,
+	/// True if the window should start with vsync enabled.
+	pub vsync: bool,
+	/// The colour the screen's cleared to before drawing, as linear radiance
+	/// for each channel. Only visible where nothing's drawn over it.
+	pub clear_color: [f32; 3],
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			turbidity: DEFAULT_TURBIDITY,
+			albedo: DEFAULT_ALBEDO,
+			sun_radius: DEFAULT_SUN_RADIUS,
+			absolute_radiance: false,
+			atmosphere_thickness: 0.0,
+			sun_occlusion: 0.0,
+			occlusion_dims_sky: false,
+			nearest_turbidity: false,
+			fov: DEFAULT_FOV,
+			move_speed: DEFAULT_MOVE_SPEED,
+			look_speed: DEFAULT_LOOK_SPEED,
+			max_delta: DEFAULT_MAX_DELTA,
+			sensitivity: 1.0,
+			invert_y: false,
+			limit_pitch: true,
+			min_pitch: DEFAULT_MIN_PITCH,
+			max_pitch: DEFAULT_MAX_PITCH,
+			left_handed: false,
+			eye_separation: DEFAULT_EYE_SEPARATION,
+			near: DEFAULT_NEAR,
+			far: DEFAULT_FAR,
+			log_depth: false,
+			fog_density: DEFAULT_FOG_DENSITY,
+			grid_spacing: DEFAULT_GRID_SPACING,
+			gamepad_deadzone: DEFAULT_GAMEPAD_DEADZONE,
+			gamepad_invert_y: false,
+			key_value: DEFAULT_KEY_VALUE,
+			adaptation_speed: DEFAULT_ADAPTATION_SPEED,
+			width: DEFAULT_WIDTH,
+			height: DEFAULT_HEIGHT,
+			vsync: true,
+			clear_color: DEFAULT_CLEAR_COLOR,
+		}
+	}
+}
+
+impl Config {
+	/// Loads a config file. Any settings missing from the file keep their
+	/// default values.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+		let mut source = String::new();
+		File::open(path)
+			.and_then(|mut file| file.read_to_string(&mut source))
+			.map_err(ConfigError::Io)?;
+		Config::parse(&source)
+	}
+
+	/// Parses a config from a small subset of TOML: `[section]` headers, and
+	/// `key = value` pairs where the value is a number, boolean, or array of
+	/// numbers. Comments start with `#`.
+	pub fn parse(source: &str) -> Result<Config, ConfigError> {
+		let mut config = Config::default();
+		let mut section = String::new();
+
+		for (index, line) in source.lines().enumerate() {
+			let line_number = index + 1;
+			let syntax_error = |message: &str| ConfigError::Syntax {
+				line: line_number,
+				message: message.to_string(),
+			};
+
+			// Strip comments and whitespace
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			// Section header
+			if line.starts_with('[') {
+				if !line.ends_with(']') {
+					return Err(syntax_error("unterminated section header"));
+				}
+				section = line[1 .. line.len() - 1].trim().to_string();
+				continue;
+			}
+
+			// Key value pair
+			let mut parts = line.splitn(2, '=');
+			let key = parts.next().unwrap().trim();
+			let value = match parts.next() {
+				Some(value) => parse_value(value.trim()).map_err(|message|
+					syntax_error(&message))?,
+				None => return Err(syntax_error("expected `key = value`")),
+			};
+			if key.is_empty() {
+				return Err(syntax_error("missing key"));
+			}
+
+			let name = if section.is_empty() {
+				key.to_string()
+			} else {
+				format!("{}.{}", section, key)
+			};
+			config.set(&name, value)?;
+		}
+
+		// The limits can be given in either order, so they're only checked
+		// against each other once both are known
+		if config.min_pitch >= config.max_pitch {
+			return Err(ConfigError::Invalid {
+				key: "camera.min_pitch".to_string(),
+				message: "must be less than `camera.max_pitch`".to_string(),
+			});
+		}
+		match camera::check_clip_planes(config.near, config.far) {
+			Ok(()) => {},
+			Err(ClipPlaneError::Near(_)) => return Err(ConfigError::Invalid {
+				key: "camera.near".to_string(),
+				message: "must be positive".to_string(),
+			}),
+			Err(ClipPlaneError::Far { .. }) => return Err(ConfigError::Invalid {
+				key: "camera.far".to_string(),
+				message: "must be greater than `camera.near`".to_string(),
+			}),
+		}
+
+		Ok(config)
+	}
+
+	/// Sets a single setting from its value in the file, checking it's within
+	/// the allowed range.
+	fn set(&mut self, name: &str, value: Value) -> Result<(), ConfigError> {
+		let invalid = |message: &str| ConfigError::Invalid {
+			key: name.to_string(),
+			message: message.to_string(),
+		};
+
+		match name {
+			"sky.turbidity" => {
+				self.turbidity = value.number(name)?;
+				check_turbidity(self.turbidity).map_err(invalid)?;
+			},
+			"sky.albedo" => {
+				// Either a single grey value, or one for each channel
+				self.albedo = match value {
+					Value::Number(albedo) => [albedo; 3],
+					Value::Array(ref values) if values.len() == 3 =>
+						[values[0], values[1], values[2]],
+					_ => return Err(invalid("must be a number or an array of 3 numbers")),
+				};
+				for &albedo in &self.albedo {
+					check_albedo(albedo).map_err(invalid)?;
+				}
+			},
+			"sky.sun_radius" => {
+				self.sun_radius = value.number(name)?;
+				if self.sun_radius <= 0.0 || self.sun_radius >= 90.0 {
+					return Err(invalid("must be between 0 and 90 degrees"));
+				}
+			},
+			"sky.absolute_radiance" => {
+				self.absolute_radiance = match value {
+					Value::Bool(absolute_radiance) => absolute_radiance,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"sky.atmosphere_thickness" => {
+				self.atmosphere_thickness = value.number(name)?;
+				if self.atmosphere_thickness < 0.0 || self.atmosphere_thickness > 10.0 {
+					return Err(invalid("must be between 0 and 10 degrees"));
+				}
+			},
+			"sky.sun_occlusion" => {
+				self.sun_occlusion = value.number(name)?;
+				if self.sun_occlusion < 0.0 || self.sun_occlusion > 1.0 {
+					return Err(invalid("must be between 0 and 1"));
+				}
+			},
+			"sky.occlusion_dims_sky" => {
+				self.occlusion_dims_sky = match value {
+					Value::Bool(occlusion_dims_sky) => occlusion_dims_sky,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"sky.nearest_turbidity" => {
+				self.nearest_turbidity = match value {
+					Value::Bool(nearest_turbidity) => nearest_turbidity,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"camera.fov" => {
+				self.fov = value.number(name)?;
+				if self.fov <= 0.0 || self.fov >= 180.0 {
+					return Err(invalid("must be between 0 and 180 degrees"));
+				}
+			},
+			"camera.move_speed" => {
+				self.move_speed = value.number(name)?;
+				if self.move_speed <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"camera.look_speed" => {
+				self.look_speed = value.number(name)?;
+				if self.look_speed <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"camera.max_delta" => {
+				self.max_delta = value.number(name)?;
+				if self.max_delta <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"camera.limit_pitch" => {
+				self.limit_pitch = match value {
+					Value::Bool(limit_pitch) => limit_pitch,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"camera.min_pitch" => {
+				self.min_pitch = value.number(name)?;
+				if self.min_pitch < -90.0 || self.min_pitch > 90.0 {
+					return Err(invalid("must be between -90 and 90 degrees"));
+				}
+			},
+			"camera.max_pitch" => {
+				self.max_pitch = value.number(name)?;
+				if self.max_pitch < -90.0 || self.max_pitch > 90.0 {
+					return Err(invalid("must be between -90 and 90 degrees"));
+				}
+			},
+			"camera.left_handed" => {
+				self.left_handed = match value {
+					Value::Bool(left_handed) => left_handed,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"camera.eye_separation" => {
+				self.eye_separation = value.number(name)?;
+				if self.eye_separation < 0.0 {
+					return Err(invalid("must not be negative"));
+				}
+			},
+			"camera.near" => self.near = value.number(name)?,
+			"camera.far" => self.far = value.number(name)?,
+			"camera.log_depth" => {
+				self.log_depth = match value {
+					Value::Bool(log_depth) => log_depth,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"input.sensitivity" => {
+				self.sensitivity = value.number(name)?;
+				if self.sensitivity <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"input.invert_y" => {
+				self.invert_y = match value {
+					Value::Bool(invert_y) => invert_y,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"ground.fog_density" => {
+				self.fog_density = value.number(name)?;
+				if self.fog_density < 0.0 {
+					return Err(invalid("must not be negative"));
+				}
+			},
+			"ground.grid_spacing" => {
+				self.grid_spacing = value.number(name)?;
+				if self.grid_spacing <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"gamepad.deadzone" => {
+				self.gamepad_deadzone = value.number(name)?;
+				if self.gamepad_deadzone < 0.0 || self.gamepad_deadzone >= 1.0 {
+					return Err(invalid("must be at least 0 and less than 1"));
+				}
+			},
+			"gamepad.invert_y" => {
+				self.gamepad_invert_y = match value {
+					Value::Bool(invert_y) => invert_y,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"exposure.key_value" => {
+				self.key_value = value.number(name)?;
+				if self.key_value <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"exposure.adaptation_speed" => {
+				self.adaptation_speed = value.number(name)?;
+				if self.adaptation_speed <= 0.0 {
+					return Err(invalid("must be positive"));
+				}
+			},
+			"window.width" => self.width = value.size(name)?,
+			"window.height" => self.height = value.size(name)?,
+			"window.vsync" => {
+				self.vsync = match value {
+					Value::Bool(vsync) => vsync,
+					_ => return Err(invalid("must be true or false")),
+				};
+			},
+			"window.clear_color" => {
+				self.clear_color = match value {
+					Value::Number(value) => [value; 3],
+					Value::Array(ref values) if values.len() == 3 =>
+						[values[0], values[1], values[2]],
+					_ => return Err(invalid("must be a number or an array of 3 numbers")),
+				};
+				if self.clear_color.iter().any(|&value| value < 0.0) {
+					return Err(invalid("must not be negative"));
+				}
+			},
+			_ => return Err(invalid("is not a known setting")),
+		}
+		Ok(())
+	}
+}
+
+
+/// A value in a config file.
+enum Value {
+	Number(f32),
+	Bool(bool),
+	Array(Vec<f32>),
+}
+
+impl Value {
+	/// Returns the value as a number.
+	fn number(&self, name: &str) -> Result<f32, ConfigError> {
+		match *self {
+			Value::Number(value) => Ok(value),
+			_ => Err(ConfigError::Invalid {
+				key: name.to_string(),
+				message: "must be a number".to_string(),
+			}),
+		}
+	}
+
+	/// Returns the value as a positive whole number.
+	fn size(&self, name: &str) -> Result<u32, ConfigError> {
+		match *self {
+			Value::Number(value) if value >= 1.0 && value.fract() == 0.0 =>
+				Ok(value as u32),
+			_ => Err(ConfigError::Invalid {
+				key: name.to_string(),
+				message: "must be a positive whole number".to_string(),
+			}),
+		}
+	}
+}
+
+/// Checks a turbidity is within the range the datasets cover, returning what's
+/// wrong with it if not. Shared with the command line's `--turbidity`.
+pub fn check_turbidity(turbidity: f32) -> Result<(), &'static str> {
+	if turbidity >= sky::MIN_TURBIDITY && turbidity <= sky::MAX_TURBIDITY {
+		Ok(())
+	} else {
+		Err("must be between 1 and 10")
+	}
+}
+
+/// Checks an albedo is between 0 and 1, returning what's wrong with it if not.
+/// Shared with the command line's `--albedo`.
+pub fn check_albedo(albedo: f32) -> Result<(), &'static str> {
+	if albedo >= 0.0 && albedo <= 1.0 {
+		Ok(())
+	} else {
+		Err("must be between 0 and 1")
+	}
+}
+
+/// Parses a value from the right hand side of a `key = value` pair.
+fn parse_value(source: &str) -> Result<Value, String> {
+	match source {
+		"true" => return Ok(Value::Bool(true)),
+		"false" => return Ok(Value::Bool(false)),
+		_ => {},
+	}
+
+	if source.starts_with('[') {
+		if !source.ends_with(']') {
+			return Err("unterminated array".to_string());
+		}
+		let values = source[1 .. source.len() - 1].split(',')
+			.map(|value| value.trim())
+			.filter(|value| !value.is_empty())
+			.map(parse_number)
+			.collect::<Result<Vec<_>, _>>()?;
+		return Ok(Value::Array(values));
+	}
+
+	parse_number(source).map(Value::Number)
+}
+
+/// Parses a single number.
+fn parse_number(source: &str) -> Result<f32, String> {
+	source.parse::<f32>()
+		.ok()
+		.filter(|value| value.is_finite())
+		.ok_or_else(|| format!("`{}` is not a number", source))
+}
+
+
+/// An error encountered while loading a config file.
+#[derive(Debug)]
+pub enum ConfigError {
+	/// The file couldn't be read.
+	Io(io::Error),
+	/// A line in the file couldn't be parsed.
+	Syntax { line: usize, message: String },
+	/// A setting has a value that isn't allowed, or doesn't exist.
+	Invalid { key: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ConfigError::Io(ref err) => write!(f, "{}", err),
+			ConfigError::Syntax { line, ref message } =>
+				write!(f, "line {}: {}", line, message),
+			ConfigError::Invalid { ref key, ref message } =>
+				write!(f, "`{}` {}", key, message),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, ConfigError};
+
+	/// Parses a config, returning the error message if it's rejected.
+	fn error(source: &str) -> String {
+		match Config::parse(source) {
+			Ok(_) => panic!("`{}` was accepted", source),
+			Err(err) => err.to_string(),
+		}
+	}
+
+	#[test]
+	fn shipped_config_parses() {
+		assert!(Config::parse(include_str!("../sky.toml")).is_ok());
+	}
+
+	#[test]
+	fn values_are_parsed() {
+		let config = Config::parse("
+			# Comments and blank lines are skipped
+			[sky]
+			turbidity = 2.5  # Trailing comments too
+			albedo = [0.1, 0.2, 0.3]
+			absolute_radiance = true
+
+			[ window ]
+			width = 1280
+			clear_color = 0.5
+		").unwrap();
+		assert_eq!(config.turbidity, 2.5);
+		assert_eq!(config.albedo, [0.1, 0.2, 0.3]);
+		assert!(config.absolute_radiance);
+		assert_eq!(config.width, 1280);
+		assert_eq!(config.clear_color, [0.5; 3]);
+		assert_eq!(config.height, Config::default().height);
+		assert_eq!(Config::parse("").unwrap(), Config::default());
+	}
+
+	#[test]
+	fn syntax_errors_give_the_line() {
+		assert_eq!(error("[sky]\n[camera"), "line 2: unterminated section header");
+		assert_eq!(error("\n\nturbidity"), "line 3: expected `key = value`");
+		assert_eq!(error(" = 1"), "line 1: missing key");
+		assert_eq!(error("[sky]\nturbidity = four"), "line 2: `four` is not a number");
+		assert_eq!(error("[sky]\nturbidity = inf"), "line 2: `inf` is not a number");
+		assert_eq!(error("[sky]\nalbedo = [0.1, 0.2"), "line 2: unterminated array");
+	}
+
+	#[test]
+	fn unknown_keys_are_rejected() {
+		assert_eq!(error("[sky]\nturbidty = 3"), "`sky.turbidty` is not a known setting");
+		assert_eq!(error("turbidity = 3"), "`turbidity` is not a known setting");
+		match Config::parse("[camera]\nspeed = 3") {
+			Err(ConfigError::Invalid { ref key, .. }) => assert_eq!(key, "camera.speed"),
+			other => panic!("unexpected result {:?}", other),
+		}
+	}
+
+	#[test]
+	fn wrong_types_are_rejected() {
+		assert_eq!(error("[sky]\nturbidity = true"), "`sky.turbidity` must be a number");
+		assert_eq!(error("[sky]\nabsolute_radiance = 1"),
+			"`sky.absolute_radiance` must be true or false");
+		assert_eq!(error("[sky]\nalbedo = [0.1, 0.2]"),
+			"`sky.albedo` must be a number or an array of 3 numbers");
+		assert_eq!(error("[window]\nwidth = 12.5"),
+			"`window.width` must be a positive whole number");
+	}
+
+	#[test]
+	fn out_of_range_values_are_rejected() {
+		assert_eq!(error("[sky]\nturbidity = 11"), "`sky.turbidity` must be between 1 and 10");
+		assert_eq!(error("[sky]\nalbedo = [0.1, 1.5, 0.1]"),
+			"`sky.albedo` must be between 0 and 1");
+		assert_eq!(error("[camera]\nnear = 0"), "`camera.near` must be positive");
+		assert_eq!(error("[window]\nwidth = 0"),
+			"`window.width` must be a positive whole number");
+		assert_eq!(error("[exposure]\nadaptation_speed = 0"),
+			"`exposure.adaptation_speed` must be positive");
+		assert_eq!(error("[gamepad]\ndeadzone = 1"),
+			"`gamepad.deadzone` must be at least 0 and less than 1");
+
+		// Limits that depend on each other are checked whichever comes first
+		assert_eq!(error("[camera]\nmax_pitch = -10\nmin_pitch = 0"),
+			"`camera.min_pitch` must be less than `camera.max_pitch`");
+		assert_eq!(error("[camera]\nfar = 5\nnear = 10"),
+			"`camera.far` must be greater than `camera.near`");
+		assert!(Config::parse("[camera]\nfar = 50\nnear = 10").is_ok());
+	}
+}
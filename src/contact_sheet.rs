@@ -0,0 +1,256 @@
+
+//
+//  Contact Sheet
+//
+
+use hud::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use math::saturate;
+use sky::{self, Datasets, SkyModel};
+
+use cgmath::{Vector2, Vector3};
+use std::f32;
+
+
+/// The number of bytes in a single RGBA8 pixel.
+const BYTES_PER_PIXEL: usize = 4;
+
+/// The size of each font pixel in the labels, in pixels.
+const LABEL_SCALE: usize = 2;
+
+/// The space around the labels, in pixels.
+const LABEL_PADDING: usize = 6;
+
+/// The longest row label, like `T 10.0`, in characters.
+const ROW_LABEL_CHARACTERS: usize = 6;
+
+/// The space between neighbouring tiles, in pixels.
+const TILE_GAP: usize = 2;
+
+/// The colour of the labels.
+const LABEL_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// The colour behind the tiles.
+const BACKGROUND_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+
+/// The settings for a contact sheet, a grid of tiles showing the sky for a
+/// range of turbidities down the side and sun elevations along the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetLayout {
+	/// The width of each tile, in pixels. Each tile's half as tall, showing
+	/// the whole sky above the horizon unwrapped, with the sun in the middle.
+	pub tile_size: u32,
+	/// The turbidities of the first and last rows.
+	pub turbidity_range: (f32, f32),
+	/// The number of rows.
+	pub turbidity_steps: u32,
+	/// The sun elevations of the first and last columns, in degrees.
+	pub elevation_range: (f32, f32),
+	/// The number of columns.
+	pub elevation_steps: u32,
+	/// The ground albedo for each colour channel.
+	pub albedo: [f32; 3],
+	/// The exposure the sky's tone mapped with.
+	pub exposure: f32,
+	/// The datasets the sky's evaluated from.
+	pub datasets: Datasets,
+}
+
+impl Default for SheetLayout {
+	fn default() -> SheetLayout {
+		SheetLayout {
+			tile_size: 96,
+			turbidity_range: (sky::MIN_TURBIDITY, sky::MAX_TURBIDITY),
+			turbidity_steps: 10,
+			elevation_range: (0.0, 90.0),
+			elevation_steps: 10,
+			albedo: [0.1; 3],
+			exposure: 1.0,
+			datasets: Datasets::embedded(),
+		}
+	}
+}
+
+impl SheetLayout {
+	/// Returns the turbidity of a row.
+	pub fn turbidity(&self, row: u32) -> f32 {
+		step(self.turbidity_range, self.turbidity_steps, row)
+	}
+
+	/// Returns the sun elevation of a column, in degrees.
+	pub fn elevation(&self, column: u32) -> f32 {
+		step(self.elevation_range, self.elevation_steps, column)
+	}
+}
+
+
+/// A rendered contact sheet, as RGBA8 pixels with the first row at the top.
+pub struct ContactSheet {
+	/// The width of the image, in pixels.
+	pub width: u32,
+	/// The height of the image, in pixels.
+	pub height: u32,
+	/// The image's pixels.
+	pub pixels: Vec<u8>,
+}
+
+/// Renders a contact sheet by sampling the sky model on the CPU, so it doesn't
+/// need a window or an OpenGL context. Each row and column is labelled with
+/// its turbidity and sun elevation.
+pub fn render(layout: &SheetLayout) -> ContactSheet {
+	let tile_width = layout.tile_size as usize;
+	let tile_height = (tile_width / 2).max(1);
+	let character_width = (GLYPH_WIDTH + 1) * LABEL_SCALE;
+	let left = ROW_LABEL_CHARACTERS * character_width + 2 * LABEL_PADDING;
+	let top = GLYPH_HEIGHT * LABEL_SCALE + 2 * LABEL_PADDING;
+	let columns = layout.elevation_steps as usize;
+	let rows = layout.turbidity_steps as usize;
+
+	let width = left + columns * (tile_width + TILE_GAP);
+	let height = top + rows * (tile_height + TILE_GAP);
+	let mut sheet = ContactSheet {
+		width: width as u32,
+		height: height as u32,
+		pixels: BACKGROUND_COLOR.iter().cloned().cycle()
+			.take(width * height * BYTES_PER_PIXEL)
+			.collect(),
+	};
+
+	// Leave out the column labels when the tiles are too narrow for them, rather
+	// than letting them run into each other
+	for column in 0 .. columns {
+		let x = left + column * (tile_width + TILE_GAP);
+		let label = format!("{:.0}\u{b0}", layout.elevation(column as u32));
+		if label.chars().count() * character_width <= tile_width + TILE_GAP {
+			draw_text(&mut sheet, x, LABEL_PADDING, &label);
+		}
+	}
+
+	for row in 0 .. rows {
+		let y = top + row * (tile_height + TILE_GAP);
+		let turbidity = layout.turbidity(row as u32);
+		draw_text(&mut sheet, LABEL_PADDING, y, &format!("T {:.1}", turbidity));
+
+		for column in 0 .. columns {
+			let x = left + column * (tile_width + TILE_GAP);
+			let sun = Vector2::new(layout.elevation(column as u32).to_radians(), 0.0);
+			let mut sky = SkyModel::new(turbidity, layout.albedo, sun);
+			sky.set_datasets(layout.datasets.clone());
+			draw_tile(&mut sheet, &sky, layout.exposure, x, y, tile_width, tile_height);
+		}
+	}
+	sheet
+}
+
+/// Returns the value of one of `steps` evenly spaced steps through a range.
+fn step(range: (f32, f32), steps: u32, index: u32) -> f32 {
+	if steps < 2 {
+		return range.0;
+	}
+	range.0 + (range.1 - range.0) * index as f32 / (steps - 1) as f32
+}
+
+/// Fills a tile with the sky above the horizon, unwrapped the same way as the
+/// equirectangular view: azimuth across, with 0 in the middle, and elevation
+/// from the zenith at the top down to the horizon.
+fn draw_tile(sheet: &mut ContactSheet, sky: &SkyModel, exposure: f32, left: usize,
+		top: usize, width: usize, height: usize) {
+	for y in 0 .. height {
+		let elevation = (1.0 - (y as f32 + 0.5) / height as f32) * f32::consts::FRAC_PI_2;
+		for x in 0 .. width {
+			let azimuth = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * f32::consts::PI;
+			let direction = Vector3::new(elevation.cos() * azimuth.sin(),
+				elevation.sin(), elevation.cos() * azimuth.cos());
+			let color = display_color(sky.sample(direction), exposure);
+			set_pixel(sheet, left + x, top + y, color);
+		}
+	}
+}
+
+/// Tone maps radiance and encodes it for an sRGB display, the same way as
+/// `tonemap_frag.glsl`.
+pub fn display_color(radiance: Vector3<f32>, exposure: f32) -> [u8; 4] {
+	let encode = |linear: f32| {
+		let mapped = 1.0 - (-linear * exposure).exp();
+		let encoded = if mapped < 0.003_130_8 {
+			mapped * 12.92
+		} else {
+			1.055 * mapped.powf(1.0 / 2.4) - 0.055
+		};
+		(saturate(encoded) * 255.0).round() as u8
+	};
+	[encode(radiance.x), encode(radiance.y), encode(radiance.z), 255]
+}
+
+/// Writes a line of text in the HUD's font, with its top left corner at a
+/// pixel. Characters the font doesn't have are left as spaces.
+fn draw_text(sheet: &mut ContactSheet, left: usize, top: usize, text: &str) {
+	for (index, character) in text.chars().enumerate() {
+		let rows = match hud::glyph(character) {
+			Some(rows) => rows,
+			None => continue,
+		};
+		let glyph_left = left + index * (GLYPH_WIDTH + 1) * LABEL_SCALE;
+		for (row, &bits) in rows.iter().enumerate() {
+			for column in 0 .. GLYPH_WIDTH {
+				if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+					continue;
+				}
+				for dy in 0 .. LABEL_SCALE {
+					for dx in 0 .. LABEL_SCALE {
+						set_pixel(sheet, glyph_left + column * LABEL_SCALE + dx,
+							top + row * LABEL_SCALE + dy, LABEL_COLOR);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Sets a single pixel, ignoring any outside the image.
+fn set_pixel(sheet: &mut ContactSheet, x: usize, y: usize, color: [u8; 4]) {
+	let width = sheet.width as usize;
+	if x >= width || y >= sheet.height as usize {
+		return;
+	}
+	let start = (y * width + x) * BYTES_PER_PIXEL;
+	sheet.pixels[start .. start + BYTES_PER_PIXEL].copy_from_slice(&color);
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{render, SheetLayout, BACKGROUND_COLOR, LABEL_COLOR};
+
+	#[test]
+	fn steps_cover_range() {
+		let layout = SheetLayout::default();
+		assert_eq!(layout.turbidity(0), 1.0);
+		assert_eq!(layout.turbidity(9), 10.0);
+		assert_eq!(layout.elevation(0), 0.0);
+		assert_eq!(layout.elevation(9), 90.0);
+		assert_eq!(layout.elevation(3), 30.0);
+	}
+
+	#[test]
+	fn renders_labelled_grid() {
+		let layout = SheetLayout {
+			tile_size: 16,
+			turbidity_steps: 2,
+			elevation_steps: 3,
+			..SheetLayout::default()
+		};
+		let sheet = render(&layout);
+		assert_eq!(sheet.pixels.len(), (sheet.width * sheet.height * 4) as usize);
+
+		let pixels: Vec<&[u8]> = sheet.pixels.chunks(4).collect();
+		assert!(pixels.contains(&&LABEL_COLOR[..]));
+
+		// The bottom right pixel of the last tile shows the sky at the
+		// horizon, which isn't black
+		let gap = 2;
+		let corner = (sheet.height - gap - 1) * sheet.width + sheet.width - gap - 1;
+		let sky = pixels[corner as usize];
+		assert!(sky != &BACKGROUND_COLOR[..] && sky[3] == 255, "sky pixel {:?}", sky);
+	}
+}
@@ -0,0 +1,234 @@
+
+//
+//  Dataset Files
+//
+
+use sky::{Datasets, SkyModel};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+
+/// The first line of a dataset file, naming the format and its version.
+const HEADER: &'static str = "hosek-wilkie-rgb 1";
+
+/// The names of the colour channels, as used in section headings.
+const CHANNELS: [&'static str; 3] = ["red", "green", "blue"];
+
+/// The number of values written on each line of a configuration dataset: the
+/// 9 coefficients at one control point.
+const CONFIG_VALUES_PER_LINE: usize = 9;
+
+/// The number of values written on each line of a radiance dataset: the 6
+/// control points of one spline.
+const RADIANCE_VALUES_PER_LINE: usize = 6;
+
+
+impl Datasets {
+	/// Loads datasets from a text file.
+	///
+	/// The file starts with the line `hosek-wilkie-rgb 1`, followed by six
+	/// sections, in any order, each starting with a heading line. The
+	/// headings are `config` or `radiance` followed by `red`, `green` or
+	/// `blue`. After each heading come that dataset's values, separated by
+	/// any whitespace, in the layout described by `Datasets::new`. Anything
+	/// from a `#` to the end of a line is a comment.
+	///
+	/// Each configuration dataset must have `Datasets::config_len` values and
+	/// each radiance dataset `Datasets::radiance_len`, which is 6 control
+	/// points x 10 turbidities x 2 albedos x 9 or 1 values respectively.
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Datasets> {
+		let mut source = String::new();
+		File::open(path)?.read_to_string(&mut source)?;
+		Datasets::parse(&source)
+	}
+
+	/// Saves the datasets to a file, in the format `load` reads.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+
+	/// Parses datasets in the format described in `load`.
+	pub fn parse(source: &str) -> io::Result<Datasets> {
+		// Strip comments and blank lines, keeping the line numbers for errors
+		let mut lines = source.lines()
+			.enumerate()
+			.map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+			.filter(|&(_, line)| !line.is_empty());
+		match lines.next() {
+			Some((_, line)) if line == HEADER => {},
+			_ => return Err(invalid_data(&format!("expected `{}` on the first line", HEADER))),
+		}
+
+		// Gather the values under each heading
+		let mut sections: Vec<(String, Vec<f32>)> = Vec::new();
+		for (number, line) in lines {
+			if line.starts_with(|c: char| c.is_alphabetic()) {
+				let heading = line.split_whitespace().collect::<Vec<_>>().join(" ");
+				if sections.iter().any(|section| section.0 == heading) {
+					return Err(invalid_data(&format!("line {}: `{}` appears twice",
+						number, heading)));
+				}
+				sections.push((heading, Vec::new()));
+				continue;
+			}
+
+			let values = match sections.last_mut() {
+				Some(&mut (_, ref mut values)) => values,
+				None => return Err(invalid_data(&format!(
+					"line {}: values before the first heading", number))),
+			};
+			for word in line.split_whitespace() {
+				let value = word.parse().map_err(|_| invalid_data(&format!(
+					"line {}: `{}` isn't a number", number, word)))?;
+				values.push(value);
+			}
+		}
+
+		// Pick out each dataset, complaining about anything left over
+		let mut take = |kind: &str, channel: &str| {
+			let heading = format!("{} {}", kind, channel);
+			match sections.iter().position(|section| section.0 == heading) {
+				Some(index) => Ok(sections.remove(index).1),
+				None => Err(invalid_data(&format!("missing `{}`", heading))),
+			}
+		};
+		let config = [take("config", CHANNELS[0])?, take("config", CHANNELS[1])?,
+			take("config", CHANNELS[2])?];
+		let radiance = [take("radiance", CHANNELS[0])?, take("radiance", CHANNELS[1])?,
+			take("radiance", CHANNELS[2])?];
+		if let Some(section) = sections.first() {
+			return Err(invalid_data(&format!("unknown heading `{}`", section.0)));
+		}
+
+		Datasets::new(config, radiance)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+	}
+
+	/// Writes the datasets in the format `load` reads. Every value is written
+	/// with as many digits as it takes to read back exactly.
+	pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		writeln!(writer, "# Hosek-Wilkie RGB datasets, for each albedo, turbidity, and control point")?;
+		writeln!(writer, "{}", HEADER)?;
+		for (channel, name) in CHANNELS.iter().enumerate() {
+			writeln!(writer, "config {}", name)?;
+			write_values(writer, self.config(channel), CONFIG_VALUES_PER_LINE)?;
+		}
+		for (channel, name) in CHANNELS.iter().enumerate() {
+			writeln!(writer, "radiance {}", name)?;
+			write_values(writer, self.radiance(channel), RADIANCE_VALUES_PER_LINE)?;
+		}
+		Ok(())
+	}
+}
+
+impl SkyModel {
+	/// Loads the tables the coefficients are evaluated from out of a file, in
+	/// the format described in `Datasets::load`, and recomputes them. If the
+	/// file can't be loaded, falls back to the built-in datasets and returns
+	/// the reason.
+	pub fn load_datasets<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+		match Datasets::load(path) {
+			Ok(datasets) => {
+				self.set_datasets(datasets);
+				Ok(())
+			},
+			Err(err) => {
+				self.set_datasets(Datasets::embedded());
+				Err(err)
+			},
+		}
+	}
+}
+
+
+/// Writes a dataset's values, a fixed number to a line.
+fn write_values<W: Write>(writer: &mut W, values: &[f32], per_line: usize)
+		-> io::Result<()> {
+	for line in values.chunks(per_line) {
+		let words: Vec<String> = line.iter().map(|value| value.to_string()).collect();
+		writeln!(writer, "\t{}", words.join(" "))?;
+	}
+	Ok(())
+}
+
+/// Creates an error for a file that isn't a valid set of datasets.
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use sky::{DatasetError, DatasetKind, Datasets, SkyModel};
+
+	use cgmath::Vector2;
+
+	/// Exports the built-in datasets as text.
+	fn exported() -> String {
+		let mut bytes = Vec::new();
+		Datasets::embedded().write(&mut bytes).unwrap();
+		String::from_utf8(bytes).unwrap()
+	}
+
+	#[test]
+	fn round_trip_reproduces_model() {
+		let loaded = Datasets::parse(&exported()).unwrap();
+		assert_eq!(loaded, Datasets::embedded());
+
+		for &elevation in &[-0.05, 0.0, 0.2, 0.7, 1.5] {
+			let sun = Vector2::new(elevation, 0.3);
+			let embedded = SkyModel::new(3.0, [0.1, 0.5, 0.9], sun);
+			let mut custom = SkyModel::new(3.0, [0.1, 0.5, 0.9], sun);
+			custom.set_datasets(loaded.clone());
+			assert_eq!(custom.coefficients(), embedded.coefficients());
+		}
+	}
+
+	#[test]
+	fn rejects_malformed_files() {
+		let source = exported();
+		assert!(Datasets::parse("").is_err());
+		assert!(Datasets::parse(&source.replace("hosek-wilkie-rgb 1", "something else")).is_err());
+		assert!(Datasets::parse(&source.replace("radiance green", "radiance purple")).is_err());
+		assert!(Datasets::parse(&source.replace("config blue", "config red")).is_err());
+
+		// One line short of a full dataset, with the error kept inside
+		let short = source.replacen("\t", "\t# ", 1);
+		let err = Datasets::parse(&short).unwrap_err();
+		assert!(err.to_string().contains("expected 1080"), "{}", err);
+		let inner = err.get_ref().and_then(|inner| inner.downcast_ref::<DatasetError>());
+		assert_eq!(inner, Some(&DatasetError::Length {
+			kind: DatasetKind::Config,
+			channel: 0,
+			len: 1071,
+		}));
+
+		// Values have to be finite, and one too big for a float isn't
+		let start = source.find("radiance red\n\t").unwrap() + "radiance red\n\t".len();
+		let end = start + source[start ..].find(' ').unwrap();
+		let infinite = format!("{}1e39{}", &source[.. start], &source[end ..]);
+		let err = Datasets::parse(&infinite).unwrap_err();
+		assert!(err.to_string().contains("radiance dataset 0 has a value that isn't \
+			finite at index 0"), "{}", err);
+
+		// Sections can be in any order, with comments anywhere
+		let mut sections: Vec<&str> = source.split("\nradiance ").collect();
+		let first = sections.remove(0);
+		let reordered = format!("{}\nradiance {} # comment\n", first,
+			sections.iter().rev().cloned().collect::<Vec<_>>().join("\nradiance "));
+		assert_eq!(Datasets::parse(&reordered).unwrap(), Datasets::embedded());
+	}
+
+	#[test]
+	fn load_falls_back_to_embedded() {
+		let mut sky = SkyModel::new(3.0, [0.1; 3], Vector2::new(0.5, 0.0));
+		let expected = *sky.coefficients();
+		assert!(sky.load_datasets("no/such/datasets.txt").is_err());
+		assert_eq!(*sky.datasets(), Datasets::embedded());
+		assert_eq!(*sky.coefficients(), expected);
+	}
+}
@@ -0,0 +1,168 @@
+
+//
+//  OpenGL Debugging
+//
+
+use gl;
+use gl::types::*;
+
+use std::ptr;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+
+
+/// True if OpenGL errors should be reported. Release builds skip all error
+/// checking so they don't pay the cost.
+pub const ENABLED: bool = cfg!(debug_assertions);
+
+/// The least severe debug message that gets printed by the callback.
+const MIN_SEVERITY: GLenum = gl::DEBUG_SEVERITY_LOW;
+
+
+/// The driver's description of the current OpenGL context, for bug reports.
+pub struct ContextInfo {
+	/// The company responsible for the implementation.
+	pub vendor: String,
+	/// The renderer, usually naming the graphics card.
+	pub renderer: String,
+	/// The OpenGL version, followed by any vendor specific details.
+	pub version: String,
+	/// The highest GLSL version the context supports.
+	pub shading_language_version: String,
+}
+
+impl ContextInfo {
+	/// Asks the driver about the current context. Requires the OpenGL
+	/// functions to have been loaded.
+	pub fn query() -> ContextInfo {
+		ContextInfo {
+			vendor: gl_string(gl::VENDOR),
+			renderer: gl_string(gl::RENDERER),
+			version: gl_string(gl::VERSION),
+			shading_language_version: gl_string(gl::SHADING_LANGUAGE_VERSION),
+		}
+	}
+}
+
+
+/// Installs a debug message callback that prints OpenGL errors and warnings
+/// to stderr. Requires a KHR_debug or OpenGL 4.3 context.
+///
+/// Returns false if debug output isn't enabled or isn't supported by the
+/// context, in which case `check_gl_error` should be used instead.
+pub fn install() -> bool {
+	if !ENABLED || !gl::DebugMessageCallback::is_loaded() {
+		return false;
+	}
+
+	unsafe {
+		// Synchronous output means the callback runs on the thread (and
+		// inside the call) that caused the message
+		gl::Enable(gl::DEBUG_OUTPUT);
+		gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+		gl::DebugMessageCallback(callback, ptr::null());
+	}
+	true
+}
+
+/// Prints any pending OpenGL errors to stderr, for contexts that don't
+/// support the debug callback. The location is included in the message to
+/// help track down which call caused the error.
+pub fn check_gl_error(location: &str) {
+	if !ENABLED {
+		return;
+	}
+
+	// Multiple error flags can be set at once, so keep going until they've
+	// all been cleared
+	loop {
+		let error = unsafe { gl::GetError() };
+		if error == gl::NO_ERROR {
+			break;
+		}
+		eprintln!("GL error at {}: {}", location, error_name(error));
+	}
+}
+
+/// Called by OpenGL whenever a debug message is generated.
+extern "system" fn callback(source: GLenum, kind: GLenum, _id: GLuint,
+		severity: GLenum, _length: GLsizei, message: *const GLchar,
+		_user: *mut c_void) {
+	if severity_rank(severity) < severity_rank(MIN_SEVERITY) {
+		return;
+	}
+
+	let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+	eprintln!("GL {} ({} severity) from {}: {}", kind_name(kind),
+		severity_name(severity), source_name(source), message);
+}
+
+/// Returns one of the context's description strings, or `unknown` if the
+/// driver doesn't give one.
+fn gl_string(name: GLenum) -> String {
+	let string = unsafe { gl::GetString(name) };
+	if string.is_null() {
+		return "unknown".to_string();
+	}
+	unsafe { CStr::from_ptr(string as *const GLchar) }.to_string_lossy().into_owned()
+}
+
+/// Orders severities from least to most severe, so they can be filtered.
+fn severity_rank(severity: GLenum) -> u32 {
+	match severity {
+		gl::DEBUG_SEVERITY_HIGH => 3,
+		gl::DEBUG_SEVERITY_MEDIUM => 2,
+		gl::DEBUG_SEVERITY_LOW => 1,
+		_ => 0,
+	}
+}
+
+/// Returns a human readable name for a debug message severity.
+fn severity_name(severity: GLenum) -> &'static str {
+	match severity {
+		gl::DEBUG_SEVERITY_HIGH => "high",
+		gl::DEBUG_SEVERITY_MEDIUM => "medium",
+		gl::DEBUG_SEVERITY_LOW => "low",
+		gl::DEBUG_SEVERITY_NOTIFICATION => "notification",
+		_ => "unknown",
+	}
+}
+
+/// Returns a human readable name for a debug message type.
+fn kind_name(kind: GLenum) -> &'static str {
+	match kind {
+		gl::DEBUG_TYPE_ERROR => "error",
+		gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behaviour",
+		gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behaviour",
+		gl::DEBUG_TYPE_PORTABILITY => "portability warning",
+		gl::DEBUG_TYPE_PERFORMANCE => "performance warning",
+		gl::DEBUG_TYPE_MARKER => "marker",
+		_ => "message",
+	}
+}
+
+/// Returns a human readable name for a debug message source.
+fn source_name(source: GLenum) -> &'static str {
+	match source {
+		gl::DEBUG_SOURCE_API => "API",
+		gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+		gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+		gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+		gl::DEBUG_SOURCE_APPLICATION => "application",
+		_ => "unknown source",
+	}
+}
+
+/// Returns the name of an error returned by `glGetError`.
+fn error_name(error: GLenum) -> &'static str {
+	match error {
+		gl::INVALID_ENUM => "GL_INVALID_ENUM",
+		gl::INVALID_VALUE => "GL_INVALID_VALUE",
+		gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+		gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+		gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+		gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+		gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+		_ => "unknown error",
+	}
+}
@@ -0,0 +1,102 @@
+
+//
+//  Coefficient Dump
+//
+
+use sky::SkyModel;
+
+use cgmath::Vector3;
+
+
+/// Formats a sky model's inputs and coefficients as JSON, so they can be used
+/// by other renderers. The sun's position is given in degrees.
+///
+/// `params` holds the 9 coefficients A to I, followed by the radiance scale,
+/// in the same order as `SkyCoefficients::as_uniform_slice`. The scale is
+/// already normalised the same way the shader uses it, so the radiance in a
+/// direction is `params[9] * F(theta, gamma)`.
+///
+/// JSON has no NaN or infinity, so any value that isn't finite is written as
+/// `null`.
+pub fn coefficients_json(sky: &SkyModel, elevation: f32, azimuth: f32) -> String {
+	let params = sky.coefficients().as_uniform_slice().iter()
+		.map(|param| format!("\t\t{}", vector_json(*param)))
+		.collect::<Vec<_>>()
+		.join(",\n");
+	let albedo = sky.albedo();
+
+	format!("{{\n\
+		\t\"turbidity\": {},\n\
+		\t\"albedo\": [{}, {}, {}],\n\
+		\t\"elevation\": {},\n\
+		\t\"azimuth\": {},\n\
+		\t\"sun_direction\": {},\n\
+		\t\"params\": [\n{}\n\t]\n\
+		}}",
+		number_json(sky.turbidity()), number_json(albedo[0]), number_json(albedo[1]),
+		number_json(albedo[2]), number_json(elevation), number_json(azimuth),
+		vector_json(sky.sun_direction()), params)
+}
+
+/// Formats a vector as a JSON array.
+fn vector_json(vector: Vector3<f32>) -> String {
+	format!("[{}, {}, {}]", number_json(vector.x), number_json(vector.y),
+		number_json(vector.z))
+}
+
+/// Formats a number for JSON, as `null` if it's NaN or infinite.
+fn number_json(value: f32) -> String {
+	if value.is_finite() {
+		value.to_string()
+	} else {
+		"null".to_string()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{coefficients_json, vector_json};
+	use sky::SkyModel;
+
+	use cgmath::{Vector2, Vector3};
+	use std::f32;
+
+	#[test]
+	fn json_has_every_field() {
+		let sky = SkyModel::new(3.0, [0.1, 0.2, 0.3], Vector2::new(0.5, 0.25));
+		let json = coefficients_json(&sky, 30.0, 15.0);
+		let lines: Vec<&str> = json.lines().collect();
+		assert_eq!(lines[.. 5], [
+			"{",
+			"\t\"turbidity\": 3,",
+			"\t\"albedo\": [0.1, 0.2, 0.3],",
+			"\t\"elevation\": 30,",
+			"\t\"azimuth\": 15,",
+		]);
+		assert!(lines[5].starts_with("\t\"sun_direction\": ["));
+		assert_eq!(lines[6], "\t\"params\": [");
+		assert_eq!(lines[lines.len() - 2 ..], ["\t]", "}"]);
+
+		// A to I, then the radiance scale, each an array of 3 numbers
+		let params = &lines[7 .. lines.len() - 2];
+		assert_eq!(params.len(), 10);
+		for (index, param) in params.iter().enumerate() {
+			let param = param.trim().trim_matches(',');
+			assert!(param.starts_with('[') && param.ends_with(']'), "{}", param);
+			let values: Vec<f32> = param[1 .. param.len() - 1].split(", ")
+				.map(|value| value.parse().unwrap())
+				.collect();
+			assert_eq!(values.len(), 3);
+			assert_eq!(params[index].ends_with(','), index < 9);
+		}
+	}
+
+	#[test]
+	fn non_finite_values_are_null() {
+		assert_eq!(vector_json(Vector3::new(f32::NAN, f32::INFINITY, -0.5)),
+			"[null, null, -0.5]");
+		assert_eq!(vector_json(Vector3::new(f32::NEG_INFINITY, 1e-3, 2.0)),
+			"[null, 0.001, 2]");
+	}
+}
@@ -0,0 +1,72 @@
+
+//
+//  Errors
+//
+
+use config::ConfigError;
+
+use glutin::{ContextError, CreationError};
+use std::fmt;
+use std::path::PathBuf;
+
+
+/// An error that stops the program from starting or running.
+#[derive(Debug)]
+pub enum SkyError {
+	/// The command line arguments couldn't be parsed.
+	Usage,
+	/// A command line argument was recognised, but its value is invalid.
+	Argument(String),
+	/// The config file couldn't be loaded.
+	Config { path: PathBuf, error: ConfigError },
+	/// The window couldn't be created, which is expected on machines without
+	/// a display.
+	Window(CreationError),
+	/// The OpenGL context couldn't be made current, or its buffers couldn't be
+	/// swapped.
+	Context(ContextError),
+	/// The OpenGL functions couldn't be loaded.
+	GlLoad,
+	/// The cursor couldn't be moved or hidden.
+	Cursor(String),
+	/// A captured frame couldn't be saved.
+	Capture { path: PathBuf, message: String },
+	/// An input recording couldn't be written, or read back.
+	Recording { path: PathBuf, message: String },
+}
+
+impl fmt::Display for SkyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			SkyError::Usage => write!(f, "Usage: sky [--config path] [--info] [--dump-coeffs \
+				[--turbidity t] [--albedo a] [--elevation degrees] [--azimuth degrees]] \
+				[--resolution WIDTHxHEIGHT] [--turntable frames [--output directory]] \
+				[--record path | --playback path] [--contact-sheet path [--tile-size pixels]] \
+				[--datasets path] [--nearest-turbidity] [--viewpoint name]"),
+			SkyError::Argument(ref message) => write!(f, "Invalid argument: {}", message),
+			SkyError::Config { ref path, ref error } =>
+				write!(f, "Invalid config file {}: {}", path.display(), error),
+			SkyError::Window(ref err) => write!(f, "Failed to create window: {}", err),
+			SkyError::Context(ref err) => write!(f, "OpenGL context error: {}", err),
+			SkyError::GlLoad => write!(f, "Failed to load OpenGL functions"),
+			SkyError::Cursor(ref message) =>
+				write!(f, "Failed to capture cursor: {}", message),
+			SkyError::Capture { ref path, ref message } =>
+				write!(f, "Failed to save {}: {}", path.display(), message),
+			SkyError::Recording { ref path, ref message } =>
+				write!(f, "Input recording {} failed: {}", path.display(), message),
+		}
+	}
+}
+
+impl From<CreationError> for SkyError {
+	fn from(err: CreationError) -> SkyError {
+		SkyError::Window(err)
+	}
+}
+
+impl From<ContextError> for SkyError {
+	fn from(err: ContextError) -> SkyError {
+		SkyError::Context(err)
+	}
+}
@@ -0,0 +1,163 @@
+
+//
+//  Automatic Exposure
+//
+
+use gl;
+use gl::types::*;
+
+use framebuffer::Framebuffer;
+use math::clamp;
+use shader::{ShaderProgram, ShaderSources};
+use sky::LUMINANCE_WEIGHTS;
+
+use cgmath::Vector3;
+
+use std::ptr;
+
+
+/// The size of the luminance buffer along each side, in texels. A power of two
+/// so each mip level halves evenly, down to a single texel.
+const LUMINANCE_SIZE: u32 = 256;
+
+/// The number of mip levels in the luminance buffer, the last being 1x1.
+const LUMINANCE_LEVELS: GLint = 9;
+
+/// The largest luminance counted towards the average. The sun's disk is far
+/// brighter than anything else, and would otherwise darken the whole image
+/// whenever it's in view.
+const MAX_LUMINANCE: f32 = 4.0;
+
+/// The smallest exposure the image can adapt to.
+const MIN_EXPOSURE: f32 = 0.25;
+
+/// The largest exposure the image can adapt to. Stops the night sky from being
+/// brightened up to daylight levels.
+const MAX_EXPOSURE: f32 = 8.0;
+
+
+/// Adapts the exposure to the average brightness of the image over time, like
+/// an eye adjusting to the light.
+pub struct AutoExposure {
+	/// The framebuffer the log luminance is rendered into.
+	fbo: GLuint,
+	/// The mipmapped texture holding the log luminance.
+	texture: GLuint,
+	/// Computes the log luminance of the HDR image.
+	program: ShaderProgram,
+	/// The source texture uniform.
+	source_uniform: GLint,
+	/// The maximum luminance uniform.
+	max_luminance_uniform: GLint,
+	/// The luminance weights uniform.
+	luminance_weights_uniform: GLint,
+
+	/// The current exposure.
+	exposure: f32,
+	/// The average luminance the exposure maps the image to.
+	pub key_value: f32,
+	/// How quickly the exposure adapts, per second.
+	pub adaptation_speed: f32,
+}
+
+impl AutoExposure {
+	/// Creates the luminance buffer, starting from the given exposure. The
+	/// key value is the average luminance the image is exposed to, and the
+	/// adaptation speed how quickly it gets there, per second.
+	///
+	/// Panics if the framebuffer is incomplete.
+	pub fn new(exposure: f32, key_value: f32, adaptation_speed: f32,
+			sources: &ShaderSources) -> AutoExposure {
+		let program = sources.program("post_vert.glsl", "luminance_frag.glsl");
+		let mut auto_exposure = AutoExposure {
+			fbo: 0,
+			texture: 0,
+			source_uniform: program.uniform("source"),
+			max_luminance_uniform: program.uniform("max_luminance"),
+			luminance_weights_uniform: program.uniform("luminance_weights"),
+			program: program,
+			exposure: exposure,
+			key_value: key_value,
+			adaptation_speed: adaptation_speed,
+		};
+
+		unsafe {
+			gl::GenFramebuffers(1, &mut auto_exposure.fbo);
+			gl::GenTextures(1, &mut auto_exposure.texture);
+
+			// Allocate every mip level, so the last one can be read back
+			gl::BindTexture(gl::TEXTURE_2D, auto_exposure.texture);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, LUMINANCE_LEVELS - 1);
+			for level in 0 .. LUMINANCE_LEVELS {
+				let size = (LUMINANCE_SIZE >> level) as GLsizei;
+				gl::TexImage2D(gl::TEXTURE_2D, level, gl::R16F as GLint, size, size,
+					0, gl::RED, gl::FLOAT, ptr::null());
+			}
+
+			gl::BindFramebuffer(gl::FRAMEBUFFER, auto_exposure.fbo);
+			gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+				gl::TEXTURE_2D, auto_exposure.texture, 0);
+			let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+			if status != gl::FRAMEBUFFER_COMPLETE {
+				panic!("Luminance framebuffer incomplete: {:#x}", status);
+			}
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+		}
+		auto_exposure
+	}
+
+	/// Returns the current exposure.
+	pub fn exposure(&self) -> f32 {
+		self.exposure
+	}
+
+	/// Measures the average luminance of an HDR framebuffer, and moves the
+	/// exposure towards the value that maps it to the key value over `delta`
+	/// seconds.
+	///
+	/// Assumes depth testing is disabled and a VAO suitable for drawing the
+	/// fullscreen triangle is bound. Leaves the luminance framebuffer bound.
+	pub fn update(&mut self, source: &Framebuffer, delta: f32) {
+		let mut log_average: f32 = 0.0;
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+			gl::Viewport(0, 0, LUMINANCE_SIZE as GLsizei, LUMINANCE_SIZE as GLsizei);
+		}
+		self.program.bind();
+		source.bind_color(0);
+		self.program.set_uniform_i32(self.source_uniform, 0);
+		self.program.set_uniform_f32(self.max_luminance_uniform, MAX_LUMINANCE);
+		self.program.set_uniform_vec3(self.luminance_weights_uniform,
+			Vector3::from(LUMINANCE_WEIGHTS));
+		unsafe {
+			gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+			// Average the log luminance down to a single texel, then read it
+			// back. It's a single float, so the stall is small
+			gl::BindTexture(gl::TEXTURE_2D, self.texture);
+			gl::GenerateMipmap(gl::TEXTURE_2D);
+			gl::GetTexImage(gl::TEXTURE_2D, LUMINANCE_LEVELS - 1, gl::RED, gl::FLOAT,
+				&mut log_average as *mut f32 as *mut GLvoid);
+		}
+
+		// Adapt in log space, so brightening and darkening take equally long.
+		// Using an exponential keeps this stable no matter how long the frame
+		// took
+		let average = log_average.exp();
+		let target = clamp(self.key_value / average, MIN_EXPOSURE, MAX_EXPOSURE);
+		let t = 1.0 - (-self.adaptation_speed * delta).exp();
+		self.exposure = (self.exposure.ln() + (target.ln() - self.exposure.ln()) * t)
+			.exp();
+	}
+}
+
+impl Drop for AutoExposure {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteFramebuffers(1, &self.fbo);
+			gl::DeleteTextures(1, &self.texture);
+		}
+	}
+}
@@ -0,0 +1,124 @@
+
+//
+//  Frame Limiter
+//
+
+use glutin::Window;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+/// The frame rate caps cycled through, in frames per second. None means the
+/// frame rate isn't capped.
+pub const FPS_CAPS: [Option<u32>; 5] = [None, Some(30), Some(60), Some(120), Some(144)];
+
+/// How long before the end of the frame to stop sleeping and start yielding
+/// instead, since sleeps can overshoot by around a millisecond.
+const SPIN_TIME: Duration = Duration::from_millis(1);
+
+
+/// Caps the frame rate in software, by sleeping away whatever's left of each
+/// frame once it's been rendered.
+pub struct FrameLimiter {
+	/// The time each frame should take, or None if the frame rate isn't capped.
+	frame_time: Option<Duration>,
+	/// When the current frame should end.
+	deadline: Instant,
+}
+
+impl FrameLimiter {
+	/// Creates a new limiter which doesn't cap the frame rate.
+	pub fn new() -> FrameLimiter {
+		FrameLimiter {
+			frame_time: None,
+			deadline: Instant::now(),
+		}
+	}
+
+	/// Sets the frame rate to cap to, in frames per second, or None to stop
+	/// capping it.
+	pub fn set_fps(&mut self, fps: Option<u32>) {
+		self.frame_time = fps.map(|fps| Duration::from_secs(1) / fps);
+		self.deadline = Instant::now();
+	}
+
+	/// Waits until the current frame has taken up its share of time. Call once
+	/// a frame, after swapping the buffers.
+	///
+	/// Deadlines are spaced evenly from each other rather than from when this
+	/// is called, so the time already spent rendering counts towards the frame.
+	pub fn wait(&mut self) {
+		let frame_time = match self.frame_time {
+			Some(frame_time) => frame_time,
+			None => return,
+		};
+
+		self.deadline += frame_time;
+		let now = Instant::now();
+		if now >= self.deadline {
+			// The frame took too long, so start counting again from now rather
+			// than rushing through the following frames to catch up
+			self.deadline = now;
+			return;
+		}
+
+		// Sleep for most of the remaining time, then yield for the rest
+		let remaining = self.deadline - now;
+		if remaining > SPIN_TIME {
+			thread::sleep(remaining - SPIN_TIME);
+		}
+		while Instant::now() < self.deadline {
+			thread::yield_now();
+		}
+	}
+}
+
+
+/// Sets the number of screen refreshes to wait for when swapping buffers,
+/// where 0 turns vsync off and 1 turns it on. Returns false if the swap
+/// interval can't be changed on this platform, in which case vsync can only be
+/// chosen when the window's created.
+#[cfg(target_os = "windows")]
+pub fn set_swap_interval(window: &Window, interval: i32) -> bool {
+	use std::mem;
+
+	// The extension function is null if the driver doesn't support it
+	let address = window.get_proc_address("wglSwapIntervalEXT");
+	if address.is_null() {
+		return false;
+	}
+	let swap_interval: extern "system" fn(i32) -> i32 =
+		unsafe { mem::transmute(address) };
+	swap_interval(interval) != 0
+}
+
+/// Sets the number of screen refreshes to wait for when swapping buffers,
+/// where 0 turns vsync off and 1 turns it on. Returns false if the swap
+/// interval can't be changed on this platform, in which case vsync can only be
+/// chosen when the window's created.
+#[cfg(not(target_os = "windows"))]
+pub fn set_swap_interval(_window: &Window, _interval: i32) -> bool {
+	false
+}
+
+/// Returns the number of screen refreshes waited for when swapping buffers, or
+/// None if it can't be found out on this platform.
+#[cfg(target_os = "windows")]
+pub fn swap_interval(window: &Window) -> Option<i32> {
+	use std::mem;
+
+	let address = window.get_proc_address("wglGetSwapIntervalEXT");
+	if address.is_null() {
+		return None;
+	}
+	let swap_interval: extern "system" fn() -> i32 = unsafe { mem::transmute(address) };
+	Some(swap_interval())
+}
+
+/// Returns the number of screen refreshes waited for when swapping buffers, or
+/// None if it can't be found out on this platform.
+#[cfg(not(target_os = "windows"))]
+pub fn swap_interval(_window: &Window) -> Option<i32> {
+	None
+}
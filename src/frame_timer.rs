@@ -0,0 +1,141 @@
+
+//
+//  Frame Timing
+//
+
+use std::time::{Duration, Instant};
+
+
+/// The number of frames the frame time is averaged over.
+const SAMPLES: usize = 60;
+
+/// How often the average should be reported, in milliseconds.
+const REPORT_INTERVAL: u64 = 250;
+
+/// How much of each new frame delta is blended into the smoothed delta. Lower
+/// values smooth more, but take longer to follow real changes in frame rate.
+const SMOOTHING: f32 = 0.2;
+
+
+/// Tracks a rolling average of the time taken by recent frames.
+pub struct FrameTimer {
+	/// The most recent frame times, in seconds, used as a ring buffer.
+	samples: [f32; SAMPLES],
+	/// The index in the ring buffer the next sample is written to.
+	next: usize,
+	/// The number of samples recorded so far, up to `SAMPLES`.
+	count: usize,
+	/// When the average was last reported.
+	last_report: Instant,
+}
+
+impl FrameTimer {
+	/// Creates a frame timer with no samples recorded.
+	pub fn new() -> FrameTimer {
+		FrameTimer {
+			samples: [0.0; SAMPLES],
+			next: 0,
+			count: 0,
+			last_report: Instant::now(),
+		}
+	}
+
+	/// Records the duration of a frame, in seconds.
+	///
+	/// Returns true if it's time for the average to be reported again, which
+	/// happens a few times a second.
+	pub fn record(&mut self, frame_time: f32, now: Instant) -> bool {
+		// Replace the oldest sample
+		self.samples[self.next] = frame_time;
+		self.next = (self.next + 1) % SAMPLES;
+		self.count = (self.count + 1).min(SAMPLES);
+
+		if now - self.last_report >= Duration::from_millis(REPORT_INTERVAL) {
+			self.last_report = now;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Returns the average frame time, in seconds.
+	pub fn average(&self) -> f32 {
+		if self.count == 0 {
+			return 0.0;
+		}
+
+		// Unused slots are zero until the buffer fills, so they don't affect
+		// the sum
+		let total: f32 = self.samples.iter().sum();
+		total / self.count as f32
+	}
+
+	/// Returns the average number of frames per second.
+	pub fn fps(&self) -> f32 {
+		let average = self.average();
+		if average > 0.0 { 1.0 / average } else { 0.0 }
+	}
+}
+
+
+/// Smooths out the time between frames, so a single long frame (like one
+/// stalled by the OS) doesn't make anything driven by it jump.
+pub struct DeltaSmoother {
+	/// The longest frame delta that's used as is, in seconds. Longer ones are
+	/// clamped to this before being smoothed.
+	max_delta: f32,
+	/// The running average of the clamped deltas, or None before the first
+	/// frame.
+	average: Option<f32>,
+}
+
+impl DeltaSmoother {
+	/// Creates a smoother which clamps deltas to `max_delta` seconds.
+	pub fn new(max_delta: f32) -> DeltaSmoother {
+		DeltaSmoother {
+			max_delta: max_delta,
+			average: None,
+		}
+	}
+
+	/// Records the time since the last frame, in seconds, and returns the
+	/// smoothed delta to use in its place.
+	pub fn smooth(&mut self, delta: f32) -> f32 {
+		let delta = delta.min(self.max_delta);
+		let average = match self.average {
+			Some(average) => average + (delta - average) * SMOOTHING,
+			None => delta,
+		};
+		self.average = Some(average);
+		average
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::DeltaSmoother;
+
+	#[test]
+	fn steady_delta_is_unchanged() {
+		let mut smoother = DeltaSmoother::new(0.1);
+		for _ in 0 .. 10 {
+			assert!((smoother.smooth(1.0 / 60.0) - 1.0 / 60.0).abs() < 1e-6);
+		}
+	}
+
+	#[test]
+	fn spike_is_clamped_and_smoothed() {
+		let mut smoother = DeltaSmoother::new(0.1);
+		smoother.smooth(1.0 / 60.0);
+		let spike = smoother.smooth(2.0);
+		assert!(spike > 1.0 / 60.0 && spike < 0.1);
+
+		// The smoothed delta settles back down once frames are short again
+		let mut delta = spike;
+		for _ in 0 .. 60 {
+			delta = smoother.smooth(1.0 / 60.0);
+		}
+		assert!((delta - 1.0 / 60.0).abs() < 1e-4);
+	}
+}
@@ -0,0 +1,232 @@
+
+//
+//  Framebuffers
+//
+
+use gl;
+use gl::types::*;
+
+use texture::{Filter, Texture, TextureTarget};
+
+
+/// An offscreen framebuffer with a colour texture and, unless it's only for
+/// post-processing, a depth buffer. Usually floating point, used to render the
+/// scene in HDR before tone mapping.
+///
+/// A multisampled framebuffer is drawn into through a second framebuffer
+/// object, with multisampled colour and depth renderbuffers, which has to be
+/// resolved into the colour texture with `resolve` before it's used.
+pub struct Framebuffer {
+	/// The framebuffer object the colour texture's attached to.
+	fbo: GLuint,
+	/// The texture the colour output is written to.
+	color: Texture,
+	/// The internal format of the colour output.
+	internal_format: GLenum,
+	/// The number of samples per pixel, or 0 if it isn't multisampled.
+	samples: GLsizei,
+	/// The framebuffer object drawn into when multisampling, or 0 if it isn't
+	/// multisampled.
+	multisample_fbo: GLuint,
+	/// The multisampled renderbuffer the colour output is written to, or 0 if
+	/// it isn't multisampled.
+	multisample_color: GLuint,
+	/// The renderbuffer used for depth testing, or 0 if there isn't one. It's
+	/// multisampled along with the colour output.
+	depth: GLuint,
+	/// The width of the framebuffer, in pixels.
+	width: u32,
+	/// The height of the framebuffer, in pixels.
+	height: u32,
+}
+
+impl Framebuffer {
+	/// Creates a new HDR framebuffer with the given dimensions, in pixels, and
+	/// number of samples per pixel for multisample antialiasing, or 0 for
+	/// none.
+	///
+	/// Panics if the framebuffer is incomplete.
+	pub fn new(width: u32, height: u32, samples: u32) -> Framebuffer {
+		Framebuffer::create(width, height, gl::RGBA16F, true, samples as GLsizei)
+	}
+
+	/// Creates a framebuffer whose colour texture has the given internal
+	/// format, like `gl::RGBA8` for tonemapped output that's read back.
+	///
+	/// Panics if the framebuffer is incomplete.
+	pub fn with_format(width: u32, height: u32, internal_format: GLenum)
+			-> Framebuffer {
+		Framebuffer::create(width, height, internal_format, true, 0)
+	}
+
+	/// Creates an HDR framebuffer with no depth buffer, for fullscreen passes
+	/// that never depth test, like the bloom blurs.
+	///
+	/// Panics if the framebuffer is incomplete.
+	pub fn color_only(width: u32, height: u32) -> Framebuffer {
+		Framebuffer::create(width, height, gl::RGBA16F, false, 0)
+	}
+
+	/// Creates the framebuffer, attaching a depth buffer if `depth` is set, and
+	/// drawing into multisampled renderbuffers if `samples` isn't 0.
+	fn create(width: u32, height: u32, internal_format: GLenum, depth: bool,
+			samples: GLsizei) -> Framebuffer {
+		let mut framebuffer = Framebuffer {
+			fbo: 0,
+			color: Texture::new(TextureTarget::Texture2D, internal_format, width,
+				height, Filter::Linear),
+			internal_format: internal_format,
+			samples: samples,
+			multisample_fbo: 0,
+			multisample_color: 0,
+			depth: 0,
+			width: width,
+			height: height,
+		};
+
+		unsafe {
+			gl::GenFramebuffers(1, &mut framebuffer.fbo);
+			if samples > 0 {
+				gl::GenFramebuffers(1, &mut framebuffer.multisample_fbo);
+				gl::GenRenderbuffers(1, &mut framebuffer.multisample_color);
+			}
+			if depth {
+				gl::GenRenderbuffers(1, &mut framebuffer.depth);
+			}
+		}
+
+		// Allocate storage, then attach it to the framebuffer
+		framebuffer.allocate();
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.fbo);
+			gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+				gl::TEXTURE_2D, framebuffer.color.id(), 0);
+			if samples > 0 {
+				check_status();
+				gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.multisample_fbo);
+				gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+					gl::RENDERBUFFER, framebuffer.multisample_color);
+			}
+
+			// The depth buffer belongs with whichever's drawn into
+			if depth {
+				gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT,
+					gl::RENDERBUFFER, framebuffer.depth);
+			}
+			check_status();
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+		}
+		framebuffer
+	}
+
+	/// (Re)allocates storage for the renderbuffers, if there are any. The
+	/// colour texture allocates its own.
+	fn allocate(&self) {
+		let (width, height) = (self.width as GLsizei, self.height as GLsizei);
+		unsafe {
+			if self.multisample_color != 0 {
+				gl::BindRenderbuffer(gl::RENDERBUFFER, self.multisample_color);
+				gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, self.samples,
+					self.internal_format, width, height);
+			}
+			// With 0 samples this is the same as `glRenderbufferStorage`
+			if self.depth != 0 {
+				gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth);
+				gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, self.samples,
+					gl::DEPTH_COMPONENT24, width, height);
+			}
+		}
+	}
+
+	/// Resizes the framebuffer, discarding its contents. Does nothing if the
+	/// size hasn't changed.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		if width == self.width && height == self.height {
+			return;
+		}
+		self.width = width;
+		self.height = height;
+		self.color.resize(width, height);
+		self.allocate();
+	}
+
+	/// Returns the dimensions of the framebuffer, in pixels.
+	pub fn size(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+	/// Binds the framebuffer as the render target, and sets the viewport to
+	/// cover it. A multisampled framebuffer's drawn into its renderbuffers.
+	pub fn bind(&self) {
+		let fbo = if self.samples > 0 { self.multisample_fbo } else { self.fbo };
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+			gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+		}
+	}
+
+	/// Resolves what's been drawn into a multisampled framebuffer into its
+	/// colour texture, averaging the samples in each pixel, so it can be read
+	/// from. Does nothing if it isn't multisampled. Leaves the default
+	/// framebuffer bound.
+	pub fn resolve(&self) {
+		if self.samples == 0 {
+			return;
+		}
+		let (width, height) = (self.width as GLint, self.height as GLint);
+		unsafe {
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.multisample_fbo);
+			gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.fbo);
+			gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height,
+				gl::COLOR_BUFFER_BIT, gl::NEAREST);
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+		}
+	}
+
+	/// Binds the framebuffer to be read from, leaving the render target alone.
+	/// A multisampled framebuffer's read from its resolved colour texture.
+	pub fn bind_read(&self) {
+		unsafe { gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo); }
+	}
+
+	/// Binds the default framebuffer (the window) as the render target.
+	pub fn unbind() {
+		unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+	}
+
+	/// Binds the colour texture to a texture unit.
+	pub fn bind_color(&self, unit: u32) {
+		self.color.bind(unit);
+	}
+}
+
+impl Drop for Framebuffer {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteFramebuffers(1, &self.fbo);
+			if self.samples > 0 {
+				gl::DeleteFramebuffers(1, &self.multisample_fbo);
+				gl::DeleteRenderbuffers(1, &self.multisample_color);
+			}
+			if self.depth != 0 {
+				gl::DeleteRenderbuffers(1, &self.depth);
+			}
+		}
+	}
+}
+
+
+/// Returns the most samples per pixel a multisampled framebuffer can have.
+pub fn max_samples() -> u32 {
+	let mut samples = 0;
+	unsafe { gl::GetIntegerv(gl::MAX_SAMPLES, &mut samples); }
+	samples.max(0) as u32
+}
+
+/// Panics if the framebuffer bound as the render target is incomplete.
+fn check_status() {
+	let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+	if status != gl::FRAMEBUFFER_COMPLETE {
+		panic!("Framebuffer incomplete: {:#x}", status);
+	}
+}
@@ -0,0 +1,132 @@
+
+//
+//  Gamepad
+//
+
+use config::Config;
+
+use gilrs::{Axis, Button, EventType, Gilrs, GilrsBuilder};
+use cgmath::{Vector2, Vector3, Zero, InnerSpace};
+
+
+/// The movement and look input from a game controller for a single frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GamepadState {
+	/// The velocity along the camera's right, up, and forward axes, as a
+	/// fraction of full speed. Comes from the left stick and the triggers.
+	pub movement: Vector3<f32>,
+	/// How fast to turn horizontally and vertically, as a fraction of full
+	/// speed, in the same direction as mouse movement. Comes from the right
+	/// stick.
+	pub look: Vector2<f32>,
+}
+
+impl GamepadState {
+	/// Returns the state of a controller with nothing held down.
+	pub fn idle() -> GamepadState {
+		GamepadState {
+			movement: Vector3::zero(),
+			look: Vector2::zero(),
+		}
+	}
+}
+
+
+/// Reads movement and look input from the first connected game controller.
+/// Does nothing if there isn't one, or controllers aren't supported.
+pub struct Gamepad {
+	/// The controller library's context, or None if it couldn't be started.
+	gilrs: Option<Gilrs>,
+	/// How far a stick has to be pushed before it registers, as a fraction of
+	/// its full range.
+	deadzone: f32,
+	/// True if pushing the right stick up should look down.
+	invert_y: bool,
+}
+
+impl Gamepad {
+	/// Starts listening for game controllers.
+	pub fn new(config: &Config) -> Gamepad {
+		// The deadzone is applied to each stick as a whole below, rather than
+		// to each axis by the default filters
+		let gilrs = match GilrsBuilder::new().with_default_filters(false).build() {
+			Ok(gilrs) => Some(gilrs),
+			Err(err) => {
+				println!("Gamepad support unavailable: {}", err);
+				None
+			},
+		};
+
+		Gamepad {
+			gilrs: gilrs,
+			deadzone: config.gamepad_deadzone,
+			invert_y: config.gamepad_invert_y,
+		}
+	}
+
+	/// Processes the controller events since the last frame. Must be called
+	/// every frame to keep the controller state up to date.
+	pub fn update(&mut self) {
+		let gilrs = match self.gilrs {
+			Some(ref mut gilrs) => gilrs,
+			None => return,
+		};
+
+		while let Some(event) = gilrs.next_event() {
+			match event.event {
+				EventType::Connected =>
+					println!("Gamepad connected: {}", gilrs.gamepad(event.id).name()),
+				EventType::Disconnected =>
+					println!("Gamepad disconnected: {}", gilrs.gamepad(event.id).name()),
+				_ => {},
+			}
+		}
+	}
+
+	/// Returns the current input from the first connected controller.
+	pub fn state(&self) -> GamepadState {
+		let gilrs = match self.gilrs {
+			Some(ref gilrs) => gilrs,
+			None => return GamepadState::idle(),
+		};
+		let gamepad = match gilrs.gamepads().next() {
+			Some((_, gamepad)) => gamepad,
+			None => return GamepadState::idle(),
+		};
+
+		// Right trigger flies up, left trigger flies down
+		let trigger = |button| gamepad.button_data(button)
+			.map(|data| data.value())
+			.unwrap_or(0.0);
+		let vertical = trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2);
+
+		let left = self.stick(gamepad.value(Axis::LeftStickX),
+			gamepad.value(Axis::LeftStickY));
+		let mut right = self.stick(gamepad.value(Axis::RightStickX),
+			gamepad.value(Axis::RightStickY));
+
+		// Stick axes point up, but mouse movement points down the screen
+		if !self.invert_y {
+			right.y = -right.y;
+		}
+
+		GamepadState {
+			movement: Vector3::new(left.x, vertical, left.y),
+			look: right,
+		}
+	}
+
+	/// Applies the deadzone to a stick's position. The deadzone is circular,
+	/// so diagonal movement isn't snapped to the axes, and the remaining range
+	/// is rescaled so the stick still reaches full speed at its edge.
+	fn stick(&self, x: f32, y: f32) -> Vector2<f32> {
+		let position = Vector2::new(x, y);
+		let magnitude = position.magnitude();
+		if magnitude <= self.deadzone {
+			return Vector2::zero();
+		}
+
+		let scaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+		position * (scaled / magnitude)
+	}
+}
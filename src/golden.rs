@@ -0,0 +1,232 @@
+
+//
+//  Golden Image Tests
+//
+
+//! Renders the sky on the CPU and checks it against images committed under
+//! `tests/golden`, so `cargo test` catches accidental changes to how the sky
+//! looks without needing a window or an OpenGL context.
+//!
+//! Set `SKY_GOLDEN_TOLERANCE` to the largest difference allowed in a channel,
+//! out of 255, to absorb floating point differences between platforms. Set
+//! `SKY_UPDATE_GOLDEN=1` to write the current output over the golden images
+//! after an intended change. On a mismatch, the rendered image and a diff are
+//! written to `target/golden`.
+
+use capture;
+use contact_sheet::display_color;
+use sky::SkyModel;
+
+use cgmath::{Vector2, Vector3};
+use png;
+use std::env;
+use std::f32;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+
+/// The directory the golden images are kept in, relative to the crate root.
+const GOLDEN_DIR: &'static str = "tests/golden";
+
+/// The largest difference allowed in any channel of a pixel, out of 255, when
+/// `SKY_GOLDEN_TOLERANCE` isn't set. Loose enough to absorb rounding, but far
+/// tighter than the change from swapping two parameters.
+const DEFAULT_TOLERANCE: u8 = 2;
+
+/// The number of bytes in a single RGBA8 pixel.
+const BYTES_PER_PIXEL: usize = 4;
+
+/// How much differences are scaled up by in the diff image, so small ones are
+/// still visible.
+const DIFF_SCALE: u32 = 16;
+
+
+/// An RGBA8 image, with the first row at the top.
+#[derive(Clone, Debug, PartialEq)]
+struct Image {
+	/// The width of the image, in pixels.
+	width: u32,
+	/// The height of the image, in pixels.
+	height: u32,
+	/// The image's pixels.
+	pixels: Vec<u8>,
+}
+
+/// How far a rendered image is from the golden one.
+struct Comparison {
+	/// The number of pixels with a channel further off than the tolerance.
+	mismatched: usize,
+	/// The largest difference in any channel, out of 255.
+	max_difference: u8,
+	/// The difference in each channel scaled up, with mismatched pixels shown
+	/// in solid red.
+	diff: Image,
+}
+
+
+/// Renders the whole sky unwrapped into an equirectangular image, the same way
+/// as the equirectangular view in `frag.glsl`: azimuth across, with 0 in the
+/// middle, and elevation from straight up at the top to straight down at the
+/// bottom.
+fn render_equirect(sky: &SkyModel, exposure: f32, width: u32, height: u32) -> Image {
+	let mut pixels = Vec::with_capacity((width * height) as usize * BYTES_PER_PIXEL);
+	for y in 0 .. height {
+		let elevation = (0.5 - (y as f32 + 0.5) / height as f32) * f32::consts::PI;
+		for x in 0 .. width {
+			let azimuth = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * f32::consts::PI;
+			let direction = Vector3::new(elevation.cos() * azimuth.sin(),
+				elevation.sin(), elevation.cos() * azimuth.cos());
+			pixels.extend_from_slice(&display_color(sky.sample(direction), exposure));
+		}
+	}
+	Image { width: width, height: height, pixels: pixels }
+}
+
+/// Compares two images of the same size pixel by pixel.
+fn compare(expected: &Image, actual: &Image, tolerance: u8) -> Comparison {
+	assert_eq!((expected.width, expected.height), (actual.width, actual.height),
+		"images are different sizes");
+	let mut comparison = Comparison {
+		mismatched: 0,
+		max_difference: 0,
+		diff: Image {
+			width: actual.width,
+			height: actual.height,
+			pixels: Vec::with_capacity(actual.pixels.len()),
+		},
+	};
+	let pairs = expected.pixels.chunks(BYTES_PER_PIXEL)
+		.zip(actual.pixels.chunks(BYTES_PER_PIXEL));
+	for (expected, actual) in pairs {
+		let differences: Vec<u8> = expected.iter().zip(actual.iter())
+			.map(|(&a, &b)| a.max(b) - a.min(b))
+			.collect();
+		let largest = *differences.iter().max().unwrap_or(&0);
+		comparison.max_difference = comparison.max_difference.max(largest);
+		if largest > tolerance {
+			comparison.mismatched += 1;
+			comparison.diff.pixels.extend_from_slice(&[255, 0, 0, 255]);
+		} else {
+			let scale = |difference: u8| (difference as u32 * DIFF_SCALE).min(255) as u8;
+			comparison.diff.pixels.extend_from_slice(&[scale(differences[0]),
+				scale(differences[1]), scale(differences[2]), 255]);
+		}
+	}
+	comparison
+}
+
+/// Returns the tolerance set by `SKY_GOLDEN_TOLERANCE`, or the default.
+fn tolerance() -> u8 {
+	match env::var("SKY_GOLDEN_TOLERANCE") {
+		Ok(value) => value.trim().parse().unwrap_or_else(|_| panic!(
+			"SKY_GOLDEN_TOLERANCE `{}` must be a whole number up to 255", value)),
+		Err(_) => DEFAULT_TOLERANCE,
+	}
+}
+
+/// Returns the path of a file in the crate's directory.
+fn crate_path(relative: &str) -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+/// Returns the directory the output of a failed comparison is written to.
+fn output_dir() -> PathBuf {
+	let target = env::var("CARGO_TARGET_DIR")
+		.map(PathBuf::from)
+		.unwrap_or_else(|_| crate_path("target"));
+	target.join("golden")
+}
+
+/// Loads an RGBA8 PNG file.
+fn load_png(path: &Path) -> Image {
+	let file = File::open(path).unwrap_or_else(|err| panic!(
+		"couldn't open golden image {}: {}. Run with SKY_UPDATE_GOLDEN=1 to create it",
+		path.display(), err));
+	let mut reader = png::Decoder::new(BufReader::new(file)).read_info()
+		.unwrap_or_else(|err| panic!("couldn't read {}: {}", path.display(), err));
+	let (width, height) = (reader.info().width, reader.info().height);
+	let mut pixels = vec![0; (width * height) as usize * BYTES_PER_PIXEL];
+	let frame = reader.next_frame(&mut pixels)
+		.unwrap_or_else(|err| panic!("couldn't decode {}: {}", path.display(), err));
+	assert!(frame.color_type == png::ColorType::Rgba &&
+		frame.bit_depth == png::BitDepth::Eight,
+		"{} isn't an 8 bit RGBA image", path.display());
+	Image { width: width, height: height, pixels: pixels }
+}
+
+/// Saves an image to a PNG file, creating its directory if need be.
+fn save(path: &Path, image: &Image) {
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).unwrap();
+	}
+	capture::save_png(path, image.width, image.height, &image.pixels)
+		.unwrap_or_else(|err| panic!("couldn't save {}: {}", path.display(), err));
+}
+
+/// Checks an image against the golden image with the same name, or replaces
+/// the golden image if `SKY_UPDATE_GOLDEN` is set. Panics if they differ by
+/// more than the tolerance, after writing the image and a diff to the target
+/// directory.
+fn check_golden(name: &str, actual: &Image) {
+	let golden = crate_path(GOLDEN_DIR).join(format!("{}.png", name));
+	if env::var("SKY_UPDATE_GOLDEN").map(|value| value == "1").unwrap_or(false) {
+		save(&golden, actual);
+		return;
+	}
+
+	let tolerance = tolerance();
+	let comparison = compare(&load_png(&golden), actual, tolerance);
+	if comparison.mismatched > 0 {
+		let output = output_dir();
+		let actual_path = output.join(format!("{}-actual.png", name));
+		let diff_path = output.join(format!("{}-diff.png", name));
+		save(&actual_path, actual);
+		save(&diff_path, &comparison.diff);
+		panic!("{} of {} pixels differ from {} by more than {} (at most {}). \
+			Wrote the output to {} and a diff to {}", comparison.mismatched,
+			actual.width * actual.height, golden.display(), tolerance,
+			comparison.max_difference, actual_path.display(), diff_path.display());
+	}
+}
+
+/// The sky the golden image is rendered from. The turbidity's between two of
+/// the datasets' and each channel has a different albedo, so interpolation
+/// and channel mix-ups both show.
+fn reference_sky() -> SkyModel {
+	SkyModel::new(3.5, [0.1, 0.3, 0.5],
+		Vector2::new(15.0f32.to_radians(), 40.0f32.to_radians()))
+}
+
+
+#[test]
+fn equirect_matches_golden() {
+	check_golden("equirect", &render_equirect(&reference_sky(), 1.0, 64, 32));
+}
+
+#[test]
+fn tolerance_catches_parameter_changes() {
+	let expected = render_equirect(&reference_sky(), 1.0, 64, 32);
+	assert_eq!(compare(&expected, &expected, 0).mismatched, 0);
+
+	// Truncating the turbidity, or swapping the sun's elevation and azimuth
+	let truncated = SkyModel::new(3.0, [0.1, 0.3, 0.5],
+		Vector2::new(15.0f32.to_radians(), 40.0f32.to_radians()));
+	let swapped = SkyModel::new(3.5, [0.1, 0.3, 0.5],
+		Vector2::new(40.0f32.to_radians(), 15.0f32.to_radians()));
+	for sky in &[truncated, swapped] {
+		let comparison = compare(&expected, &render_equirect(sky, 1.0, 64, 32),
+			DEFAULT_TOLERANCE);
+		assert!(comparison.mismatched > 0);
+	}
+}
+
+#[test]
+fn diff_marks_mismatched_pixels() {
+	let expected = Image { width: 2, height: 1, pixels: vec![10, 20, 30, 255, 0, 0, 0, 255] };
+	let actual = Image { width: 2, height: 1, pixels: vec![11, 20, 30, 255, 0, 90, 0, 255] };
+	let comparison = compare(&expected, &actual, 2);
+	assert_eq!(comparison.mismatched, 1);
+	assert_eq!(comparison.max_difference, 90);
+	assert_eq!(comparison.diff.pixels, vec![16, 0, 0, 255, 255, 0, 0, 255]);
+}
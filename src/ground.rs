@@ -0,0 +1,216 @@
+
+//
+//  Ground Plane
+//
+
+use gl;
+use gl::types::*;
+use cgmath::{Vector3, Vector4, InnerSpace, SquareMatrix};
+
+use camera::Camera;
+use math::Handedness;
+use mesh::Mesh;
+use shader::{ShaderProgram, ShaderSources};
+use sky::{self, SkyModel};
+use sky_data;
+
+
+/// The height of the ground plane.
+const GROUND_HEIGHT: f32 = -1.0;
+
+/// Half the width of the ground plane. Kept well inside the camera's far plane
+/// so the ground isn't clipped before it reaches the horizon.
+const GROUND_EXTENT: f32 = 500.0;
+
+/// A unit quad in the horizontal plane, as (x, z) pairs. Wound anticlockwise
+/// when viewed from above, so it's culled when the camera is below it.
+static QUAD_DATA: [GLfloat; 8] = [
+	-1.0, -1.0,
+	-1.0,  1.0,
+	 1.0,  1.0,
+	 1.0, -1.0,
+];
+
+
+/// A flat plane beneath the camera, giving the horizon some context.
+pub struct Ground {
+	/// Draws the plane in a flat colour.
+	program: ShaderProgram,
+	/// The quad's vertices.
+	quad: Mesh,
+
+	/// The projection matrix uniform.
+	projection_uniform: GLint,
+	/// The view matrix uniform.
+	view_uniform: GLint,
+	/// The position of the plane's centre.
+	offset_uniform: GLint,
+	/// The plane's half width.
+	extent_uniform: GLint,
+	/// The plane's colour.
+	albedo_uniform: GLint,
+	/// The camera's position, for measuring the distance through the fog.
+	camera_uniform: GLint,
+	/// The fog density.
+	fog_density_uniform: GLint,
+	/// The light reaching the ground from the sky.
+	sky_light_uniform: GLint,
+	/// The distance between grid lines, or 0 to hide the grid.
+	grid_spacing_uniform: GLint,
+	/// The scale applied to logarithmic depth, or 0 to use the projection's.
+	log_depth_scale_uniform: GLint,
+
+	/// How quickly the ground fades into the colour of the sky with distance,
+	/// per unit.
+	pub fog_density: f32,
+	/// The distance between the lines of the grid drawn over the ground.
+	pub grid_spacing: f32,
+	/// True if the grid is drawn.
+	pub show_grid: bool,
+}
+
+impl Ground {
+	/// Creates the ground plane, with the given fog density and grid line
+	/// spacing. It's coloured with the albedo the sky is computed with, and
+	/// the fragment shader includes the sky model, to match the fog to the
+	/// sky. The grid starts hidden.
+	///
+	/// The quad's wound to face upwards once it's been mirrored from the
+	/// camera's coordinate convention, `handedness`, into OpenGL's.
+	pub fn new(fog_density: f32, grid_spacing: f32, handedness: Handedness,
+			sources: &ShaderSources) -> Ground {
+		let program = sources.program("ground_vert.glsl", "ground_frag.glsl");
+
+		// Going round the corners the other way reverses the winding
+		let corners: Vec<GLfloat> = if handedness.is_mirrored() {
+			QUAD_DATA.chunks(2).rev().flat_map(|corner| corner.to_vec()).collect()
+		} else {
+			QUAD_DATA.to_vec()
+		};
+		let quad = Mesh::new(gl::TRIANGLE_FAN, &corners, 2, program.attr("position"));
+
+		// The sky model's coefficients, used for the colour of the fog, come
+		// from the same uniform block as the sky shader's
+		program.bind_uniform_block(sky_data::BLOCK_NAME, sky_data::BINDING);
+
+		// The night sky colours never change
+		program.bind();
+		program.set_uniform_vec3(program.uniform("night_zenith"),
+			Vector3::from(sky::NIGHT_ZENITH));
+		program.set_uniform_vec3(program.uniform("night_horizon"),
+			Vector3::from(sky::NIGHT_HORIZON));
+
+		Ground {
+			projection_uniform: program.uniform("projection"),
+			view_uniform: program.uniform("view"),
+			offset_uniform: program.uniform("offset"),
+			extent_uniform: program.uniform("extent"),
+			albedo_uniform: program.uniform("albedo"),
+			camera_uniform: program.uniform("camera_position"),
+			fog_density_uniform: program.uniform("fog_density"),
+			sky_light_uniform: program.uniform("sky_light"),
+			grid_spacing_uniform: program.uniform("grid_spacing"),
+			log_depth_scale_uniform: program.uniform("log_depth_scale"),
+			program: program,
+			quad: quad,
+			fog_density: fog_density,
+			grid_spacing: grid_spacing,
+			show_grid: false,
+		}
+	}
+
+	/// Renders the ground plane beneath the camera, fogged towards the colour
+	/// of the sky, with the grid over it if it's shown.
+	///
+	/// Assumes depth testing is enabled, and the sky is drawn at the far plane
+	/// (before or after the ground) so the ground is always in front of it, and
+	/// that the sky's values have been uploaded to the SkyData block.
+	pub fn render(&self, camera: &Camera, sky: &SkyModel) {
+		// Keep the plane centred under the camera so it never runs out
+		let position = camera.position();
+		let offset = Vector3::new(position.x, GROUND_HEIGHT, position.z);
+		if !is_in_view(camera, offset) {
+			return;
+		}
+
+		self.program.bind();
+		self.program.set_uniform_mat4(self.projection_uniform, &camera.projection);
+		self.program.set_uniform_mat4(self.view_uniform, &camera.view);
+		self.program.set_uniform_vec3(self.offset_uniform, offset);
+		self.program.set_uniform_f32(self.extent_uniform, GROUND_EXTENT);
+		self.program.set_uniform_vec3(self.albedo_uniform,
+			Vector3::from(sky.albedo()));
+		self.program.set_uniform_vec3(self.camera_uniform, position);
+		self.program.set_uniform_f32(self.fog_density_uniform, self.fog_density);
+		self.program.set_uniform_f32(self.grid_spacing_uniform,
+			if self.show_grid { self.grid_spacing } else { 0.0 });
+
+		// Logarithmic depth runs from 0 at the eye to 1 at the far plane, the
+		// same as the sky's depth, so the sky stays behind the ground
+		let (_, far) = camera.clip_planes();
+		self.program.set_uniform_f32(self.log_depth_scale_uniform,
+			if camera.log_depth() { 1.0 / (1.0 + far).log2() } else { 0.0 });
+
+		// Approximate the light from the whole sky by its brightness overhead
+		let sky_light = sky.sample(Vector3::new(0.0, 1.0, 0.0));
+		self.program.set_uniform_vec3(self.sky_light_uniform, sky_light);
+		self.quad.draw();
+	}
+}
+
+
+/// Returns true if any of the plane, centred on `offset`, could be in the
+/// camera's view. It's out of view if all its corners are outside the same side
+/// of the frustum, or if all the frustum's corners are above it, like when
+/// looking up at the sky.
+fn is_in_view(camera: &Camera, offset: Vector3<f32>) -> bool {
+	let corners: Vec<Vector3<f32>> = QUAD_DATA.chunks(2)
+		.map(|corner| offset + Vector3::new(corner[0], 0.0, corner[1]) * GROUND_EXTENT)
+		.collect();
+	let outside_frustum = camera.frustum_planes().iter().any(|plane| corners.iter()
+		.all(|corner| plane.truncate().dot(*corner) + plane.w < 0.0));
+	if outside_frustum {
+		return false;
+	}
+
+	// The frustum's corners, from the corners of clip space
+	let inverse = match (camera.projection * camera.view).invert() {
+		Some(inverse) => inverse,
+		None => return true,
+	};
+	let mut above = true;
+	for &x in &[-1.0, 1.0] {
+		for &y in &[-1.0, 1.0] {
+			for &z in &[-1.0, 1.0] {
+				let corner = inverse * Vector4::new(x, y, z, 1.0);
+				above = above && corner.y / corner.w > offset.y;
+			}
+		}
+	}
+	!above
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{is_in_view, GROUND_HEIGHT};
+	use camera::Camera;
+	use config::Config;
+
+	use cgmath::Vector3;
+
+	#[test]
+	fn ground_is_culled_when_looking_up() {
+		let mut camera = Camera::new(900, 620, &Config::default());
+		let offset = Vector3::new(0.0, GROUND_HEIGHT, 0.0);
+		assert!(is_in_view(&camera, offset));
+
+		// Straight up, the plane's entirely below the bottom of the view
+		camera.rotate(0.0, 1.5);
+		assert!(!is_in_view(&camera, offset));
+
+		// Just above the horizon, the far edge of the plane is still in view
+		camera.rotate(0.0, -1.45);
+		assert!(is_in_view(&camera, offset));
+	}
+}
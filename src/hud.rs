@@ -0,0 +1,287 @@
+
+//
+//  Debug HUD
+//
+
+use gl;
+use gl::types::*;
+
+use mesh::Mesh;
+use shader::{ShaderProgram, ShaderSources};
+use texture::{Filter, Texture, TextureTarget};
+
+
+/// The width of each glyph in the font, in font pixels.
+pub const GLYPH_WIDTH: usize = 5;
+
+/// The height of each glyph in the font, in font pixels.
+pub const GLYPH_HEIGHT: usize = 7;
+
+/// The size of each font pixel on screen, in pixels.
+const SCALE: f32 = 2.0;
+
+/// The horizontal distance from one glyph to the next, in font pixels.
+const ADVANCE: f32 = 6.0;
+
+/// The vertical distance from one line to the next, in font pixels.
+const LINE_HEIGHT: f32 = 10.0;
+
+/// The distance of the text from the top left corner of the screen, in
+/// pixels.
+const MARGIN: f32 = 8.0;
+
+/// The number of floats in each vertex: the position on screen in pixels,
+/// followed by the texture coordinates in the font atlas.
+const VERTEX_COMPONENTS: i32 = 4;
+
+/// The colour of the text.
+const TEXT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// The length of each arm of the crosshair marking the colour picker's pixel,
+/// in pixels.
+const CROSSHAIR_SIZE: GLint = 6;
+
+/// The colour of the shadow drawn behind the text, so it can be read against
+/// a bright sky.
+const SHADOW_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+
+/// A 5x7 bitmap font, covering the characters the HUD shows. Each row is given
+/// from the top, with the leftmost pixel in the highest of the 5 bits. Lower
+/// case letters are drawn as upper case, and anything else is left blank.
+static FONT: [(char, [u8; GLYPH_HEIGHT]); 45] = [
+	('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+	('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+	('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+	('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+	('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+	('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+	('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+	('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+	('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+	('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+	('A', [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11]),
+	('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+	('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+	('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+	('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+	('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+	('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+	('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+	('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+	('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+	('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+	('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+	('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+	('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+	('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+	('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+	('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+	('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+	('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+	('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+	('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+	('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+	('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+	('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+	('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+	('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+	('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+	(',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+	(':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+	('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+	('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+	(')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+	('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+	('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+	('\u{b0}', [0x0C, 0x12, 0x12, 0x0C, 0x00, 0x00, 0x00]),
+];
+
+
+/// Draws lines of text in the top left corner of the screen, rebuilt from
+/// scratch every frame.
+pub struct Hud {
+	/// Draws the text from the font atlas.
+	program: ShaderProgram,
+	/// Every glyph in the font side by side, in a single row.
+	font: Texture,
+	/// The quads for each character drawn this frame.
+	batch: Mesh,
+
+	/// The font atlas sampler uniform.
+	font_uniform: GLint,
+	/// The viewport size uniform.
+	viewport_size_uniform: GLint,
+	/// The offset of the text on screen, in pixels.
+	offset_uniform: GLint,
+	/// The colour of the text.
+	color_uniform: GLint,
+}
+
+impl Hud {
+	/// Creates the HUD, building the font atlas.
+	pub fn new(sources: &ShaderSources) -> Hud {
+		let program = sources.program("hud_vert.glsl", "hud_frag.glsl");
+		let font = Texture::from_rgba8(TextureTarget::Texture2D,
+			(FONT.len() * GLYPH_WIDTH) as u32, GLYPH_HEIGHT as u32, &font_atlas(),
+			Filter::Nearest);
+		let batch = Mesh::new(gl::TRIANGLES, &[], VERTEX_COMPONENTS,
+			program.attr("vertex"));
+
+		Hud {
+			font_uniform: program.uniform("font"),
+			viewport_size_uniform: program.uniform("viewport_size"),
+			offset_uniform: program.uniform("offset"),
+			color_uniform: program.uniform("text_color"),
+			program: program,
+			font: font,
+			batch: batch,
+		}
+	}
+
+	/// Draws lines of text over whatever's in the bound framebuffer, which has
+	/// the given dimensions, in pixels.
+	///
+	/// Assumes depth testing is disabled.
+	pub fn draw(&mut self, lines: &[String], width: u32, height: u32) {
+		self.batch.update(&layout(lines));
+
+		self.program.bind();
+		self.font.bind(0);
+		self.program.set_uniform_i32(self.font_uniform, 0);
+		self.program.set_uniform_vec2(self.viewport_size_uniform, width as f32,
+			height as f32);
+
+		unsafe {
+			gl::Enable(gl::BLEND);
+			gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+		}
+
+		// The shadow's offset by a single font pixel
+		let passes = [(SHADOW_COLOR, SCALE), (TEXT_COLOR, 0.0)];
+		for &(color, offset) in passes.iter() {
+			self.program.set_uniform_vec3(self.color_uniform, color.into());
+			self.program.set_uniform_vec2(self.offset_uniform, offset, offset);
+			self.batch.draw();
+		}
+
+		unsafe { gl::Disable(gl::BLEND); }
+	}
+
+	/// Draws a small cross centred on a pixel, given from the bottom left, to
+	/// show which one the colour picker reads. It's cleared into place rather
+	/// than drawn, so it changes the clear colour.
+	pub fn draw_crosshair(&self, x: u32, y: u32) {
+		let (x, y) = (x as GLint, y as GLint);
+		let length = CROSSHAIR_SIZE * 2 + 1;
+		unsafe {
+			gl::Enable(gl::SCISSOR_TEST);
+			gl::ClearColor(TEXT_COLOR[0], TEXT_COLOR[1], TEXT_COLOR[2], 1.0);
+			gl::Scissor(x - CROSSHAIR_SIZE, y, length, 1);
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+			gl::Scissor(x, y - CROSSHAIR_SIZE, 1, length);
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+			gl::Disable(gl::SCISSOR_TEST);
+		}
+	}
+}
+
+
+/// Returns the rows of a character's glyph from the top down, with the
+/// leftmost pixel in the highest of the `GLYPH_WIDTH` bits, if the font has
+/// it.
+pub fn glyph(character: char) -> Option<[u8; GLYPH_HEIGHT]> {
+	glyph_index(character).map(|index| FONT[index].1)
+}
+
+/// Returns the index of a character's glyph in the font, if it has one.
+fn glyph_index(character: char) -> Option<usize> {
+	let character = character.to_ascii_uppercase();
+	FONT.iter().position(|&(glyph, _)| glyph == character)
+}
+
+/// Builds the font atlas as RGBA8 pixels from the bottom row up, with every
+/// glyph side by side. Set pixels are opaque white, and the rest transparent.
+fn font_atlas() -> Vec<u8> {
+	let width = FONT.len() * GLYPH_WIDTH;
+	let mut pixels = vec![0; width * GLYPH_HEIGHT * 4];
+	for (index, &(_, rows)) in FONT.iter().enumerate() {
+		for (row, &bits) in rows.iter().enumerate() {
+			let y = GLYPH_HEIGHT - 1 - row;
+			for column in 0 .. GLYPH_WIDTH {
+				if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+					let start = (y * width + index * GLYPH_WIDTH + column) * 4;
+					pixels[start .. start + 4].copy_from_slice(&[255; 4]);
+				}
+			}
+		}
+	}
+	pixels
+}
+
+/// Builds two triangles for each visible character in some lines of text, as
+/// vertices made up of a position in pixels from the top left of the screen
+/// and texture coordinates in the font atlas.
+fn layout(lines: &[String]) -> Vec<GLfloat> {
+	let glyph_u = 1.0 / FONT.len() as f32;
+	let (glyph_width, glyph_height) = (GLYPH_WIDTH as f32 * SCALE,
+		GLYPH_HEIGHT as f32 * SCALE);
+
+	let mut vertices = Vec::new();
+	for (line_index, line) in lines.iter().enumerate() {
+		let top = MARGIN + line_index as f32 * LINE_HEIGHT * SCALE;
+		for (column, character) in line.chars().enumerate() {
+			let index = match glyph_index(character) {
+				Some(index) => index,
+				None => continue,
+			};
+			let left = MARGIN + column as f32 * ADVANCE * SCALE;
+			let (right, bottom) = (left + glyph_width, top + glyph_height);
+			let (u0, u1) = (index as f32 * glyph_u, (index + 1) as f32 * glyph_u);
+
+			// Anticlockwise once the y axis is flipped to point up
+			vertices.extend_from_slice(&[
+				left, top, u0, 1.0,
+				left, bottom, u0, 0.0,
+				right, bottom, u1, 0.0,
+				left, top, u0, 1.0,
+				right, bottom, u1, 0.0,
+				right, top, u1, 1.0,
+			]);
+		}
+	}
+	vertices
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{font_atlas, glyph_index, layout, FONT, GLYPH_WIDTH, GLYPH_HEIGHT,
+		VERTEX_COMPONENTS};
+
+	#[test]
+	fn font_glyphs_are_unique_and_fit() {
+		for (index, &(character, ref rows)) in FONT.iter().enumerate() {
+			assert_eq!(glyph_index(character), Some(index),
+				"'{}' appears more than once", character);
+			assert!(rows.iter().all(|&row| row < 1 << GLYPH_WIDTH),
+				"'{}' is wider than {} pixels", character, GLYPH_WIDTH);
+		}
+		assert_eq!(font_atlas().len(), FONT.len() * GLYPH_WIDTH * GLYPH_HEIGHT * 4);
+	}
+
+	#[test]
+	fn layout_skips_blank_characters() {
+		// Lower case is drawn as upper case, and spaces and unknown characters
+		// only take up room
+		assert_eq!(glyph_index('a'), glyph_index('A'));
+		assert_eq!(glyph_index(' '), None);
+		let vertices = layout(&["Sun 1.5".to_string(), "? x".to_string()]);
+		assert_eq!(vertices.len(), 7 * 6 * VERTEX_COMPONENTS as usize);
+
+		// The second line's below the first, and its 'x' is two characters in
+		let first = &vertices[.. 4];
+		let x = &vertices[6 * 6 * VERTEX_COMPONENTS as usize ..];
+		assert!(x[1] > first[1]);
+		assert!(x[0] > first[0]);
+	}
+}
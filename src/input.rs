@@ -3,8 +3,10 @@
 //  Input
 //
 
+use error::SkyError;
+
 use glutin;
-use glutin::{Event, Window, ElementState};
+use glutin::{Event, Window, ElementState, CursorState};
 
 
 /// The number of virtual key codes we need to keep track of.
@@ -13,17 +15,53 @@ const KEYS_COUNT: usize = 134;
 /// The number of mouse buttons we need to keep track of.
 const MOUSE_BUTTONS_COUNT: usize = 3;
 
+/// The key that closes the window.
+const QUIT_KEY: Key = Key::Escape;
+
 
 /// Rename the virtual key code to `Key`.
 pub type Key = glutin::VirtualKeyCode;
 
 
+/// The modifier keys that can be held down, with the keys on the left and
+/// right of the keyboard treated the same.
+///
+/// Left shift is also held to fly down, so shortcuts should be combined with
+/// control or alt instead, or they'd go off while the player's moving.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Modifiers {
+	/// Either control key.
+	pub ctrl: bool,
+	/// Either shift key.
+	pub shift: bool,
+	/// Either alt key.
+	pub alt: bool,
+	/// Either logo key, like the Windows or command key.
+	pub logo: bool,
+}
+
+impl Modifiers {
+	/// No modifiers held.
+	pub const NONE: Modifiers = Modifiers { ctrl: false, shift: false, alt: false, logo: false };
+
+	/// Just control held.
+	pub const CTRL: Modifiers = Modifiers { ctrl: true, ..Modifiers::NONE };
+
+	/// Just alt held.
+	pub const ALT: Modifiers = Modifiers { alt: true, ..Modifiers::NONE };
+}
+
+
 /// Persists input data across frames.
 pub struct Input {
 	/// An array indexed by virtual key codes, set to true if a key is held
 	/// down.
 	keys_down: [bool; KEYS_COUNT],
 
+	/// An array indexed by virtual key codes, set to true if a key was just
+	/// pressed. These values are only true for a single frame.
+	keys_pressed: [bool; KEYS_COUNT],
+
 	/// An array indexed by mouse buttons, set to true if a mouse button is held
 	/// down.
 	mouse_buttons_down: [bool; MOUSE_BUTTONS_COUNT],
@@ -38,6 +76,10 @@ pub struct Input {
 	/// The most recent movement of the mouse along the y axis.
 	mouse_delta_y: f32,
 
+	/// True if the mouse has moved since the cursor was last moved back to the
+	/// centre of the window.
+	cursor_moved: bool,
+
 	/// True if the main window is open.
 	window_open: bool,
 
@@ -52,21 +94,46 @@ pub struct Input {
 }
 
 impl Input {
-	/// Creates a new input handler, using the window's dimensions.
-	pub fn new(window: &Window) -> Input {
-		let (point_width, height) = window.get_inner_size_points().unwrap();
-		let (pixel_width, _) = window.get_inner_size_pixels().unwrap();
-		let scale = pixel_width as f32 / point_width as f32;
+	/// Creates a new input handler, using the window's dimensions. Some
+	/// platforms can't report the size of a window yet, so the size it was
+	/// created with, in points, is used instead, with one pixel per point.
+	pub fn new(window: &Window, width: u32, height: u32) -> Input {
+		match window_size(window) {
+			Some((width, height, scale)) => Input::with_size(width, height, scale),
+			None => {
+				eprintln!("Warning: couldn't measure the window, assuming it's \
+					{}x{} points", width, height);
+				Input::with_size(width, height, 1.0)
+			},
+		}
+	}
+
+	/// Creates a new input handler for a window with the given dimensions, in
+	/// points, and scale factor.
+	fn with_size(width: u32, height: u32, scale_factor: f32) -> Input {
 		Input {
 			keys_down: [false; KEYS_COUNT],
+			keys_pressed: [false; KEYS_COUNT],
 			mouse_buttons_down: [false; MOUSE_BUTTONS_COUNT],
 			mouse_buttons_pressed: [false; MOUSE_BUTTONS_COUNT],
 			mouse_delta_x: 0.0,
 			mouse_delta_y: 0.0,
+			cursor_moved: false,
 			window_open: true,
-			width: point_width,
+			width: width,
 			height: height,
-			scale_factor: scale,
+			scale_factor: scale_factor,
+		}
+	}
+
+	/// Measures the window again after it's been resized or moved to a display
+	/// with a different scale factor, so the cursor's kept in the centre. Keeps
+	/// the old size if the window's been minimised.
+	pub fn resize(&mut self, window: &Window) {
+		if let Some((width, height, scale)) = window_size(window) {
+			self.width = width;
+			self.height = height;
+			self.scale_factor = scale;
 		}
 	}
 
@@ -86,6 +153,42 @@ impl Input {
 		}
 	}
 
+	/// Returns true if a key was just pressed, ignoring key repeats while it's
+	/// held down.
+	pub fn was_key_pressed(&self, key: Key) -> bool {
+		let index = key as usize;
+		if index < KEYS_COUNT {
+			self.keys_pressed[index]
+		} else {
+			// We're not keeping track of the requested key
+			false
+		}
+	}
+
+	/// Returns true if any key was just pressed, ignoring key repeats.
+	pub fn any_key_pressed(&self) -> bool {
+		self.keys_pressed.iter().any(|&pressed| pressed)
+	}
+
+	/// Returns the modifier keys held down.
+	pub fn modifiers(&self) -> Modifiers {
+		let either = |left, right| self.is_key_down(left) || self.is_key_down(right);
+		Modifiers {
+			ctrl: either(Key::LControl, Key::RControl),
+			shift: either(Key::LShift, Key::RShift),
+			// Some platforms report the alt keys as the menu keys
+			alt: either(Key::LAlt, Key::RAlt) || either(Key::LMenu, Key::RMenu),
+			logo: either(Key::LWin, Key::RWin),
+		}
+	}
+
+	/// Returns true if a key was just pressed while exactly the given modifiers
+	/// were held, so Ctrl+S doesn't also count as Ctrl+Shift+S, or as a plain
+	/// S. Like `was_key_pressed`, key repeats don't count.
+	pub fn was_combo_pressed(&self, modifiers: Modifiers, key: Key) -> bool {
+		self.was_key_pressed(key) && self.modifiers() == modifiers
+	}
+
 	/// Returns true if a mouse button is held down.
 	pub fn is_mouse_down(&self, button: MouseButton) -> bool {
 		let index = button as usize;
@@ -108,25 +211,23 @@ impl Input {
 		}
 	}
 
-	/// Returns true as long as the main window is open.
+	/// Returns true as long as the main window is open, and the quit key
+	/// hasn't been pressed.
 	pub fn window_is_open(&self) -> bool {
 		self.window_open
 	}
 
-	/// Called when the mouse moves.
-	fn mouse_move(&mut self, x: i32, y: i32, window: &Window) {
-		// Convert the mouse coordinates to points instead of pixels
-		let real_x = x as f32 / self.scale_factor;
-		let real_y = y as f32 / self.scale_factor;
-
-		// Calculate the new deltas
-		let center_x = self.width / 2;
-		let center_y = self.height / 2;
-		self.mouse_delta_x = center_x as f32 - real_x;
-		self.mouse_delta_y = center_y as f32 - real_y;
+	/// Returns the centre of the window, in points, where the cursor is kept.
+	fn center(&self) -> (u32, u32) {
+		(self.width / 2, self.height / 2)
+	}
 
-		// Reset the mouse location in the window
-		window.set_cursor_position(center_x as i32, center_y as i32).unwrap();
+	/// Called when the mouse moves.
+	fn mouse_move(&mut self, x: i32, y: i32) {
+		let (dx, dy) = cursor_delta(x, y, self.center(), self.scale_factor);
+		self.mouse_delta_x = dx;
+		self.mouse_delta_y = dy;
+		self.cursor_moved = true;
 	}
 
 	/// Called when a key is pressed or released.
@@ -135,6 +236,13 @@ impl Input {
 
 		// Only if we're tracking the key
 		if index < KEYS_COUNT {
+			// Only count a press on the transition, not for key repeats
+			if is_down && !self.keys_down[index] {
+				self.keys_pressed[index] = true;
+				if key == QUIT_KEY {
+					self.window_open = false;
+				}
+			}
 			self.keys_down[index] = is_down;
 		}
 	}
@@ -156,10 +264,14 @@ impl Input {
 	}
 
 	/// Called when an event occurs to update state.
-	pub fn handle_event(&mut self, event: Event, window: &Window) {
+	///
+	/// Mouse movement is measured from the centre of the window, so the cursor
+	/// needs moving back there with `recenter_cursor` once the frame's events
+	/// have been handled.
+	pub fn handle_event(&mut self, event: Event) {
 		match event {
 			Event::Closed => self.window_open = false,
-			Event::MouseMoved(x, y) => self.mouse_move(x, y, window),
+			Event::MouseMoved(x, y) => self.mouse_move(x, y),
 			Event::KeyboardInput(action, _, Some(key)) =>
 				self.key(key, action == ElementState::Pressed),
 			Event::MouseInput(action, button) =>
@@ -168,13 +280,70 @@ impl Input {
 		}
 	}
 
+	/// Moves the cursor back to the centre of the window, if the mouse has
+	/// moved since it was last there.
+	pub fn recenter_cursor(&mut self, window: &Window) -> Result<(), SkyError> {
+		if self.cursor_moved {
+			let (center_x, center_y) = self.center();
+			window.set_cursor_position(center_x as i32, center_y as i32)
+				.map_err(|()| SkyError::Cursor("couldn't move the cursor".to_string()))?;
+			self.cursor_moved = false;
+		}
+		Ok(())
+	}
+
+	/// Returns the keyboard and mouse state for the current frame, so it can be
+	/// recorded.
+	pub fn snapshot(&self) -> InputFrame {
+		let set = |flags: &[bool]| flags.iter()
+			.enumerate()
+			.filter(|&(_, &flag)| flag)
+			.map(|(index, _)| index)
+			.collect();
+		InputFrame {
+			keys_down: set(&self.keys_down),
+			keys_pressed: set(&self.keys_pressed),
+			mouse_buttons_down: set(&self.mouse_buttons_down),
+			mouse_buttons_pressed: set(&self.mouse_buttons_pressed),
+			mouse_delta: self.mouse_delta(),
+		}
+	}
+
+	/// Replaces the keyboard and mouse state with a recorded frame's, in place
+	/// of any live events. Pressing the quit key in the recording closes the
+	/// window, as it did when it was recorded.
+	pub fn replay(&mut self, frame: &InputFrame) {
+		fn fill(flags: &mut [bool], indices: &[usize]) {
+			for flag in flags.iter_mut() {
+				*flag = false;
+			}
+			for &index in indices {
+				if index < flags.len() {
+					flags[index] = true;
+				}
+			}
+		}
+		fill(&mut self.keys_down, &frame.keys_down);
+		fill(&mut self.keys_pressed, &frame.keys_pressed);
+		fill(&mut self.mouse_buttons_down, &frame.mouse_buttons_down);
+		fill(&mut self.mouse_buttons_pressed, &frame.mouse_buttons_pressed);
+		self.mouse_delta_x = frame.mouse_delta.0;
+		self.mouse_delta_y = frame.mouse_delta.1;
+		if self.was_key_pressed(QUIT_KEY) {
+			self.window_open = false;
+		}
+	}
+
 	/// Called once a frame to update state.
 	pub fn update(&mut self) {
 		// Reset the mouse deltas
 		self.mouse_delta_x = 0.0;
 		self.mouse_delta_y = 0.0;
 
-		// Reset the button pressed states
+		// Reset the key and button pressed states
+		for i in 0 .. KEYS_COUNT {
+			self.keys_pressed[i] = false;
+		}
 		for i in 0 .. MOUSE_BUTTONS_COUNT {
 			self.mouse_buttons_pressed[i] = false;
 		}
@@ -182,6 +351,80 @@ impl Input {
 }
 
 
+/// The keyboard and mouse state for a single frame, which can be recorded and
+/// played back in place of live events.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputFrame {
+	/// The virtual key codes of the keys held down.
+	pub keys_down: Vec<usize>,
+	/// The virtual key codes of the keys pressed this frame.
+	pub keys_pressed: Vec<usize>,
+	/// The mouse buttons held down.
+	pub mouse_buttons_down: Vec<usize>,
+	/// The mouse buttons pressed this frame.
+	pub mouse_buttons_pressed: Vec<usize>,
+	/// The mouse's movement this frame, in points.
+	pub mouse_delta: (f32, f32),
+}
+
+
+/// Hides the cursor and keeps it in the centre of the window, to fake
+/// capturing it. The cursor's shown again when this is dropped, including when
+/// unwinding from a panic, so it's never left hidden once the program ends.
+pub struct CursorCapture<'a> {
+	/// The window the cursor's captured by.
+	window: &'a Window,
+}
+
+impl<'a> CursorCapture<'a> {
+	/// Moves the cursor to the centre of the window and hides it. If the
+	/// window can't be measured yet, the cursor's left where it is until it's
+	/// first recentred.
+	pub fn new(window: &'a Window) -> Result<CursorCapture<'a>, SkyError> {
+		if let Some((width, height)) = window.get_inner_size_points() {
+			window.set_cursor_position(width as i32 / 2, height as i32 / 2)
+				.map_err(|()| SkyError::Cursor("couldn't move the cursor".to_string()))?;
+		}
+		window.set_cursor_state(CursorState::Hide).map_err(SkyError::Cursor)?;
+		Ok(CursorCapture { window: window })
+	}
+}
+
+impl<'a> Drop for CursorCapture<'a> {
+	fn drop(&mut self) {
+		// Also releases the cursor if it's grabbed. There's nothing to be done
+		// if this fails, and the window's about to close anyway
+		let _ = self.window.set_cursor_state(CursorState::Normal);
+	}
+}
+
+
+/// Returns the size of a window in points, and the number of pixels per point,
+/// or None if the window's closed or has no area. If the size in pixels isn't
+/// known, there's assumed to be one pixel per point.
+fn window_size(window: &Window) -> Option<(u32, u32, f32)> {
+	let (point_width, point_height) = window.get_inner_size_points()?;
+	if point_width == 0 || point_height == 0 {
+		return None;
+	}
+	let scale = window.get_inner_size_pixels()
+		.map_or(1.0, |(pixel_width, _)| pixel_width as f32 / point_width as f32);
+	Some((point_width, point_height, scale))
+}
+
+/// Returns how far the cursor is from the centre of the window, in points,
+/// where the cursor's position is in pixels, the centre is in points, and
+/// `scale_factor` is the number of pixels per point. Movement left and up is
+/// positive.
+pub fn cursor_delta(x: i32, y: i32, center: (u32, u32), scale_factor: f32)
+		-> (f32, f32) {
+	// Convert the mouse coordinates to points instead of pixels
+	let real_x = x as f32 / scale_factor;
+	let real_y = y as f32 / scale_factor;
+	(center.0 as f32 - real_x, center.1 as f32 - real_y)
+}
+
+
 /// All mouse buttons that we care about.
 pub enum MouseButton {
 	Left,
@@ -200,3 +443,234 @@ impl MouseButton {
 		}
 	}
 }
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glutin::VirtualKeyCode;
+
+	/// Returns an input handler for an 800x600 window.
+	fn input() -> Input {
+		Input::with_size(800, 600, 1.0)
+	}
+
+	fn key_event(key: Key, state: ElementState) -> Event {
+		Event::KeyboardInput(state, 0, Some(key))
+	}
+
+	fn mouse_event(button: glutin::MouseButton, state: ElementState) -> Event {
+		Event::MouseInput(state, button)
+	}
+
+	#[test]
+	fn key_down_until_released() {
+		let mut input = input();
+		assert!(!input.is_key_down(VirtualKeyCode::W));
+
+		input.handle_event(key_event(VirtualKeyCode::W, ElementState::Pressed));
+		assert!(input.is_key_down(VirtualKeyCode::W));
+		assert!(!input.is_key_down(VirtualKeyCode::S));
+
+		// Held keys stay down across frames
+		input.update();
+		assert!(input.is_key_down(VirtualKeyCode::W));
+
+		input.handle_event(key_event(VirtualKeyCode::W, ElementState::Released));
+		assert!(!input.is_key_down(VirtualKeyCode::W));
+	}
+
+	#[test]
+	fn key_pressed_for_one_frame() {
+		let mut input = input();
+		input.handle_event(key_event(VirtualKeyCode::C, ElementState::Pressed));
+		assert!(input.was_key_pressed(VirtualKeyCode::C));
+
+		input.update();
+		assert!(!input.was_key_pressed(VirtualKeyCode::C));
+
+		// Key repeats while held don't count as new presses
+		input.handle_event(key_event(VirtualKeyCode::C, ElementState::Pressed));
+		assert!(!input.was_key_pressed(VirtualKeyCode::C));
+	}
+
+	#[test]
+	fn any_key_pressed_for_one_frame() {
+		let mut input = input();
+		assert!(!input.any_key_pressed());
+		input.handle_event(key_event(VirtualKeyCode::Q, ElementState::Pressed));
+		assert!(input.any_key_pressed());
+
+		// Holding the key down doesn't count again, but another key does
+		input.update();
+		assert!(!input.any_key_pressed());
+		input.handle_event(key_event(VirtualKeyCode::Q, ElementState::Pressed));
+		assert!(!input.any_key_pressed());
+		input.handle_event(key_event(VirtualKeyCode::LShift, ElementState::Pressed));
+		assert!(input.any_key_pressed());
+
+		// Neither do releases or mouse buttons
+		input.update();
+		input.handle_event(key_event(VirtualKeyCode::Q, ElementState::Released));
+		input.handle_event(mouse_event(glutin::MouseButton::Left, ElementState::Pressed));
+		assert!(!input.any_key_pressed());
+	}
+
+	#[test]
+	fn modifiers_either_side() {
+		let mut input = input();
+		assert_eq!(input.modifiers(), Modifiers::NONE);
+
+		input.handle_event(key_event(VirtualKeyCode::RControl, ElementState::Pressed));
+		assert_eq!(input.modifiers(), Modifiers::CTRL);
+		input.handle_event(key_event(VirtualKeyCode::LShift, ElementState::Pressed));
+		input.handle_event(key_event(VirtualKeyCode::RShift, ElementState::Pressed));
+		assert_eq!(input.modifiers(), Modifiers { shift: true, ..Modifiers::CTRL });
+
+		// Releasing one shift key leaves the other held
+		input.handle_event(key_event(VirtualKeyCode::LShift, ElementState::Released));
+		assert!(input.modifiers().shift);
+		input.handle_event(key_event(VirtualKeyCode::RShift, ElementState::Released));
+		input.handle_event(key_event(VirtualKeyCode::RControl, ElementState::Released));
+		input.handle_event(key_event(VirtualKeyCode::LMenu, ElementState::Pressed));
+		input.handle_event(key_event(VirtualKeyCode::LWin, ElementState::Pressed));
+		assert_eq!(input.modifiers(), Modifiers { alt: true, logo: true, ..Modifiers::NONE });
+	}
+
+	#[test]
+	fn combo_needs_exact_modifiers() {
+		let mut input = input();
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Pressed));
+		assert!(input.was_combo_pressed(Modifiers::NONE, VirtualKeyCode::S));
+		assert!(!input.was_combo_pressed(Modifiers::CTRL, VirtualKeyCode::S));
+
+		input.update();
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Released));
+		input.handle_event(key_event(VirtualKeyCode::LControl, ElementState::Pressed));
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Pressed));
+		assert!(input.was_combo_pressed(Modifiers::CTRL, VirtualKeyCode::S));
+		assert!(!input.was_combo_pressed(Modifiers::NONE, VirtualKeyCode::S));
+
+		// Nor do key repeats, or extra modifiers
+		input.update();
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Pressed));
+		assert!(!input.was_combo_pressed(Modifiers::CTRL, VirtualKeyCode::S));
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Released));
+		input.handle_event(key_event(VirtualKeyCode::RShift, ElementState::Pressed));
+		input.handle_event(key_event(VirtualKeyCode::S, ElementState::Pressed));
+		assert!(!input.was_combo_pressed(Modifiers::CTRL, VirtualKeyCode::S));
+		assert!(input.was_combo_pressed(Modifiers { shift: true, ..Modifiers::CTRL },
+			VirtualKeyCode::S));
+	}
+
+	#[test]
+	fn mouse_pressed_for_one_frame() {
+		let mut input = input();
+		input.handle_event(mouse_event(glutin::MouseButton::Left,
+			ElementState::Pressed));
+		assert!(input.is_mouse_down(MouseButton::Left));
+		assert!(input.was_mouse_pressed(MouseButton::Left));
+		assert!(!input.is_mouse_down(MouseButton::Right));
+
+		input.update();
+		assert!(input.is_mouse_down(MouseButton::Left));
+		assert!(!input.was_mouse_pressed(MouseButton::Left));
+
+		input.handle_event(mouse_event(glutin::MouseButton::Left,
+			ElementState::Released));
+		assert!(!input.is_mouse_down(MouseButton::Left));
+	}
+
+	#[test]
+	fn untracked_inputs_ignored() {
+		let mut input = input();
+
+		// Past the end of the tracked key codes
+		assert!(VirtualKeyCode::Yen as usize >= KEYS_COUNT);
+		input.handle_event(key_event(VirtualKeyCode::Yen, ElementState::Pressed));
+		assert!(!input.is_key_down(VirtualKeyCode::Yen));
+		assert!(!input.was_key_pressed(VirtualKeyCode::Yen));
+
+		// Keys without a virtual key code, and other mouse buttons
+		input.handle_event(Event::KeyboardInput(ElementState::Pressed, 0, None));
+		input.handle_event(mouse_event(glutin::MouseButton::Other(4),
+			ElementState::Pressed));
+		assert!(input.keys_down.iter().all(|&down| !down));
+		assert!(input.mouse_buttons_down.iter().all(|&down| !down));
+	}
+
+	#[test]
+	fn mouse_delta_reset_each_frame() {
+		let mut input = input();
+		input.handle_event(Event::MouseMoved(410, 290));
+		assert_eq!(input.mouse_delta(), (-10.0, 10.0));
+		assert!(input.cursor_moved);
+
+		input.update();
+		assert_eq!(input.mouse_delta(), (0.0, 0.0));
+	}
+
+	#[test]
+	fn replay_restores_snapshot() {
+		let mut live = input();
+		live.handle_event(key_event(VirtualKeyCode::W, ElementState::Pressed));
+		live.update();
+		live.handle_event(key_event(VirtualKeyCode::C, ElementState::Pressed));
+		live.handle_event(mouse_event(glutin::MouseButton::Right,
+			ElementState::Pressed));
+		live.handle_event(Event::MouseMoved(405, 280));
+		let frame = live.snapshot();
+		assert_eq!(frame.keys_down, vec![VirtualKeyCode::C as usize,
+			VirtualKeyCode::W as usize]);
+		assert_eq!(frame.keys_pressed, vec![VirtualKeyCode::C as usize]);
+
+		// Replaying clears anything held down live
+		let mut replayed = input();
+		replayed.handle_event(key_event(VirtualKeyCode::S, ElementState::Pressed));
+		replayed.replay(&frame);
+		assert_eq!(replayed.snapshot(), frame);
+		assert!(replayed.is_key_down(VirtualKeyCode::W));
+		assert!(!replayed.is_key_down(VirtualKeyCode::S));
+		assert!(replayed.was_mouse_pressed(MouseButton::Right));
+		assert_eq!(replayed.mouse_delta(), (-5.0, 20.0));
+		assert!(replayed.window_is_open());
+
+		// Including quitting
+		replayed.replay(&InputFrame {
+			keys_pressed: vec![QUIT_KEY as usize],
+			..frame
+		});
+		assert!(!replayed.window_is_open());
+	}
+
+	#[test]
+	fn window_closed() {
+		let mut input = input();
+		assert!(input.window_is_open());
+		input.handle_event(Event::Closed);
+		assert!(!input.window_is_open());
+	}
+
+	#[test]
+	fn quit_key_closes_window() {
+		let mut input = input();
+		input.handle_event(key_event(VirtualKeyCode::Escape, ElementState::Released));
+		assert!(input.window_is_open());
+		input.handle_event(key_event(VirtualKeyCode::Escape, ElementState::Pressed));
+		assert!(!input.window_is_open());
+	}
+
+	#[test]
+	fn cursor_delta_from_center() {
+		assert_eq!(cursor_delta(400, 300, (400, 300), 1.0), (0.0, 0.0));
+		assert_eq!(cursor_delta(390, 320, (400, 300), 1.0), (10.0, -20.0));
+	}
+
+	#[test]
+	fn cursor_delta_hidpi() {
+		// The cursor is in pixels, but the centre and delta are in points
+		assert_eq!(cursor_delta(800, 600, (400, 300), 2.0), (0.0, 0.0));
+		assert_eq!(cursor_delta(820, 580, (400, 300), 2.0), (-10.0, 10.0));
+		assert_eq!(cursor_delta(630, 450, (400, 300), 1.5), (-20.0, 0.0));
+	}
+}
@@ -0,0 +1,22 @@
+
+//
+//  Sky Model Library
+//
+
+//! The CPU side of the sky model, split out from the viewer so it can be
+//! benchmarked and tested without an OpenGL context.
+//!
+//! Only depends on cgmath. Build with `--no-default-features --features model`
+//! to leave out the viewer's OpenGL and windowing dependencies.
+
+extern crate cgmath;
+
+pub mod math;
+#[cfg(feature = "model")]
+mod hosek;
+#[cfg(feature = "model")]
+pub mod sky;
+#[cfg(feature = "model")]
+pub mod lut;
+#[cfg(feature = "model")]
+pub mod datasets;
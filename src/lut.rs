@@ -0,0 +1,243 @@
+
+//
+//  Baked Sky Lookup Table
+//
+
+use sky::{SkyCoefficients, SkyModel};
+
+use cgmath::{Vector2, Vector3};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+
+/// Identifies a baked lookup table file.
+const MAGIC: &'static [u8; 4] = b"HWLT";
+
+/// The version of the file format, increased whenever it changes.
+const VERSION: u32 = 1;
+
+/// The number of floats stored for each elevation: the elevation itself,
+/// followed by the coefficient vectors.
+const SLICE_FLOATS: usize = 1 + SkyCoefficients::COUNT * 3;
+
+
+/// The sky model's coefficients baked for a set of sun elevations, so a game
+/// engine can load them and interpolate without running the model itself.
+///
+/// The coefficients only depend on the sun's elevation, not its azimuth. To
+/// find them for an elevation between two slices, interpolate each of the
+/// coefficient vectors linearly between the slices either side (as `interpolate` does),
+/// then evaluate the model in the shader the same way as `hosek_wilkie.glsl`.
+/// Elevations outside the table should be clamped to its ends.
+///
+/// On disk, everything is stored little endian: the 4 bytes `HWLT`, the format
+/// version and the number of slices as `u32`s, the turbidity and the albedo
+/// for each colour channel as `f32`s, then for each slice the elevation in
+/// radians followed by the 10 coefficient vectors, all as `f32`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkyLut {
+	/// The atmospheric turbidity the table was baked with.
+	pub turbidity: f32,
+	/// The ground albedo the table was baked with, for each colour channel.
+	pub albedo: [f32; 3],
+	/// The sun elevation of each slice, in radians, in increasing order.
+	pub elevations: Vec<f32>,
+	/// The Hosek-Wilkie coefficients for each slice.
+	pub coefficients: Vec<SkyCoefficients>,
+}
+
+impl SkyLut {
+	/// Returns the coefficients for a sun elevation, in radians, interpolated
+	/// between the slices either side of it.
+	///
+	/// Panics if the table is empty.
+	pub fn interpolate(&self, elevation: f32) -> SkyCoefficients {
+		let last = self.elevations.len() - 1;
+		let next = self.elevations.iter()
+			.position(|&slice| slice >= elevation)
+			.unwrap_or(last);
+		if next == 0 || self.elevations[next] <= elevation {
+			// Exactly on a slice, or clamped to the ends of the table
+			return self.coefficients[next];
+		}
+
+		let (lower, upper) = (self.elevations[next - 1], self.elevations[next]);
+		let t = (elevation - lower) / (upper - lower);
+		self.coefficients[next - 1].lerp(&self.coefficients[next], t)
+	}
+
+	/// Saves the table to a file.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+
+	/// Loads a table from a file.
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<SkyLut> {
+		SkyLut::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	/// Writes the table in the binary format described above.
+	pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		writer.write_all(MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+		writer.write_all(&(self.elevations.len() as u32).to_le_bytes())?;
+		write_f32(writer, self.turbidity)?;
+		for &albedo in self.albedo.iter() {
+			write_f32(writer, albedo)?;
+		}
+
+		for (&elevation, coefficients) in self.elevations.iter().zip(self.coefficients.iter()) {
+			write_f32(writer, elevation)?;
+			for param in coefficients.as_uniform_slice() {
+				write_f32(writer, param.x)?;
+				write_f32(writer, param.y)?;
+				write_f32(writer, param.z)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads a table in the binary format described above.
+	pub fn read<R: Read>(reader: &mut R) -> io::Result<SkyLut> {
+		let mut magic = [0; 4];
+		reader.read_exact(&mut magic)?;
+		if &magic != MAGIC {
+			return Err(invalid_data("not a sky lookup table"));
+		}
+		let version = read_u32(reader)?;
+		if version != VERSION {
+			return Err(invalid_data(&format!("unsupported version {}", version)));
+		}
+
+		let count = read_u32(reader)? as usize;
+		let turbidity = read_f32(reader)?;
+		let mut albedo = [0.0; 3];
+		for channel in albedo.iter_mut() {
+			*channel = read_f32(reader)?;
+		}
+
+		// Don't trust the count to size the buffers, in case the file's been
+		// truncated or corrupted
+		let mut elevations = Vec::new();
+		let mut coefficients = Vec::new();
+		let mut slice = [0.0; SLICE_FLOATS];
+		for _ in 0 .. count {
+			for value in slice.iter_mut() {
+				*value = read_f32(reader)?;
+			}
+			elevations.push(slice[0]);
+			let vectors: Vec<Vector3<f32>> = slice[1 ..].chunks(3)
+				.map(|v| Vector3::new(v[0], v[1], v[2]))
+				.collect();
+			coefficients.push(SkyCoefficients::from_slice(&vectors));
+		}
+
+		Ok(SkyLut {
+			turbidity: turbidity,
+			albedo: albedo,
+			elevations: elevations,
+			coefficients: coefficients,
+		})
+	}
+}
+
+impl SkyModel {
+	/// Bakes the coefficients for each of a list of sun elevations, in
+	/// radians, using this model's turbidity and albedo.
+	///
+	/// Panics if the elevations aren't in increasing order.
+	pub fn bake_lut(&self, elevations: &[f32]) -> SkyLut {
+		assert!(elevations.windows(2).all(|pair| pair[0] < pair[1]),
+			"elevations must be in increasing order");
+		let mut model = SkyModel::new(self.turbidity(), self.albedo(),
+			Vector2::new(0.0, 0.0));
+		let coefficients = elevations.iter()
+			.map(|&elevation| {
+				model.set_sun(Vector2::new(elevation, 0.0));
+				*model.coefficients()
+			})
+			.collect();
+
+		SkyLut {
+			turbidity: self.turbidity(),
+			albedo: self.albedo(),
+			elevations: elevations.to_vec(),
+			coefficients: coefficients,
+		}
+	}
+}
+
+
+/// Writes a float in little endian order.
+fn write_f32<W: Write>(writer: &mut W, value: f32) -> io::Result<()> {
+	writer.write_all(&value.to_bits().to_le_bytes())
+}
+
+/// Reads a little endian unsigned integer.
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads a little endian float.
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+	read_u32(reader).map(f32::from_bits)
+}
+
+/// Creates an error for a file that isn't a valid lookup table.
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::SkyLut;
+	use sky::SkyModel;
+
+	use cgmath::{Vector2, InnerSpace};
+	use std::f32::consts::FRAC_PI_2;
+
+	/// A few elevations from the horizon to straight up.
+	const ELEVATIONS: [f32; 4] = [0.0, 0.3, 0.8, FRAC_PI_2];
+
+	#[test]
+	fn round_trip() {
+		let sky = SkyModel::new(3.5, [0.2, 0.3, 0.4], Vector2::new(0.0, 0.0));
+		let lut = sky.bake_lut(&ELEVATIONS);
+		let mut bytes = Vec::new();
+		lut.write(&mut bytes).unwrap();
+		assert_eq!(bytes.len(), 4 + 4 + 4 + 4 * 4 + ELEVATIONS.len() * 31 * 4);
+		assert_eq!(SkyLut::read(&mut &bytes[..]).unwrap(), lut);
+
+		// Anything cut short is an error rather than a partial table
+		assert!(SkyLut::read(&mut &bytes[.. bytes.len() - 1]).is_err());
+		assert!(SkyLut::read(&mut &b"nope"[..]).is_err());
+	}
+
+	#[test]
+	fn slices_match_model() {
+		let sky = SkyModel::new(3.5, [0.2; 3], Vector2::new(0.0, 0.0));
+		let lut = sky.bake_lut(&ELEVATIONS);
+		for (&elevation, coefficients) in ELEVATIONS.iter().zip(lut.coefficients.iter()) {
+			let model = SkyModel::new(3.5, [0.2; 3], Vector2::new(elevation, 0.0));
+			assert_eq!(model.coefficients(), coefficients);
+			assert_eq!(&lut.interpolate(elevation), coefficients);
+		}
+
+		// Halfway between two slices is halfway between their coefficients,
+		// and anything past the ends is clamped
+		let halfway = lut.interpolate(0.55);
+		let (values, lower, upper) = (halfway.as_uniform_slice(),
+			lut.coefficients[1].as_uniform_slice(), lut.coefficients[2].as_uniform_slice());
+		for ((&value, &lower), &upper) in values.iter().zip(lower.iter()).zip(upper.iter()) {
+			assert!((value - (lower + upper) / 2.0).magnitude() < 1e-5);
+		}
+		assert_eq!(lut.interpolate(-1.0), lut.coefficients[0]);
+		assert_eq!(lut.interpolate(2.0), lut.coefficients[3]);
+	}
+}
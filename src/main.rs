@@ -6,298 +6,1720 @@
 extern crate gl;
 extern crate glutin;
 extern crate cgmath;
+extern crate png;
+extern crate gilrs;
+extern crate sky as sky_model;
 
-use input::Input;
+// The sky model is built as a library, so it can be benchmarked on its own
+use sky_model::sky;
+use sky_model::math::{self, Handedness};
+use input::{CursorCapture, Input, Modifiers, MouseButton};
 use player::Player;
-use camera::Camera;
-use shader::{Shader, ShaderType, ShaderProgram};
-use hosek::{DATASETS_RGB, DATASETS_RGB_RAD};
+use camera::{Camera, CameraState};
+use time_of_day::TimeOfDay;
+use sun_control::SunControl;
+use frame_timer::{DeltaSmoother, FrameTimer};
+use frame_limiter::FrameLimiter;
+use error::SkyError;
+use debug::ContextInfo;
+use exposure::AutoExposure;
+use framebuffer::Framebuffer;
+use capture::Readback;
+use bloom::Bloom;
+use ground::Ground;
+use mesh::Mesh;
+use uniform_buffer::UniformBuffer;
+use sky_data::SkyData;
+use sky_cube::SkyCube;
+use skybox::Skybox;
+use transition::SkyTransition;
+use turntable::Turntable;
+use recording::{Playback, RecordedFrame, Recorder};
+use hud::Hud;
+use shader::{ShaderProgram, ShaderSources};
+use sky::{Datasets, RadianceUnits, SkyModel, SkyState};
+use config::{Config, ConfigError};
+use gamepad::Gamepad;
+use viewpoint::Viewpoint;
 
 use gl::types::*;
-use glutin::{WindowBuilder, CursorState, VirtualKeyCode};
-use cgmath::{Matrix, Vector2, Vector3, ElementWise, InnerSpace, Quaternion, Rotation3, Rad, Rotation};
-use std::{mem, ptr, f32};
+use glutin::{Event, Window, WindowBuilder, VirtualKeyCode};
+use cgmath::{Vector2, Vector3};
+use std::time::{Duration, Instant};
+use std::{cmp, env, io, process};
+use std::io::Write;
+use std::path::PathBuf;
 
 mod player;
 mod camera;
 mod input;
 mod shader;
-mod hosek;
-
-const TURBIDITY: f32 = 4.0;
-const ALBEDO: [f32; 3] = [0.1, 0.1, 0.1];
-const NORMALIZED_SUN_Y: f32 = 1.0;
-
-static VERT_SOURCE: &'static str = include_str!("shaders/vert.glsl");
-static FRAG_SOURCE: &'static str = include_str!("shaders/frag.glsl");
-
-static VERTEX_DATA: [GLfloat; 24] = [
-	-1.0, -1.0,  1.0, // 0: Left,  bottom, front
-	 1.0, -1.0,  1.0, // 1: Right, bottom, front
-	 1.0,  1.0,  1.0, // 2: Right, top,    front
-	-1.0,  1.0,  1.0, // 3: Left,  top,    front
-	-1.0, -1.0, -1.0, // 4: Left,  bottom, back
-	 1.0, -1.0, -1.0, // 5: Right, bottom, back
-	 1.0,  1.0, -1.0, // 6: Right, top,    back
-	-1.0,  1.0, -1.0, // 7: Left,  top,    back
+mod debug;
+mod time_of_day;
+mod sun_control;
+mod capture;
+mod framebuffer;
+mod texture;
+mod mesh;
+mod bloom;
+mod frame_timer;
+mod ground;
+mod config;
+mod gamepad;
+mod frame_limiter;
+mod error;
+mod exposure;
+mod dump;
+mod uniform_buffer;
+mod sky_data;
+mod sky_cube;
+mod transition;
+mod turntable;
+mod recording;
+mod contact_sheet;
+mod hud;
+mod viewpoint;
+mod skybox;
+#[cfg(test)]
+mod golden;
+
+/// The title of the window, before the frame rate is appended.
+const WINDOW_TITLE: &'static str = "Skybox Prototype";
+
+/// The config file loaded when one isn't given on the command line.
+const DEFAULT_CONFIG_PATH: &'static str = "sky.toml";
+
+/// The directory turntable frames are saved into when one isn't given on the
+/// command line.
+const DEFAULT_TURNTABLE_OUTPUT: &'static str = "turntable";
+
+/// The length of a full day when animating the sun, in seconds.
+const DAY_LENGTH: f32 = 120.0;
+
+/// The number of samples per pixel to render the scene with for multisample
+/// antialiasing, or 0 to disable it, lowered to the most the driver supports.
+/// The sky itself is a smooth gradient, so this mainly helps the edges of any
+/// geometry drawn alongside it, like the ground.
+const MSAA_SAMPLES: u32 = 4;
+
+/// The keys used to save and restore camera bookmarks.
+const BOOKMARK_KEYS: [VirtualKeyCode; 8] = [
+	VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+	VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6,
+	VirtualKeyCode::Key7, VirtualKeyCode::Key8,
+];
+
+/// The key that jumps to the next built-in viewpoint.
+const VIEWPOINT_KEY: VirtualKeyCode = VirtualKeyCode::F11;
+
+/// The key that turns the camera to face the sun.
+const LOOK_AT_SUN_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+
+/// Keys which set the camera's vertical field of view to a preset, in degrees,
+/// so screenshots can be taken with a consistent framing.
+const FOV_PRESETS: [(VirtualKeyCode, f32); 3] = [
+	(VirtualKeyCode::F5, 50.0),
+	(VirtualKeyCode::F6, 70.0),
+	(VirtualKeyCode::F7, 90.0),
 ];
 
-static INDEX_DATA: [GLushort; 36] = [
-	2, 1, 0, 0, 3, 2, // Front
-	5, 6, 7, 7, 4, 5, // Back
-	3, 0, 4, 4, 7, 3, // Left
-	6, 5, 1, 1, 2, 6, // Top
-	6, 2, 3, 3, 7, 6, // Right
-	0, 1, 5, 5, 4, 0, // Bottom
+/// The factor supersampled screenshots are rendered at, before it's reduced to
+/// fit within the limits checked by `capture::supersample_scale`.
+const SUPERSAMPLE_SCALE: u32 = 4;
+
+/// The factor the mouse sensitivity changes by with each key press.
+const SENSITIVITY_STEP: f32 = 1.25;
+
+/// How much the turbidity changes with each key press.
+const TURBIDITY_STEP: f32 = 0.5;
+
+/// How much the sun's warmth changes with each key press.
+const SUN_WARMTH_STEP: f32 = 0.1;
+
+/// How much of the sun's disk is blocked or uncovered with each key press.
+const SUN_OCCLUSION_STEP: f32 = 0.1;
+
+/// Ground albedo presets that can be cycled through, as the fraction of red,
+/// green, and blue light reflected.
+const ALBEDO_PRESETS: [(&'static str, [f32; 3]); 4] = [
+	("fresh snow", [0.9, 0.9, 0.9]),
+	("grass", [0.15, 0.25, 0.07]),
+	("ocean", [0.04, 0.06, 0.08]),
+	("asphalt", [0.08, 0.08, 0.08]),
+];
+
+/// The radiance of the line dividing the two halves of the screen when
+/// comparing skies.
+const DIVIDER_RADIANCE: f32 = 0.5;
+
+/// The terms of the Hosek-Wilkie model that can be shown on their own, in the
+/// order the fragment shader numbers them, after the full sky.
+const DEBUG_TERMS: [&'static str; 7] = [
+	"off",
+	"A exp(B / cos theta)",
+	"C",
+	"D exp(E gamma)",
+	"F cos^2 gamma",
+	"G chi",
+	"I sqrt(cos theta)",
+];
+
+/// Strength of the ordered dither applied to the final colour, in LSBs.
+const DITHER_AMOUNT: f32 = 1.0;
+
+/// Scales the HDR radiance before it's tone mapped to the screen, when the
+/// exposure isn't adapting automatically.
+const EXPOSURE: f32 = 2.0;
+
+/// How long the sky takes to blend over to new turbidity or albedo settings,
+/// in seconds.
+const SKY_TRANSITION_TIME: f32 = 0.6;
+
+/// The mouse button held to drag the sun around the sky.
+const SUN_DRAG_BUTTON: MouseButton = MouseButton::Right;
+
+/// The colour the screen's cleared to while checking the sky covers it, so any
+/// gaps stand out.
+const DEBUG_CLEAR_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+
+/// Every shader file, embedded in the executable as a fallback for when the
+/// files can't be read from disk.
+static SHADER_SOURCES: &'static [(&'static str, &'static str)] = &[
+	("vert.glsl", include_str!("shaders/vert.glsl")),
+	("frag.glsl", include_str!("shaders/frag.glsl")),
+	("post_vert.glsl", include_str!("shaders/post_vert.glsl")),
+	("tonemap_frag.glsl", include_str!("shaders/tonemap_frag.glsl")),
+	("bright_frag.glsl", include_str!("shaders/bright_frag.glsl")),
+	("blur_frag.glsl", include_str!("shaders/blur_frag.glsl")),
+	("ground_vert.glsl", include_str!("shaders/ground_vert.glsl")),
+	("ground_frag.glsl", include_str!("shaders/ground_frag.glsl")),
+	("luminance_frag.glsl", include_str!("shaders/luminance_frag.glsl")),
+	("hosek_wilkie.glsl", include_str!("shaders/hosek_wilkie.glsl")),
+	("dither.glsl", include_str!("shaders/dither.glsl")),
+	("night.glsl", include_str!("shaders/night.glsl")),
+	("sky.glsl", include_str!("shaders/sky.glsl")),
+	("cube_bake_frag.glsl", include_str!("shaders/cube_bake_frag.glsl")),
+	("cube_net_frag.glsl", include_str!("shaders/cube_net_frag.glsl")),
+	("hud_vert.glsl", include_str!("shaders/hud_vert.glsl")),
+	("hud_frag.glsl", include_str!("shaders/hud_frag.glsl")),
 ];
 
 fn main() {
+	// Print failures as a plain message, rather than the debug representation
+	// returning them from main would print
+	if let Err(err) = run() {
+		eprintln!("{}", err);
+		process::exit(1);
+	}
+}
+
+/// Sets up the window and runs the main loop until the window's closed.
+fn run() -> Result<(), SkyError> {
+	let args = parse_args()?;
+	let mut config = load_config(args.config.clone())?;
+	if let Some((width, height)) = args.resolution {
+		config.width = width;
+		config.height = height;
+	}
+	if args.nearest_turbidity {
+		config.nearest_turbidity = true;
+	}
+
+	// Print the coefficients without opening a window, if asked to
+	if args.dump_coeffs {
+		return dump_coeffs(&args, &config);
+	}
+
+	// Likewise for a contact sheet, which is rendered on the CPU
+	if let Some(ref path) = args.contact_sheet {
+		return save_contact_sheet(&args, &config, path);
+	}
+
 	// Create a window
-	let width = 900;
-	let height = 620;
-	let window = WindowBuilder::new()
-		.with_dimensions(width, height)
-		.with_title("Skybox Prototype")
-		.with_vsync()
-		.build().unwrap();
+	let width = config.width;
+	let height = config.height;
+	let window = match create_window(width, height, config.vsync) {
+		Ok(window) => window,
+		Err(err) => {
+			if args.info {
+				println!("No OpenGL context could be created, so there's nothing to report");
+			}
+			return Err(err);
+		},
+	};
+	if let (Some(points), Some(pixels)) = (window.get_inner_size_points(),
+			window.get_inner_size_pixels()) {
+		println!("Resolution: {}x{} ({}x{} pixels)", points.0, points.1, pixels.0,
+			pixels.1);
+	}
 
-	// Hide the cursor to fake capturing it
-	window.set_cursor_position(width as i32 / 2, height as i32 / 2).unwrap();
-	window.set_cursor_state(CursorState::Hide).unwrap();
+	// Hide the cursor to fake capturing it, until the program ends
+	let _cursor = CursorCapture::new(&window)?;
 
 	// Create input system
-	let mut input = Input::new(&window);
+	let mut input = Input::new(&window, width, height);
 
 	// Load OpenGL
 	unsafe {
-		window.make_current().unwrap();
+		window.make_current()?;
 		gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
-		gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+		if !gl::Viewport::is_loaded() {
+			return Err(SkyError::GlLoad);
+		}
 		gl::Enable(gl::DEPTH_TEST);
+		// The sky is drawn exactly on the far plane, which has to pass the
+		// depth test against the cleared depth buffer, but not against any
+		// geometry drawn before it
+		gl::DepthFunc(gl::LEQUAL);
+		// Back faces are culled, besides while the skybox is drawn from the
+		// inside; see `Skybox`
 		gl::Enable(gl::CULL_FACE);
+		gl::CullFace(gl::BACK);
+	}
+
+	// Say which driver's in use, since rendering problems often come down to
+	// it, or describe the context in full and stop if that's all that's wanted
+	let context = ContextInfo::query();
+	let msaa_samples = cmp::min(MSAA_SAMPLES, framebuffer::max_samples());
+	if args.info {
+		print_context_info(&window, &context, config.vsync, msaa_samples);
+		return Ok(());
 	}
+	println!("OpenGL {} on {}", context.version, context.renderer);
+
+	// Report OpenGL errors, falling back to `glGetError` on older contexts
+	let debug_callback = debug::install();
 
 	// Player
-	let camera = Camera::new(width, height);
-	let mut player = Player::new(camera);
+	let camera = Camera::new(width, height, &config);
+	let mut player = Player::new(camera, &config);
+	let mut gamepad = Gamepad::new(&config);
 
 	// Load shaders
-	let vert = Shader::new(ShaderType::Vertex, VERT_SOURCE);
-	let frag = Shader::new(ShaderType::Fragment, FRAG_SOURCE);
-	let program = ShaderProgram::new();
-	program.attach(vert);
-	program.attach(frag);
-	program.link();
+	let sources = ShaderSources::new(SHADER_SOURCES);
+	let program = sources.program("vert.glsl", "frag.glsl");
 	program.bind();
 
-	// Buffers
-	let mut vao = 0;
-	let mut pos_buffer = 0;
-	let mut index_buffer = 0;
-	unsafe {
-		// VAO
-		gl::GenVertexArrays(1, &mut vao);
-		gl::BindVertexArray(vao);
-
-		// Position
-		gl::GenBuffers(1, &mut pos_buffer);
-		gl::BindBuffer(gl::ARRAY_BUFFER, pos_buffer);
-		let vert_size = (VERTEX_DATA.len() * mem::size_of::<GLfloat>()) as GLsizeiptr;
-		let vert_ptr = mem::transmute(&VERTEX_DATA[0]);
-		gl::BufferData(gl::ARRAY_BUFFER, vert_size, vert_ptr, gl::STATIC_DRAW);
-
-		// Indices
-		gl::GenBuffers(1, &mut index_buffer);
-		gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
-		let idx_size = (INDEX_DATA.len() * mem::size_of::<GLushort>()) as GLsizeiptr;
-		let idx_ptr = mem::transmute(&INDEX_DATA[0]);
-		gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, idx_size, idx_ptr, gl::STATIC_DRAW);
-	}
-
-	// Shader attributes
-	unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, pos_buffer) };
-	let pos_loc = program.attr("position");
-	shader::set_attr(pos_loc, 3, gl::FLOAT, 0, 0); // Position
+	// Load tone mapping shaders
+	let tonemap_program = sources.program("post_vert.glsl", "tonemap_frag.glsl");
+
+	// HDR framebuffer the sky is rendered into, before tone mapping
+	let (pixel_width, pixel_height) = window.get_inner_size_pixels()
+		.unwrap_or((width, height));
+	let mut framebuffer = Framebuffer::new(pixel_width, pixel_height, msaa_samples);
+	let mut bloom = Bloom::new(pixel_width, pixel_height, &sources);
+
+	// Reused for every screenshot and turntable frame read back from the screen
+	let mut readback = Readback::new(pixel_width, pixel_height);
+
+	// Adapts the exposure to the brightness of the image
+	let mut auto_exposure = AutoExposure::new(EXPOSURE, config.key_value,
+		config.adaptation_speed, &sources);
+	let mut adapt_exposure = true;
+
+	// The coordinate convention for the camera and the sky. Left-handed
+	// coordinates are mirrored on the way to OpenGL, which turns the winding
+	// of anything drawn in them inside out
+	let handedness = handedness(&config);
+
+	// The cube the sky can be drawn on instead of the fullscreen triangle, for
+	// comparison
+	let skybox = Skybox::new(handedness, program.attr("position"));
+
+	// The fullscreen triangle used for post processing, whose vertices are
+	// generated in the vertex shader
+	let fullscreen_triangle = Mesh::empty(gl::TRIANGLES, 3);
+
+	// Whether the sky's drawn on the fullscreen triangle, working out the view
+	// ray for each pixel, rather than on the cube
+	let mut fullscreen_sky = true;
+
+	// The sky baked into a cube map, which can be shown unwrapped to check
+	// its faces line up
+	let sky_cube = SkyCube::new(&sources);
+	let mut show_cube_net = false;
+
+	// Text showing the sky's settings over the top of it
+	let mut hud = Hud::new(&sources);
+	let mut show_hud = true;
+
+	// Whether the screen's cleared to an obvious colour rather than the
+	// configured one, to show up any pixels the sky doesn't cover
+	let mut debug_clear = false;
+
+	// Whether the scene's drawn twice side by side, once for each eye, for
+	// viewing in stereo
+	let mut stereo = false;
+
+	// Ground plane, coloured to match the albedo the sky is computed with
+	let mut ground = Ground::new(config.fog_density, config.grid_spacing, handedness,
+		&sources);
+	let mut show_ground = true;
+
+	// Whether the sky's drawn after the ground rather than before it, so the
+	// depth test skips shading the sky where the ground covers it. Drawing it
+	// first is simpler to follow when only the sky's being looked at
+	let mut sky_last = true;
+
+	// Saved camera positions
+	let mut bookmarks: [Option<CameraState>; 8] = [None; 8];
 
 	// Shader uniforms
 	let projection_uniform = program.uniform("projection");
 	let orientation_uniform = program.uniform("orientation");
+	let inverse_orientation_uniform = program.uniform("inverse_orientation");
+	let fullscreen_uniform = program.uniform("fullscreen");
 
 	// Sky data uniforms
-	let params_uniform = program.uniform("params");
-	let sun_uniform = program.uniform("sun_direction");
+	let sky_uniforms = SkyUniforms::new(&program);
+	let fov_uniform = program.uniform("fov");
+	let viewport_size_uniform = program.uniform("viewport_size");
+	let view_mode_uniform = program.uniform("view_mode");
+	let mut equirectangular = false;
+	let debug_term_uniform = program.uniform("debug_term");
+	let mut debug_term = 0;
 
-	// Compute sky values
+	// The night sky colours never change
+	program.bind();
+	program.set_uniform_vec3(program.uniform("night_zenith"),
+		Vector3::from(sky::NIGHT_ZENITH));
+	program.set_uniform_vec3(program.uniform("night_horizon"),
+		Vector3::from(sky::NIGHT_HORIZON));
+	program.set_uniform_f32(program.uniform("z_sign"),
+		if handedness.is_mirrored() { -1.0 } else { 1.0 });
+
+	// Display uniforms
+	let hdr_buffer_uniform = tonemap_program.uniform("hdr_buffer");
+	let exposure_uniform = tonemap_program.uniform("exposure");
+	let dither_uniform = tonemap_program.uniform("dither_amount");
+	let bloom_intensity_uniform = tonemap_program.uniform("bloom_intensity");
+	let srgb_output_uniform = tonemap_program.uniform("srgb_output");
+	let mut dither = true;
+	let mut srgb_output = true;
+
+	// Debug rendering
+	let mut wireframe = false;
+
+	if !debug_callback {
+		debug::check_gl_error("setup");
+	}
+
+	// Sun position, as an (elevation, azimuth) pair
 	let mut sun = Vector2::new(0.0, 0.0);
-	let (sun_dir, params) = recalc_sun(sun);
+	let mut sky = SkyModel::new(config.turbidity, config.albedo, sun);
+	sky.set_units(radiance_units(&config));
+	sky.set_handedness(handedness);
+	sky.set_datasets(load_datasets(&args)?);
+	let mut albedo_preset: Option<usize> = None;
+
+	// A snapshot of the sky settings shown on the left half of the screen to
+	// compare against, along with the name of its albedo
+	let mut compare_sky: Option<(SkyModel, &'static str)> = None;
+	sky.set_sun_radius(config.sun_radius.to_radians());
+	sky.set_atmosphere_thickness(config.atmosphere_thickness.to_radians());
+	sky.set_sun_occlusion(config.sun_occlusion);
+	sky.set_occlusion_dims_sky(config.occlusion_dims_sky);
+	sky.set_nearest_turbidity(config.nearest_turbidity);
+
+	// The sky uniforms only need uploading when the coefficients change
+	let mut sky_dirty = true;
+	let mut transition: Option<SkyTransition> = None;
+	let mut last_recompute_report = Instant::now();
+
+	// Day-night cycle
+	let mut time_of_day = TimeOfDay::new(DAY_LENGTH, sun.y);
+	let mut animate_sun = false;
+	let mut sun_control = SunControl::new();
+	let mut dragging_sun = false;
+	let mut last_frame = Instant::now();
+	let mut frame_timer = FrameTimer::new();
+	let mut camera_delta = DeltaSmoother::new(config.max_delta);
+
+	// Start from a named viewpoint, if one was asked for, and keep track of
+	// which one was jumped to last so the key can cycle on from it
+	let mut viewpoint_index = args.viewpoint.and_then(|viewpoint|
+		viewpoint::VIEWPOINTS.iter().position(|preset| preset == viewpoint));
+	if let Some(viewpoint) = args.viewpoint {
+		jump_to_viewpoint(viewpoint, &mut player, &mut sky, &mut sun, &mut animate_sun,
+			&mut albedo_preset, &mut transition);
+	}
+
+	// Frame pacing
+	let mut vsync = config.vsync;
+	let mut frame_limiter = FrameLimiter::new();
+	let mut fps_cap = 0;
+
+	// Spins the camera through a full turn, saving every frame, if asked to on
+	// the command line
+	let mut turntable = match args.turntable {
+		Some(frames) => Some(Turntable::new(frames, &args.output).map_err(|err|
+			SkyError::Capture { path: args.output.clone(), message: err.to_string() })?),
+		None => None,
+	};
+
+	// Records each frame's input to a file, or plays a recording back instead
+	// of the live input, if asked to on the command line
+	let mut recorder = match args.record {
+		Some(ref path) => Some(Recorder::create(path)
+			.map_err(|err| recording_error(&args, err))?),
+		None => None,
+	};
+	let mut playback = match args.playback {
+		Some(ref path) => Some(Playback::load(path)
+			.map_err(|err| recording_error(&args, err))?),
+		None => None,
+	};
 
 	// Main event loop
 	while input.window_is_open() {
 		// Handle events
 		for event in window.poll_events() {
-			input.handle_event(event, &window);
+			if let Event::Resized(..) = event {
+				// The event's size is in pixels on some platforms and points on
+				// others, so ask the window. Rendering is done at the full pixel
+				// size, so it's sharp on HiDPI displays
+				if let Some((width, height)) = window.get_inner_size_pixels() {
+					// Ignore the window being minimised
+					if width > 0 && height > 0 {
+						framebuffer.resize(width, height);
+						bloom.resize(width, height);
+						readback.resize(width, height);
+						player.camera.resize(width, height);
+					}
+				}
+
+				// Mouse movement is measured in points instead
+				input.resize(&window);
+			}
+
+			// Live input's ignored while a recording's played back, besides
+			// closing the window
+			match event {
+				Event::Closed => input.handle_event(event),
+				_ if playback.is_some() => {},
+				_ => input.handle_event(event),
+			}
+		}
+		input.recenter_cursor(&window)?;
+
+		// Frame timing
+		let now = Instant::now();
+		let elapsed = now - last_frame;
+		let mut frame_time = elapsed.as_secs() as f32 +
+			elapsed.subsec_nanos() as f32 * 1e-9;
+		last_frame = now;
+
+		// Show the frame rate in the title, since it's cheaper than drawing it
+		if frame_timer.record(frame_time, now) {
+			let mut label = sky_label(&sky, albedo_name(albedo_preset));
+			if let Some((ref compare, compare_albedo)) = compare_sky {
+				label = format!("left: {} | right: {}",
+					sky_label(compare, compare_albedo), label);
+			}
+			window.set_title(&format!("{} - {} - {:.0}\u{b0} FOV - {:.0} FPS ({:.2} ms)",
+				WINDOW_TITLE, label, player.camera.fov().to_degrees(),
+				frame_timer.fps(), frame_timer.average() * 1000.0));
 		}
 
 		// Update
-		player.update(&input, 1.0);
-		if input.is_key_down(VirtualKeyCode::Up) {
-			sun.x -= 0.01;
-		} else if input.is_key_down(VirtualKeyCode::Down) {
-			sun.x += 0.01;
+		gamepad.update();
+		let mut gamepad_state = gamepad.state();
+
+		// Play back the next recorded frame in place of the live input, with
+		// its frame time, so everything moves exactly as it did when recorded
+		if let Some(ref mut playback) = playback {
+			match playback.next() {
+				Some(frame) => {
+					input.replay(&frame.input);
+					gamepad_state = frame.gamepad;
+					frame_time = frame.frame_time;
+				},
+				None => {
+					println!("Finished playing back the input recording");
+					break;
+				},
+			}
+		}
+		if let Some(ref mut recorder) = recorder {
+			let frame = RecordedFrame {
+				frame_time: frame_time,
+				input: input.snapshot(),
+				gamepad: gamepad_state,
+			};
+			recorder.record(&frame).map_err(|err| recording_error(&args, err))?;
+		}
+
+		// Holding the right mouse button drags the sun around instead of
+		// looking around, unless the day-night cycle is moving it
+		let was_adjusting = sun_control.is_adjusting() || dragging_sun;
+		dragging_sun = !animate_sun && input.is_mouse_down(SUN_DRAG_BUTTON);
+		player.set_mouse_look(!dragging_sun);
+
+		// Pressing any key stops the turntable early, keeping the frames saved
+		// so far
+		if turntable.is_some() && input.any_key_pressed() {
+			println!("Stopped turntable, frames so far are in {}", args.output.display());
+			turntable = None;
+		}
+
+		// The camera moves by the smoothed frame time, so a hitch doesn't jump
+		// the view. The sun keeps the real time, so a day always takes as long.
+		// The turntable has the camera to itself while it's spinning
+		if turntable.is_none() {
+			player.update(&input, &gamepad_state, camera_delta.smooth(frame_time));
+		}
+		if animate_sun {
+			time_of_day.update(frame_time);
+			sun = time_of_day.sun_position();
+		} else {
+			sun = sun_control.update(sun, sun_keys(&input), frame_time);
+			if dragging_sun {
+				sun = sun_control::drag_sun(sun, input.mouse_delta());
+			}
+
+			// Keep the sun's position on a single line of the terminal while
+			// it's moving, finishing the line once it stops
+			if sun_control.is_adjusting() || dragging_sun {
+				print!("\rSun elevation {:.1}\u{b0}, azimuth {:.1}\u{b0}    ",
+					sun.x.to_degrees(), sun.y.to_degrees());
+				io::stdout().flush().ok();
+			} else if was_adjusting {
+				println!();
+			}
+		}
+
+		// Turbidity
+		let shown = shown_state(&sky, &transition);
+		let mut settings_changed = false;
+		if input.was_key_pressed(VirtualKeyCode::K) {
+			let turbidity = sky.turbidity() + TURBIDITY_STEP;
+			settings_changed |= sky.set_turbidity(turbidity);
+		} else if input.was_key_pressed(VirtualKeyCode::J) {
+			let turbidity = sky.turbidity() - TURBIDITY_STEP;
+			settings_changed |= sky.set_turbidity(turbidity);
+		}
+
+		// Cycle through the albedo presets, starting from the configured albedo
+		if input.was_key_pressed(VirtualKeyCode::N) {
+			let preset = albedo_preset.map_or(0,
+				|preset| (preset + 1) % ALBEDO_PRESETS.len());
+			albedo_preset = Some(preset);
+			settings_changed |= sky.set_albedo(ALBEDO_PRESETS[preset].1);
+		}
+
+		// Blend over to the new settings from whatever's on screen, which
+		// might be part way through an earlier blend
+		if settings_changed {
+			transition = Some(SkyTransition::new(shown, SKY_TRANSITION_TIME));
+		}
+		if let Some(ref mut transition) = transition {
+			transition.update(frame_time);
+			sky_dirty = true;
+		}
+
+		// Extra warmth for the sun near the horizon, for a stylised look
+		if input.was_combo_pressed(Modifiers::NONE, VirtualKeyCode::Apostrophe) {
+			let warmth = sky.sun_warmth() + SUN_WARMTH_STEP;
+			sky.set_sun_warmth(warmth);
+			println!("Sun warmth: {:.1}", sky.sun_warmth());
+			sky_dirty = true;
+		} else if input.was_combo_pressed(Modifiers::NONE, VirtualKeyCode::Semicolon) {
+			let warmth = sky.sun_warmth() - SUN_WARMTH_STEP;
+			sky.set_sun_warmth(warmth);
+			println!("Sun warmth: {:.1}", sky.sun_warmth());
+			sky_dirty = true;
+		}
+
+		// Blocking out part of the sun, like thin cloud or a partial eclipse,
+		// on the same keys with alt held
+		if input.was_combo_pressed(Modifiers::ALT, VirtualKeyCode::Apostrophe) {
+			let occlusion = sky.sun_occlusion() + SUN_OCCLUSION_STEP;
+			sky.set_sun_occlusion(occlusion);
+			println!("Sun occlusion: {:.1}", sky.sun_occlusion());
+			sky_dirty = true;
+		} else if input.was_combo_pressed(Modifiers::ALT, VirtualKeyCode::Semicolon) {
+			let occlusion = sky.sun_occlusion() - SUN_OCCLUSION_STEP;
+			sky.set_sun_occlusion(occlusion);
+			println!("Sun occlusion: {:.1}", sky.sun_occlusion());
+			sky_dirty = true;
+		}
+
+		// Split the screen, comparing the current sky settings on the left with
+		// any changes made to them on the right. Both this and the stereo view
+		// split the screen, so only one's shown at a time
+		if input.was_key_pressed(VirtualKeyCode::X) {
+			compare_sky = match compare_sky {
+				Some(_) => None,
+				// The snapshot keeps every setting, so only changes made
+				// afterwards show up as a difference
+				None => Some((sky.clone(), albedo_name(albedo_preset))),
+			};
+			if compare_sky.is_some() && stereo {
+				stereo = false;
+				println!("Stereo view off while comparing skies");
+			}
+		}
+
+		// Compute sky values
+		if sky.set_sun(sun) {
+			sky_dirty = true;
+		}
+		if let Some((ref mut compare, _)) = compare_sky {
+			compare.set_sun(sun);
+		}
+
+		// Check the coefficients aren't being recomputed needlessly
+		if debug::ENABLED && now - last_recompute_report >= Duration::from_secs(1) {
+			if sky.recompute_count() > 0 {
+				println!("Sky recomputed {} times in the last second",
+					sky.recompute_count());
+			}
+			sky.reset_recompute_count();
+			last_recompute_report = now;
+		}
+
+		// Day-night cycle controls
+		if input.was_key_pressed(VirtualKeyCode::C) {
+			// Animate along a path through the sun's current azimuth
+			animate_sun = !animate_sun;
+			time_of_day.azimuth = sun.y;
+		}
+		if input.was_key_pressed(VirtualKeyCode::P) {
+			time_of_day.paused = !time_of_day.paused;
+		}
+		if input.was_key_pressed(VirtualKeyCode::R) {
+			time_of_day.reverse();
+		}
+		if input.was_key_pressed(VirtualKeyCode::RBracket) {
+			time_of_day.speed_up();
+			println!("Day cycle speed: {}x", time_of_day.speed());
+		} else if input.was_key_pressed(VirtualKeyCode::LBracket) {
+			time_of_day.slow_down();
+			println!("Day cycle speed: {}x", time_of_day.speed());
+		}
+
+		// Toggle dithering
+		if input.was_key_pressed(VirtualKeyCode::B) {
+			dither = !dither;
+		}
+
+		// Toggle sRGB encoding of the output, to compare against writing the
+		// linear values straight to the display
+		if input.was_key_pressed(VirtualKeyCode::Y) {
+			srgb_output = !srgb_output;
+			println!("sRGB output: {}", if srgb_output { "on" } else { "off" });
+		}
+
+		// Toggle automatic exposure
+		if input.was_key_pressed(VirtualKeyCode::U) {
+			adapt_exposure = !adapt_exposure;
+			println!("Automatic exposure: {}", if adapt_exposure { "on" } else { "off" });
+		}
+
+		// Mouse look options
+		if input.was_key_pressed(VirtualKeyCode::I) {
+			let invert_y = !player.invert_y();
+			player.set_invert_y(invert_y);
+		}
+		if input.was_key_pressed(VirtualKeyCode::Key0) {
+			let sensitivity = player.sensitivity() * SENSITIVITY_STEP;
+			player.set_sensitivity(sensitivity);
+			println!("Mouse sensitivity: {:.2}", player.sensitivity());
+		} else if input.was_key_pressed(VirtualKeyCode::Key9) {
+			let sensitivity = player.sensitivity() / SENSITIVITY_STEP;
+			player.set_sensitivity(sensitivity);
+			println!("Mouse sensitivity: {:.2}", player.sensitivity());
+		}
+
+		// Camera bookmarks, saved with control and a number key, and restored
+		// with just the number key
+		for (index, &key) in BOOKMARK_KEYS.iter().enumerate() {
+			if input.was_combo_pressed(Modifiers::CTRL, key) {
+				bookmarks[index] = Some(player.camera.state());
+				println!("Saved bookmark {}: {:?}", index + 1, bookmarks[index].unwrap());
+			} else if input.was_combo_pressed(Modifiers::NONE, key) {
+				if let Some(state) = bookmarks[index] {
+					player.camera.set_state(state);
+				}
+			}
+		}
+		if input.was_key_pressed(VirtualKeyCode::Home) {
+			player.camera.reset();
+		}
+
+		// Jump to the next viewpoint, moving the camera and changing the sky
+		// at once. The sky snaps straight to it rather than blending, since
+		// the camera's moved too
+		if input.was_key_pressed(VIEWPOINT_KEY) {
+			let index = viewpoint_index.map_or(0,
+				|index| (index + 1) % viewpoint::VIEWPOINTS.len());
+			viewpoint_index = Some(index);
+			jump_to_viewpoint(&viewpoint::VIEWPOINTS[index], &mut player, &mut sky,
+				&mut sun, &mut animate_sun, &mut albedo_preset, &mut transition);
+			sky_dirty = true;
+		}
+
+		// Turn to face the sun, or as near as the pitch limits allow, to find
+		// it again after moving it or looking away
+		if input.was_key_pressed(LOOK_AT_SUN_KEY) {
+			let eye = player.camera.position();
+			player.camera.look_at(eye + sky.sun_direction());
+		}
+		if input.was_key_pressed(VirtualKeyCode::F8) {
+			print!("{}", player.camera.describe());
+		}
+
+		// Field of view presets
+		for &(key, fov) in FOV_PRESETS.iter() {
+			if input.was_key_pressed(key) {
+				player.camera.set_fov(fov.to_radians());
+			}
+		}
+
+		// Toggle the ground plane
+		if input.was_key_pressed(VirtualKeyCode::G) {
+			show_ground = !show_ground;
+		}
+
+		// Switch between drawing the sky on a fullscreen triangle and the cube
+		if input.was_key_pressed(VirtualKeyCode::Slash) {
+			fullscreen_sky = !fullscreen_sky;
+			println!("Drawing the sky on {}",
+				if fullscreen_sky { "a fullscreen triangle" } else { "a cube" });
+		}
+
+		// Toggle the grid over the ground
+		if input.was_key_pressed(VirtualKeyCode::Backslash) {
+			ground.show_grid = !ground.show_grid;
+		}
+
+		// Toggle drawing the sky before or after the ground
+		if input.was_key_pressed(VirtualKeyCode::Q) {
+			sky_last = !sky_last;
+			println!("Drawing the sky {} the ground", if sky_last { "after" } else { "before" });
+		}
+
+		// Toggle orthographic projection
+		if input.was_key_pressed(VirtualKeyCode::O) {
+			player.camera.toggle_projection();
+		}
+
+		// Toggle the unwrapped view of the whole sky
+		if input.was_key_pressed(VirtualKeyCode::E) {
+			equirectangular = !equirectangular;
+		}
+
+		// Toggle the HUD, so it can be hidden for screenshots
+		if input.was_key_pressed(VirtualKeyCode::F1) {
+			show_hud = !show_hud;
+		}
+
+		// Toggle clearing to magenta, to check the sky covers the screen
+		if input.was_key_pressed(VirtualKeyCode::F9) {
+			debug_clear = !debug_clear;
+		}
+
+		// Toggle the side-by-side stereo view
+		if input.was_key_pressed(VirtualKeyCode::F10) {
+			stereo = !stereo;
+			if stereo && compare_sky.is_some() {
+				compare_sky = None;
+				println!("Stopped comparing skies for the stereo view");
+			}
+		}
+
+		// Toggle the unwrapped cube map view
+		if input.was_key_pressed(VirtualKeyCode::H) {
+			show_cube_net = !show_cube_net;
+		}
+
+		// Cycle through showing each term of the model on its own
+		if input.was_key_pressed(VirtualKeyCode::T) {
+			debug_term = (debug_term + 1) % DEBUG_TERMS.len();
+			println!("Showing term: {}", DEBUG_TERMS[debug_term]);
+		}
+
+		// Toggle between FPS-style and free look
+		if input.was_key_pressed(VirtualKeyCode::L) {
+			let yaw_lock = player.camera.yaw_lock();
+			player.camera.set_yaw_lock(!yaw_lock);
+		}
+
+		// Bloom controls
+		if input.was_key_pressed(VirtualKeyCode::Equals) {
+			bloom.intensity += 0.05;
+			println!("Bloom intensity: {:.2}", bloom.intensity);
+		} else if input.was_key_pressed(VirtualKeyCode::Minus) {
+			bloom.intensity = (bloom.intensity - 0.05).max(0.0);
+			println!("Bloom intensity: {:.2}", bloom.intensity);
+		}
+		if input.was_key_pressed(VirtualKeyCode::Period) {
+			bloom.threshold += 0.1;
+			println!("Bloom threshold: {:.2}", bloom.threshold);
+		} else if input.was_key_pressed(VirtualKeyCode::Comma) {
+			bloom.threshold = (bloom.threshold - 0.1).max(0.0);
+			println!("Bloom threshold: {:.2}", bloom.threshold);
+		}
+
+		// Toggle wireframe rendering
+		if input.was_key_pressed(VirtualKeyCode::F) {
+			wireframe = !wireframe;
 		}
-		let (sun_dir, params) = recalc_sun(sun);
+
+		// Toggle vsync, where the platform allows it
+		if input.was_key_pressed(VirtualKeyCode::V) {
+			if frame_limiter::set_swap_interval(&window, if vsync { 0 } else { 1 }) {
+				vsync = !vsync;
+				println!("Vsync: {}", if vsync { "on" } else { "off" });
+			} else {
+				println!("Vsync can't be changed while running on this platform, \
+					set window.vsync in the config instead");
+			}
+		}
+
+		// Cycle through frame rate caps
+		if input.was_key_pressed(VirtualKeyCode::M) {
+			fps_cap = (fps_cap + 1) % frame_limiter::FPS_CAPS.len();
+			let cap = frame_limiter::FPS_CAPS[fps_cap];
+			frame_limiter.set_fps(cap);
+			match cap {
+				Some(fps) => println!("Frame rate capped to {} FPS", fps),
+				None => println!("Frame rate uncapped"),
+			}
+		}
+
+		// Screenshots are taken after rendering. A supersampled one renders the
+		// frame at a multiple of the window's size and shrinks it back down,
+		// for smoother gradients and sun edges
+		let take_screenshot = input.was_key_pressed(VirtualKeyCode::F2);
+
+		// Ctrl+F2 saves the HDR framebuffer instead, before tone mapping, for
+		// measuring radiance or grading elsewhere
+		let take_hdr_screenshot = input.was_combo_pressed(Modifiers::CTRL,
+			VirtualKeyCode::F2);
+		let take_screenshot = take_screenshot && !take_hdr_screenshot;
+
+		// Print the colour in the middle of the window once the frame's
+		// rendered
+		let pick_color = input.was_key_pressed(VirtualKeyCode::F4);
+		let supersample = if input.was_key_pressed(VirtualKeyCode::F3) {
+			let (width, height) = framebuffer.size();
+			Some(capture::supersample_scale(SUPERSAMPLE_SCALE, width, height,
+				capture::max_framebuffer_size()))
+		} else {
+			None
+		};
 
 		input.update();
 
+		// Enlarge the render targets for a supersampled screenshot, until the
+		// end of the frame
+		let (window_width, window_height) = framebuffer.size();
+		if let Some(scale) = supersample {
+			framebuffer.resize(window_width * scale, window_height * scale);
+			bloom.resize(window_width * scale, window_height * scale);
+		}
+
+		// Render the sky into the HDR framebuffer
+		framebuffer.bind();
+		program.bind();
 		unsafe {
-			// Clear the screen to the clear colour
+			gl::Enable(gl::DEPTH_TEST);
+
+			// Clear the screen to the clear colour, which is set every frame
+			// as it's changed for the divider when comparing skies
+			let clear = if debug_clear { DEBUG_CLEAR_COLOR } else { config.clear_color };
+			gl::ClearColor(clear[0], clear[1], clear[2], 1.0);
 			gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+		}
 
-			// Matrix uniforms
-			gl::UniformMatrix4fv(projection_uniform, 1, gl::FALSE, player.camera.projection.as_ptr());
-			gl::UniformMatrix4fv(orientation_uniform, 1, gl::FALSE, player.camera.orientation.as_ptr());
+		// Matrix uniforms
+		program.set_uniform_mat4(projection_uniform, &player.camera.projection);
+		program.set_uniform_mat4(orientation_uniform, &player.camera.orientation);
+		program.set_uniform_mat4(inverse_orientation_uniform,
+			&player.camera.inverse_orientation());
+		program.set_uniform_i32(fullscreen_uniform, if fullscreen_sky { 1 } else { 0 });
 
-			// Sky uniforms
-			gl::Uniform3fv(params_uniform, 10, mem::transmute(&params[0]));
-			gl::Uniform3f(sun_uniform, sun_dir.x, sun_dir.y, sun_dir.z);
+		// The sun's edge is antialiased over the angle covered by a pixel, which
+		// changes with the field of view and window size
+		let (buffer_width, buffer_height) = framebuffer.size();
+		program.set_uniform_f32(fov_uniform, player.camera.fov());
+		program.set_uniform_vec2(viewport_size_uniform, buffer_width as f32,
+			buffer_height as f32);
+		program.set_uniform_i32(view_mode_uniform,
+			if equirectangular { 1 } else { 0 });
+		program.set_uniform_i32(debug_term_uniform, debug_term as i32);
 
-			// Render
-			gl::DrawElements(gl::TRIANGLES, INDEX_DATA.len() as GLint, gl::UNSIGNED_SHORT, ptr::null());
+		unsafe {
+			// Draw back edges too in wireframe mode, since culling would hide
+			// half the cube's edges
+			if wireframe {
+				gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+				gl::Disable(gl::CULL_FACE);
+			}
+		}
+
+		// Draws the sky and the ground beneath it. The sky's at the far plane,
+		// so it ends up behind the ground whichever's drawn first
+		let draw_scene = |camera: &Camera, sky: &SkyModel| {
+			// The ground would cover the bottom half of the unwrapped sky
+			let draw_ground = show_ground && !equirectangular;
+			if draw_ground && sky_last {
+				ground.render(camera, sky);
+			}
+
+			program.bind();
+			if fullscreen_sky {
+				fullscreen_triangle.draw();
+			} else {
+				skybox.draw();
+			}
+
+			if draw_ground && !sky_last {
+				ground.render(camera, sky);
+			}
+		};
+
+		match compare_sky {
+			// The net shows the main sky on its own, even when comparing
+			_ if show_cube_net => {
+				if sky_dirty {
+					sky_uniforms.upload(&program, &sky, &shown_state(&sky, &transition));
+					sky_dirty = false;
+				}
+
+				// Bake every frame, so the cube map follows the sun
+				fullscreen_triangle.bind();
+				sky_cube.bake();
+				framebuffer.bind();
+				sky_cube.draw_net(buffer_width, buffer_height);
+			},
+			// Each eye sees the middle of the view, cropped to fit its half of
+			// the screen, from half the eye separation to that side. Parallel
+			// viewing puts the left eye on the left. Comparing skies is turned
+			// off while it's shown
+			_ if stereo => {
+				if sky_dirty {
+					sky_uniforms.upload(&program, &sky, &shown_state(&sky, &transition));
+					sky_dirty = false;
+				}
+
+				let halves = split_halves(buffer_width as GLint);
+				for (&(x, width), &side) in halves.iter().zip([-0.5, 0.5].iter()) {
+					let mut eye = player.camera.eye(side * config.eye_separation);
+					eye.resize(width as u32, buffer_height);
+					unsafe { gl::Viewport(x, 0, width, buffer_height as GLsizei); }
+					program.bind();
+					program.set_uniform_mat4(projection_uniform, &eye.projection);
+					program.set_uniform_mat4(orientation_uniform, &eye.orientation);
+					program.set_uniform_mat4(inverse_orientation_uniform,
+						&eye.inverse_orientation());
+					program.set_uniform_vec2(viewport_size_uniform, width as f32,
+						buffer_height as f32);
+					draw_scene(&eye, &sky);
+				}
+				unsafe {
+					gl::Viewport(0, 0, buffer_width as GLsizei, buffer_height as GLsizei);
+				}
+			},
+			Some((ref compare, _)) => {
+				// Draw each sky into its half of the screen. They share the
+				// same uniforms, so these are uploaded for every draw
+				let halves = split_halves(buffer_width as GLint);
+				let half_width = halves[1].0;
+				let states = [
+					(compare, compare.state()),
+					(&sky, shown_state(&sky, &transition)),
+				];
+				unsafe { gl::Enable(gl::SCISSOR_TEST); }
+				for (&(half_sky, ref state), &(x, width)) in states.iter().zip(halves.iter()) {
+					unsafe { gl::Scissor(x, 0, width, buffer_height as GLsizei); }
+					program.bind();
+					sky_uniforms.upload(&program, half_sky, state);
+					draw_scene(&player.camera, half_sky);
+				}
+
+				// Draw a thin line between the halves
+				unsafe {
+					gl::Scissor(half_width - 1, 0, 2, buffer_height as GLsizei);
+					gl::ClearColor(DIVIDER_RADIANCE, DIVIDER_RADIANCE,
+						DIVIDER_RADIANCE, 1.0);
+					gl::Clear(gl::COLOR_BUFFER_BIT);
+					gl::Disable(gl::SCISSOR_TEST);
+				}
+				sky_dirty = true;
+			},
+			None => {
+				// Sky uniforms, which keep their values between frames
+				if sky_dirty {
+					sky_uniforms.upload(&program, &sky, &shown_state(&sky, &transition));
+					sky_dirty = false;
+				}
+				draw_scene(&player.camera, &sky);
+			},
+		}
+		if transition.as_ref().map_or(false, |transition| transition.finished()) {
+			transition = None;
+		}
+
+		unsafe {
+			// Restore fill mode so any later passes are unaffected
+			if wireframe {
+				gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+				gl::Enable(gl::CULL_FACE);
+			}
+
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		// Average the scene's samples into the texture the rest of the passes
+		// read, then extract and blur the bright parts of the image
+		framebuffer.resolve();
+		if take_hdr_screenshot {
+			let (width, height) = framebuffer.size();
+			framebuffer.bind_read();
+			readback.resize(width, height);
+			save_hdr_screenshot(width, height, readback.read_rgba16f());
+			readback.resize(window_width, window_height);
+			Framebuffer::unbind();
+		}
+		fullscreen_triangle.bind();
+		bloom.render(&framebuffer);
+		if adapt_exposure {
+			auto_exposure.update(&framebuffer, frame_time);
+		}
+
+		// Tone map the HDR framebuffer onto the screen
+		tonemap_program.bind();
+		framebuffer.bind_color(0);
+		bloom.bind_textures(1);
+
+		// Display uniforms
+		tonemap_program.set_uniform_i32(hdr_buffer_uniform, 0);
+		bloom::set_sampler_uniforms(&tonemap_program, 1);
+		tonemap_program.set_uniform_f32(bloom_intensity_uniform, bloom.intensity);
+		let exposure = if adapt_exposure { auto_exposure.exposure() } else { EXPOSURE };
+		tonemap_program.set_uniform_f32(exposure_uniform, exposure);
+		tonemap_program.set_uniform_f32(dither_uniform,
+			if dither { DITHER_AMOUNT } else { 0.0 });
+		tonemap_program.set_uniform_i32(srgb_output_uniform, srgb_output as GLint);
+
+		// For a supersampled screenshot, tone map into an offscreen target at
+		// the enlarged size first, then read it back and shrink it
+		if let Some(scale) = supersample {
+			let (width, height) = framebuffer.size();
+			let target = Framebuffer::with_format(width, height, gl::RGBA8);
+			target.bind();
+			fullscreen_triangle.draw();
+			readback.resize(width, height);
+			let pixels = capture::downsample(readback.read_rgba8(), window_width,
+				window_height, scale);
+			readback.resize(window_width, window_height);
+			save_screenshot(window_width, window_height, &pixels);
+		}
+
+		Framebuffer::unbind();
+		unsafe {
+			gl::Viewport(0, 0, window_width as GLsizei, window_height as GLsizei);
+		}
+		fullscreen_triangle.draw();
+
+		// The colour picker reads the pixel in the middle of the window, which
+		// the HUD marks with a crosshair. The cursor's hidden and held there
+		// for mouse look, so it can't point at anything else
+		let center = capture::pixel_at((window_width as f32 / 2.0,
+			window_height as f32 / 2.0), 1.0, window_width, window_height);
+
+		// Read back the colour under the crosshair, before the HUD's drawn
+		// over it, along with the linear radiance from the HDR framebuffer
+		if let (true, Some((x, y))) = (pick_color, center) {
+			let display = capture::read_pixel(x, y);
+
+			// The HDR framebuffer's enlarged while taking a supersampled
+			// screenshot
+			let scale = supersample.unwrap_or(1);
+			framebuffer.bind_read();
+			let radiance = capture::read_pixel(x * scale, y * scale);
+			Framebuffer::unbind();
+
+			// The middle of the window's in the direction the camera's facing
+			let direction = player.camera.forward();
+			println!("Colour at pixel ({}, {}), direction {:.3} {:.3} {:.3}: \
+				display {:.3} {:.3} {:.3}, radiance {:.4} {:.4} {:.4}", x,
+				window_height - 1 - y, direction.x, direction.y, direction.z,
+				display[0], display[1], display[2], radiance[0], radiance[1],
+				radiance[2]);
+		}
+
+		// The HUD's kept out of the turntable's frames
+		if show_hud && turntable.is_none() {
+			let split = if stereo {
+				Some("Stereo view")
+			} else if compare_sky.is_some() {
+				Some("Comparing skies, snapshot on the left")
+			} else {
+				None
+			};
+			hud.draw(&hud_lines(&sky, &player.camera, &frame_timer, split), window_width,
+				window_height);
+			if let Some((x, y)) = center {
+				hud.draw_crosshair(x, y);
+			}
+		}
+		if !debug_callback {
+			debug::check_gl_error("render");
+		}
+
+		// Save a screenshot, before the back buffer is swapped away
+		if take_screenshot {
+			save_screenshot(window_width, window_height, readback.read_rgba8());
+		}
+
+		// Save the turntable's frame, then turn the camera for the next one
+		if let Some(ref mut turntable) = turntable {
+			let path = turntable.frame_path();
+			capture::save_png(&path, window_width, window_height, readback.read_rgba8())
+				.map_err(|err| SkyError::Capture {
+					path: path.clone(),
+					message: err.to_string(),
+				})?;
+			turntable.advance();
+			player.camera.rotate(turntable.step(), 0.0);
+		}
+
+		// Put the render targets back to the window's size
+		if supersample.is_some() {
+			framebuffer.resize(window_width, window_height);
+			bloom.resize(window_width, window_height);
 		}
 
 		// Show the triangle on screen
-		window.swap_buffers().unwrap();
+		window.swap_buffers()?;
+		frame_limiter.wait();
+
+		if turntable.as_ref().map_or(false, Turntable::finished) {
+			println!("Saved turntable frames to {}", args.output.display());
+			break;
+		}
 	}
+
+	if let Some(recorder) = recorder {
+		recorder.finish().map_err(|err| recording_error(&args, err))?;
+	}
+	Ok(())
 }
 
+/// Describes a failure to read or write the input recording named on the
+/// command line.
+fn recording_error(args: &Args, err: io::Error) -> SkyError {
+	let path = args.record.as_ref().or(args.playback.as_ref())
+		.cloned()
+		.unwrap_or_default();
+	SkyError::Recording { path: path, message: err.to_string() }
+}
 
-fn recalc_sun(sun_pos: Vector2<f32>) -> (Vector3<f32>, [Vector3<f32>; 10]) {
-	let sun_dir = Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), Rad(sun_pos.y)).rotate_vector(Quaternion::from_axis_angle(Vector3::new(-1.0, 0.0, 0.0), Rad(sun_pos.x)).rotate_vector(Vector3::new(0.0, 0.0, 1.0)));
-	// println!("{:?}", sun_dir);
 
-	// let hor = (sun_dir.x * sun_dir.x + sun_dir.z * sun_dir.z).sqrt();
-	let sun_theta = clamp(sun_dir.y, 0.0, 1.0).acos();
-	// println!("sun theta {}, cos sun theta {}", sun_theta, sun_theta.cos());
-	let mut params = [Vector3::new(0.0, 0.0, 0.0); 10];
-	for i in 0 .. 3 {
-		params[0][i] = evaluate(&DATASETS_RGB[i][0 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[1][i] = evaluate(&DATASETS_RGB[i][1 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[2][i] = evaluate(&DATASETS_RGB[i][2 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[3][i] = evaluate(&DATASETS_RGB[i][3 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[4][i] = evaluate(&DATASETS_RGB[i][4 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[5][i] = evaluate(&DATASETS_RGB[i][5 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[6][i] = evaluate(&DATASETS_RGB[i][6 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
+/// The sky model's values used by the sky shader. Most are shared with the
+/// ground through the SkyData uniform block, and the rest are only used by the
+/// sky shader itself.
+struct SkyUniforms {
+	/// The buffer backing the SkyData block.
+	buffer: UniformBuffer<SkyData>,
+	/// The angular radius of the sun's disk.
+	sun_radius: GLint,
+	/// The extra colour of the sun's disk.
+	sun_tint: GLint,
+	/// How much of the sun's disk is blocked.
+	sun_occlusion: GLint,
+	/// The rotation of the star field.
+	star_rotation: GLint,
+}
+
+impl SkyUniforms {
+	/// Creates the SkyData buffer, which is filled in by the first upload, and
+	/// looks up the remaining uniforms in the sky shader.
+	fn new(program: &ShaderProgram) -> SkyUniforms {
+		program.bind_uniform_block(sky_data::BLOCK_NAME, sky_data::BINDING);
+		SkyUniforms {
+			buffer: UniformBuffer::new(sky_data::BINDING, &SkyData::default()),
+			sun_radius: program.uniform("sun_radius"),
+			sun_tint: program.uniform("sun_tint"),
+			sun_occlusion: program.uniform("sun_occlusion"),
+			star_rotation: program.uniform("star_rotation"),
+		}
+	}
+
+	/// Uploads a sky model's values to the SkyData block and the sky shader,
+	/// which must be bound. The coefficients and sun direction come from
+	/// `state`, which can differ from the model's own during a transition.
+	fn upload(&self, program: &ShaderProgram, sky: &SkyModel, state: &SkyState) {
+		self.buffer.update(&SkyData::new(sky, state));
+		program.set_uniform_f32(self.sun_radius, sky.sun_radius());
+		program.set_uniform_vec3(self.sun_tint, sky.sun_tint(state.sun_direction));
+		program.set_uniform_f32(self.sun_occlusion, sky.sun_occlusion());
+		program.set_uniform_mat3(self.star_rotation, &sky.star_rotation());
+	}
+}
+
+/// Returns the state of the sky to draw, which lags behind the sky model while
+/// a transition is blending over to its current settings.
+fn shown_state(sky: &SkyModel, transition: &Option<SkyTransition>) -> SkyState {
+	match *transition {
+		Some(ref transition) => transition.state(&sky.state()),
+		None => sky.state(),
+	}
+}
+
+/// Moves the camera to a viewpoint and snaps the sky straight to its settings,
+/// stopping the day cycle and any transition, and forgetting the albedo preset
+/// so they don't pull the sky away from it.
+fn jump_to_viewpoint(viewpoint: &Viewpoint, player: &mut Player, sky: &mut SkyModel,
+		sun: &mut Vector2<f32>, animate_sun: &mut bool, albedo_preset: &mut Option<usize>,
+		transition: &mut Option<SkyTransition>) {
+	player.teleport_to_viewpoint(viewpoint);
+	viewpoint.apply_sky(sky);
+	*sun = viewpoint.sun;
+	*animate_sun = false;
+	*albedo_preset = None;
+	*transition = None;
+	println!("Viewpoint: {}", viewpoint.name);
+}
+
+/// Saves RGBA8 pixels as a screenshot, reporting where it went.
+fn save_screenshot(width: u32, height: u32, pixels: &[u8]) {
+	let path = capture::screenshot_path("png");
+	match capture::save_png(&path, width, height, pixels) {
+		Ok(()) => println!("Saved screenshot to {}", path.display()),
+		Err(err) => println!("Failed to save screenshot: {}", err),
+	}
+}
 
-		params[7][i] = evaluate(&DATASETS_RGB[i][8 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
-		params[8][i] = evaluate(&DATASETS_RGB[i][7 ..], 9, TURBIDITY, ALBEDO[i], sun_theta);
+/// Saves RGBA16F pixels, before tone mapping, as an HDR screenshot, reporting
+/// where it went.
+fn save_hdr_screenshot(width: u32, height: u32, pixels: &[u16]) {
+	let path = capture::screenshot_path("pfm");
+	match capture::save_pfm(&path, width, height, pixels) {
+		Ok(()) => println!("Saved HDR screenshot to {}", path.display()),
+		Err(err) => println!("Failed to save HDR screenshot: {}", err),
+	}
+}
 
-		// Z value thing
-		params[9][i] = evaluate(DATASETS_RGB_RAD[i], 1, TURBIDITY, ALBEDO[i], sun_theta);
+/// Returns the name of an albedo preset, or of the configured albedo if no
+/// preset's been chosen.
+fn albedo_name(preset: Option<usize>) -> &'static str {
+	match preset {
+		Some(preset) => ALBEDO_PRESETS[preset].0,
+		None => "custom",
 	}
+}
 
-	let S = hosek_wilkie(sun_theta.cos(), 0.0, 1.0, &params[0 .. 9]).mul_element_wise(params[9]);
-	// println!("S {:?}", S);
-	params[9] /= S.dot(Vector3::new(0.2126, 0.7152, 0.0722));
+/// Returns the direction the arrow keys are moving the sun in, as an
+/// (elevation, azimuth) pair.
+fn sun_keys(input: &Input) -> Vector2<f32> {
+	let axis = |negative, positive| {
+		if input.is_key_down(negative) {
+			-1.0
+		} else if input.is_key_down(positive) {
+			1.0
+		} else {
+			0.0
+		}
+	};
+	Vector2::new(axis(VirtualKeyCode::Up, VirtualKeyCode::Down),
+		axis(VirtualKeyCode::Left, VirtualKeyCode::Right))
+}
+
+/// Returns the lines of text shown on the HUD, describing the sky and camera,
+/// along with what the screen's split for, if it is.
+fn hud_lines(sky: &SkyModel, camera: &Camera, frame_timer: &FrameTimer,
+		split: Option<&str>) -> Vec<String> {
+	// Measure the azimuth the same way whichever way round the axes are
+	let sun = sky.handedness().convert(sky.sun_direction());
+	let albedo = sky.albedo();
+	let night = if sky.is_sun_below_horizon() { " (below horizon)" } else { "" };
+	let mut lines = vec![
+		format!("Turbidity {:.1}", sky.turbidity()),
+		format!("Albedo {:.2} {:.2} {:.2}", albedo[0], albedo[1], albedo[2]),
+		format!("Sun elevation {:.1}\u{b0}, azimuth {:.1}\u{b0}{}",
+			sun.y.asin().to_degrees(), sun.x.atan2(sun.z).to_degrees(), night),
+		format!("FOV {:.0}\u{b0}", camera.fov().to_degrees()),
+		format!("{:.0} FPS ({:.2} ms)", frame_timer.fps(), frame_timer.average() * 1000.0),
+	];
+	lines.extend(split.map(|split| split.to_string()));
+	lines
+}
 
-	let mut sun_amount = (sun_dir.y / f32::consts::FRAC_PI_2) % 4.0;
-	if sun_amount > 2.0 {
-		sun_amount = 0.0;//-(sun_amount - 2.0);
+/// Describes a sky's settings for the window title.
+fn sky_label(sky: &SkyModel, albedo_name: &str) -> String {
+	format!("turbidity {:.1}, {} albedo", sky.turbidity(), albedo_name)
+}
+
+/// Prints the details of the OpenGL context and the window it's drawing to, for
+/// including in bug reports.
+fn print_context_info(window: &Window, context: &ContextInfo, vsync: bool,
+		msaa_samples: u32) {
+	println!("Vendor: {}", context.vendor);
+	println!("Renderer: {}", context.renderer);
+	println!("OpenGL version: {}", context.version);
+	println!("GLSL version: {}", context.shading_language_version);
+	if let (Some(points), Some(pixels)) = (window.get_inner_size_points(),
+			window.get_inner_size_pixels()) {
+		println!("Window size: {}x{} points ({}x{} pixels)", points.0, points.1,
+			pixels.0, pixels.1);
+	}
+	match msaa_samples {
+		0 if MSAA_SAMPLES > 0 => println!("MSAA: off ({}x unavailable)", MSAA_SAMPLES),
+		0 => println!("MSAA: off"),
+		samples => println!("MSAA: {}x", samples),
 	}
-	if sun_amount > 1.0 {
-		sun_amount = 2.0 - sun_amount;
-	} else if sun_amount < -1.0 {
-		sun_amount = -2.0 - sun_amount;
+	let on_off = |on: bool| if on { "on" } else { "off" };
+	match frame_limiter::swap_interval(window) {
+		Some(interval) => println!("Vsync: {}", on_off(interval > 0)),
+		None => println!("Vsync: {} requested, but this platform can't confirm it",
+			on_off(vsync)),
 	}
+}
+
+/// Creates the main window. It's only drawn into by the tone mapping and the
+/// HUD, so it isn't multisampled; the scene is, offscreen.
+fn create_window(width: u32, height: u32, vsync: bool)
+		-> Result<Window, SkyError> {
+	let builder = WindowBuilder::new()
+		.with_dimensions(width, height)
+		.with_title(WINDOW_TITLE)
+		.with_gl_debug_flag(debug::ENABLED);
+	let builder = if vsync { builder.with_vsync() } else { builder };
+	Ok(builder.build()?)
+}
 
-	let normalized_sun_y = 0.6 + 0.45 * sun_amount;
-	params[9] *= normalized_sun_y;
+/// The options given on the command line.
+struct Args {
+	/// The config file to load, if one was given.
+	config: Option<PathBuf>,
+	/// True if the OpenGL context should be described rather than running the
+	/// viewer.
+	info: bool,
+	/// True if the sky's coefficients should be printed rather than opening a
+	/// window.
+	dump_coeffs: bool,
+	/// True if the turbidity should be snapped to the nearest level in the
+	/// datasets, for debugging, whatever the config file says.
+	nearest_turbidity: bool,
+	/// Overrides the config file's turbidity when dumping coefficients.
+	turbidity: Option<f32>,
+	/// Overrides the config file's albedo when dumping coefficients.
+	albedo: Option<f32>,
+	/// The sun's elevation to dump coefficients for, in degrees.
+	elevation: f32,
+	/// The sun's azimuth to dump coefficients for, in degrees.
+	azimuth: f32,
+	/// Overrides the config file's window size, in points.
+	resolution: Option<(u32, u32)>,
+	/// The number of frames to spin the camera through a full turn in, saving
+	/// each one, before exiting.
+	turntable: Option<u32>,
+	/// The directory turntable frames are saved into.
+	output: PathBuf,
+	/// The file to record each frame's input to.
+	record: Option<PathBuf>,
+	/// The file to play recorded input back from, in place of live input.
+	playback: Option<PathBuf>,
+	/// The file to save a contact sheet of turbidities and sun elevations to,
+	/// rather than opening a window.
+	contact_sheet: Option<PathBuf>,
+	/// The width of each tile in the contact sheet, in pixels.
+	tile_size: Option<u32>,
+	/// The file to load the model's datasets from, in place of the built-in
+	/// ones.
+	datasets: Option<PathBuf>,
+	/// The built-in viewpoint to start from.
+	viewpoint: Option<&'static Viewpoint>,
+}
 
-	// for i in 0 .. 10 {
-	// 	println!("params {}: {:?}", i, params[i]);
-	// }
+/// Parses the command line arguments.
+///
+/// Fails if an option isn't recognised or a number can't be parsed.
+fn parse_args() -> Result<Args, SkyError> {
+	let mut args = env::args().skip(1);
+	let mut parsed = Args {
+		config: None,
+		info: false,
+		dump_coeffs: false,
+		nearest_turbidity: false,
+		turbidity: None,
+		albedo: None,
+		elevation: 0.0,
+		azimuth: 0.0,
+		resolution: None,
+		turntable: None,
+		output: PathBuf::from(DEFAULT_TURNTABLE_OUTPUT),
+		record: None,
+		playback: None,
+		contact_sheet: None,
+		tile_size: None,
+		datasets: None,
+		viewpoint: None,
+	};
+	while let Some(arg) = args.next() {
+		if arg == "--dump-coeffs" {
+			parsed.dump_coeffs = true;
+			continue;
+		}
+		if arg == "--info" {
+			parsed.info = true;
+			continue;
+		}
+		if arg == "--nearest-turbidity" {
+			parsed.nearest_turbidity = true;
+			continue;
+		}
+		match (arg.as_str(), args.next()) {
+			("--config", Some(value)) => parsed.config = Some(PathBuf::from(value)),
+			("--turbidity", Some(value)) => parsed.turbidity = Some(
+				parse_checked_number("--turbidity", &value, config::check_turbidity)?),
+			("--albedo", Some(value)) => parsed.albedo = Some(
+				parse_checked_number("--albedo", &value, config::check_albedo)?),
+			("--elevation", Some(value)) => parsed.elevation = parse_number("--elevation", &value)?,
+			("--azimuth", Some(value)) => parsed.azimuth = parse_number("--azimuth", &value)?,
+			("--resolution", Some(value)) =>
+				parsed.resolution = Some(parse_resolution(&value)?),
+			("--turntable", Some(value)) =>
+				parsed.turntable = Some(parse_frame_count(&value)?),
+			("--output", Some(value)) => parsed.output = PathBuf::from(value),
+			("--record", Some(value)) => parsed.record = Some(PathBuf::from(value)),
+			("--playback", Some(value)) => parsed.playback = Some(PathBuf::from(value)),
+			("--contact-sheet", Some(value)) =>
+				parsed.contact_sheet = Some(PathBuf::from(value)),
+			("--tile-size", Some(value)) => parsed.tile_size = Some(parse_tile_size(&value)?),
+			("--datasets", Some(value)) => parsed.datasets = Some(PathBuf::from(value)),
+			("--viewpoint", Some(value)) => parsed.viewpoint = Some(parse_viewpoint(&value)?),
+			_ => return Err(SkyError::Usage),
+		}
+	}
+	if parsed.record.is_some() && parsed.playback.is_some() {
+		return Err(SkyError::Argument(
+			"can't record and play back input at the same time".to_string()));
+	}
+	Ok(parsed)
+}
 
-	(sun_dir, params)
+/// Parses the value of a numeric command line argument, given as `flag`.
+fn parse_number(flag: &str, value: &str) -> Result<f32, SkyError> {
+	value.parse::<f32>()
+		.ok()
+		.filter(|value| value.is_finite())
+		.ok_or_else(|| SkyError::Argument(format!("`{}` must be a number, not `{}`",
+			flag, value)))
 }
 
+/// Parses the value of a numeric command line argument, given as `flag`, and
+/// checks it with the same function as the config file's setting.
+fn parse_checked_number(flag: &str, value: &str, check: fn(f32) -> Result<(), &'static str>)
+		-> Result<f32, SkyError> {
+	let number = parse_number(flag, value)?;
+	check(number).map_err(|message| SkyError::Argument(format!("`{}` {}", flag, message)))?;
+	Ok(number)
+}
 
-fn evaluate_spline(dataset: &[f32], start: usize, stride: usize, value: f32) -> f32 {
-	1.0 *  (1.0 - value).powi(5) *                 dataset[start + 0 * stride] +
-	5.0 *  (1.0 - value).powi(4) * value.powi(1) * dataset[start + 1 * stride] +
-	10.0 * (1.0 - value).powi(3) * value.powi(2) * dataset[start + 2 * stride] +
-	10.0 * (1.0 - value).powi(2) * value.powi(3) * dataset[start + 3 * stride] +
-	5.0 *  (1.0 - value).powi(1) * value.powi(4) * dataset[start + 4 * stride] +
-	1.0 *                          value.powi(5) * dataset[start + 5 * stride]
+/// Parses the name of a built-in viewpoint.
+fn parse_viewpoint(value: &str) -> Result<&'static Viewpoint, SkyError> {
+	viewpoint::find(value).ok_or_else(|| SkyError::Argument(format!(
+		"unknown viewpoint `{}`, expected one of: {}", value,
+		viewpoint::names().join(", "))))
 }
 
-fn evaluate(dataset: &[f32], stride: usize, turbidity: f32, albedo: f32, sun_theta: f32) -> f32 {
-	// splines are functions of elevation^1/3
-	let elevationK = (1.0 - sun_theta / f32::consts::FRAC_PI_2).max(0.0).powf(1.0 / 3.0);
+/// Parses the number of frames in a turntable, which must be at least 1.
+fn parse_frame_count(value: &str) -> Result<u32, SkyError> {
+	match value.parse::<u32>() {
+		Ok(frames) if frames > 0 => Ok(frames),
+		_ => Err(SkyError::Argument(format!(
+			"turntable frame count `{}` must be a positive whole number", value))),
+	}
+}
 
-	// table has values for turbidity 1..10
-	let turbidity0 = clamp(turbidity as usize, 1, 10);
-	let turbidity1 = min(turbidity0 + 1, 10);
-	let turbidityK = clamp(turbidity - turbidity0 as f32, 0.0, 1.0);
+/// Parses the width of a contact sheet's tiles, which must be at least 2 so
+/// they're at least a pixel tall.
+fn parse_tile_size(value: &str) -> Result<u32, SkyError> {
+	match value.parse::<u32>() {
+		Ok(size) if size >= 2 => Ok(size),
+		_ => Err(SkyError::Argument(format!(
+			"tile size `{}` must be a whole number of at least 2", value))),
+	}
+}
 
-	let datasetA0 = 0;
-	let datasetA1 = stride * 6 * 10;
+/// Parses a window size given as `WIDTHxHEIGHT`, like `1280x720`.
+fn parse_resolution(value: &str) -> Result<(u32, u32), SkyError> {
+	let mut parts = value.splitn(2, 'x');
+	let width = parts.next().and_then(|width| width.parse::<u32>().ok());
+	let height = parts.next().and_then(|height| height.parse::<u32>().ok());
+	match (width, height) {
+		(Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+		(Some(_), Some(_)) => Err(SkyError::Argument(
+			format!("resolution `{}` can't have a zero dimension", value))),
+		_ => Err(SkyError::Argument(format!(
+			"resolution `{}` must be given as WIDTHxHEIGHT, like 1280x720", value))),
+	}
+}
 
-	let a0t0 = evaluate_spline(dataset, datasetA0 + stride * 6 * (turbidity0 - 1), stride, elevationK);
-	let a1t0 = evaluate_spline(dataset, datasetA1 + stride * 6 * (turbidity0 - 1), stride, elevationK);
-	let a0t1 = evaluate_spline(dataset, datasetA0 + stride * 6 * (turbidity1 - 1), stride, elevationK);
-	let a1t1 = evaluate_spline(dataset, datasetA1 + stride * 6 * (turbidity1 - 1), stride, elevationK);
+/// Loads the config file named by the `--config path` argument, or the
+/// default config file if there isn't one. Falls back to the default settings
+/// if the file doesn't exist.
+///
+/// Fails if the config file is invalid.
+fn load_config(path: Option<PathBuf>) -> Result<Config, SkyError> {
+	let explicit = path.is_some();
+	let path = path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+	match Config::load(&path) {
+		Ok(config) => Ok(config),
+		Err(ConfigError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => {
+			// Only mention a missing file if it was asked for
+			if explicit {
+				println!("Config file {} not found, using defaults", path.display());
+			}
+			Ok(Config::default())
+		},
+		Err(error) => Err(SkyError::Config { path: path, error: error }),
+	}
+}
 
-	a0t0 * (1.0 - albedo) * (1.0 - turbidityK) + a1t0 * albedo * (1.0 - turbidityK) + a0t1 * (1.0 - albedo) * turbidityK + a1t1 * albedo * turbidityK
+/// Loads the datasets named by the `--datasets path` argument, or returns the
+/// built-in ones if there isn't one. Fails the same way as a bad config file
+/// if the file can't be loaded, or its datasets are invalid.
+fn load_datasets(args: &Args) -> Result<Datasets, SkyError> {
+	match args.datasets {
+		Some(ref path) => Datasets::load(path).map_err(|err| SkyError::Config {
+			path: path.clone(),
+			error: ConfigError::Io(err),
+		}),
+		None => Ok(Datasets::embedded()),
+	}
 }
 
-fn hosek_wilkie(cos_theta: f32, gamma: f32, cos_gamma: f32, params: &[Vector3<f32>]) -> Vector3<f32> {
-	let A = params[0];
-	let B = params[1];
-	let C = params[2];
-	let D = params[3];
-	let E = params[4];
-	let F = params[5];
-	let G = params[6];
-	let H = params[7];
-	let I = params[8];
-
-	// println!("INPUT");
-	// println!("cos theta {}", cos_theta);
-	// println!("gamma {}", gamma);
-	// println!("cos gamma {}", cos_gamma);
-	// println!("params {:?}",  params);
-	// println!("END INPUT");
-
-	// println!("cos gamma {}", cos_gamma);
-    // float3 chi = (1.f + cos_gamma * cos_gamma) / pow(1.f + H * H - 2.f * cos_gamma * H, float3(1.5f));
-
-	let chi = (1.0 + cos_gamma * cos_gamma) / powv(H.mul_element_wise(H).add_element_wise(1.0) - 2.0 * cos_gamma * H, Vector3::new(1.5, 1.5, 1.5));
-	// println!("denom {:?}",  powv(H.mul_element_wise(H).add_element_wise(1.0) - 2.0 * cos_gamma * H, Vector3::new(1.5, 1.5, 1.5)));
-	// println!("chi {:?}", chi);
-	(A.mul_element_wise(exp(B / (cos_theta + 0.01))).add_element_wise(1.0)).mul_element_wise((C + D.mul_element_wise(exp(E * gamma)) + F * (cos_gamma * cos_gamma) + G.mul_element_wise(chi) + I * cos_theta.max(0.0).sqrt()))
+/// Renders a contact sheet of the sky across every turbidity and a range of sun
+/// elevations, with the config file's albedo, and saves it to a PNG file.
+///
+/// Fails if the file can't be saved.
+fn save_contact_sheet(args: &Args, config: &Config, path: &PathBuf)
+		-> Result<(), SkyError> {
+	let layout = contact_sheet::SheetLayout {
+		tile_size: args.tile_size.unwrap_or(contact_sheet::SheetLayout::default().tile_size),
+		albedo: config.albedo,
+		exposure: EXPOSURE,
+		datasets: load_datasets(args)?,
+		..contact_sheet::SheetLayout::default()
+	};
+	let sheet = contact_sheet::render(&layout);
+	capture::save_png(path, sheet.width, sheet.height, &sheet.pixels)
+		.map_err(|err| SkyError::Capture { path: path.clone(), message: err.to_string() })?;
+	println!("Saved contact sheet to {}", path.display());
+	Ok(())
 }
 
-fn powv(a: Vector3<f32>, b: Vector3<f32>) -> Vector3<f32> {
-	Vector3::new(a.x.powf(b.x), a.y.powf(b.y), a.z.powf(b.z))
+/// Splits the width of the screen into left and right halves, as the x
+/// coordinate and width of each, in pixels. The right half gets the extra
+/// pixel if the width is odd.
+fn split_halves(width: GLint) -> [(GLint, GLint); 2] {
+	let half_width = width / 2;
+	[(0, half_width), (half_width, width - half_width)]
 }
 
-fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
-	if value < min {
-		min
-	} else if value > max {
-		max
+/// Returns the coordinate convention the config file asks for.
+fn handedness(config: &Config) -> Handedness {
+	if config.left_handed {
+		Handedness::Left
 	} else {
-		value
+		Handedness::Right
 	}
 }
 
-fn min<T: PartialOrd>(value: T, min: T) -> T {
-	if value < min {
-		value
+/// Returns the units the config file asks for the sky's radiance in.
+fn radiance_units(config: &Config) -> RadianceUnits {
+	if config.absolute_radiance {
+		RadianceUnits::Absolute
 	} else {
-		min
+		RadianceUnits::Normalized
 	}
 }
 
-fn exp(vec: Vector3<f32>) -> Vector3<f32> {
-	Vector3::new(vec.x.exp(), vec.y.exp(), vec.z.exp())
+/// Prints the coefficients for the sky described by the command line to
+/// stdout as JSON, using the config file for anything not given.
+///
+/// Fails if the datasets can't be loaded.
+fn dump_coeffs(args: &Args, config: &Config) -> Result<(), SkyError> {
+	// Both have already been checked, the same way on the command line as in
+	// the config file
+	let turbidity = args.turbidity.unwrap_or(config.turbidity);
+	let albedo = args.albedo.map(|albedo| [albedo; 3]).unwrap_or(config.albedo);
+	let sun = Vector2::new(args.elevation.to_radians(), args.azimuth.to_radians());
+	let mut sky = SkyModel::new(turbidity, albedo, sun);
+	sky.set_units(radiance_units(config));
+	sky.set_handedness(handedness(config));
+	sky.set_datasets(load_datasets(args)?);
+	sky.set_nearest_turbidity(config.nearest_turbidity);
+	println!("{}", dump::coefficients_json(&sky, args.elevation, args.azimuth));
+	Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_checked_number, parse_number, parse_resolution};
+	use config;
+	use error::SkyError;
+
+	/// Parses a resolution, returning the error message if it's rejected.
+	fn error(value: &str) -> String {
+		match parse_resolution(value) {
+			Ok(size) => panic!("`{}` was parsed as {:?}", value, size),
+			Err(SkyError::Argument(message)) => message,
+			Err(err) => panic!("unexpected error {}", err),
+		}
+	}
+
+	#[test]
+	fn resolution_is_parsed() {
+		assert_eq!(parse_resolution("1280x720").unwrap(), (1280, 720));
+		assert_eq!(parse_resolution("1x1").unwrap(), (1, 1));
+	}
+
+	#[test]
+	fn bad_resolutions_are_rejected() {
+		assert_eq!(error("0x720"), "resolution `0x720` can't have a zero dimension");
+		assert_eq!(error("1280x0"), "resolution `1280x0` can't have a zero dimension");
+
+		// Missing a dimension, or the `x` between them
+		let format = |value| format!(
+			"resolution `{}` must be given as WIDTHxHEIGHT, like 1280x720", value);
+		for &value in &["1280", "1280x", "x720", "1280*720", "", "1280x720x2"] {
+			assert_eq!(error(value), format(value));
+		}
+
+		// Too big to fit, or negative
+		for &value in &["4294967296x720", "1280x99999999999", "-1280x720"] {
+			assert_eq!(error(value), format(value));
+		}
+	}
+
+	#[test]
+	fn numbers_are_checked_like_the_config_file() {
+		let message = |result: Result<f32, SkyError>| match result {
+			Err(SkyError::Argument(message)) => message,
+			other => panic!("unexpected result {:?}", other),
+		};
+		assert_eq!(parse_number("--elevation", "-12.5").unwrap(), -12.5);
+		assert_eq!(message(parse_number("--elevation", "high")),
+			"`--elevation` must be a number, not `high`");
+		assert_eq!(message(parse_number("--azimuth", "NaN")),
+			"`--azimuth` must be a number, not `NaN`");
+
+		let turbidity = |value| parse_checked_number("--turbidity", value,
+			config::check_turbidity);
+		assert_eq!(turbidity("4").unwrap(), 4.0);
+		assert_eq!(message(turbidity("11")), "`--turbidity` must be between 1 and 10");
+		assert_eq!(message(turbidity("nan")), "`--turbidity` must be a number, not `nan`");
+
+		let albedo = |value| parse_checked_number("--albedo", value, config::check_albedo);
+		assert_eq!(albedo("0").unwrap(), 0.0);
+		assert_eq!(message(albedo("-0.1")), "`--albedo` must be between 0 and 1");
+	}
 }
@@ -0,0 +1,135 @@
+
+//
+//  Math Helpers
+//
+
+use cgmath::{Matrix3, Vector3};
+use std::ops::{Add, Mul, Sub};
+
+
+/// Which way round the world's axes are. Either way x is to the right when
+/// looking from the default camera position and y is up; the conventions
+/// differ in the sign of z. Right-handed is the default, matching OpenGL.
+///
+/// Only the coordinates are mirrored, not the scene: the same camera and sun
+/// settings give the same picture under either convention.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Handedness {
+	/// Right-handed coordinates, where z points back towards the default
+	/// camera position.
+	Right,
+	/// Left-handed coordinates, where z points away from the default camera
+	/// position, as in Direct3D and Unity.
+	Left,
+}
+
+impl Handedness {
+	/// Converts a vector between right-handed coordinates and this convention.
+	/// Converting twice gives back the original vector, so this works in
+	/// either direction.
+	pub fn convert(self, v: Vector3<f32>) -> Vector3<f32> {
+		match self {
+			Handedness::Right => v,
+			Handedness::Left => Vector3::new(v.x, v.y, -v.z),
+		}
+	}
+
+	/// Returns the matrix form of `convert`.
+	pub fn mirror(self) -> Matrix3<f32> {
+		match self {
+			Handedness::Right => Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0),
+			Handedness::Left => Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, -1.0),
+		}
+	}
+
+	/// Returns true if this convention mirrors right-handed coordinates, which
+	/// turns the winding of any triangles given in it inside out.
+	pub fn is_mirrored(self) -> bool {
+		self == Handedness::Left
+	}
+}
+
+
+/// Restricts a value to a range, returning `min` if it's below the range and
+/// `max` if it's above.
+pub fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
+	if value < min {
+		min
+	} else if value > max {
+		max
+	} else {
+		value
+	}
+}
+
+/// Restricts a value to the range [0, 1], like HLSL's `saturate`.
+pub fn saturate(value: f32) -> f32 {
+	clamp(value, 0.0, 1.0)
+}
+
+/// Interpolates linearly from `a` to `b`, where `t` is 0 for `a` and 1 for
+/// `b`. Works on scalars and on cgmath's vectors. `t` isn't clamped, so values
+/// outside [0, 1] extrapolate.
+pub fn lerp<T>(a: T, b: T, t: f32) -> T
+		where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T> {
+	a + (b - a) * t
+}
+
+/// Smoothly interpolates from 0 to 1 as a value moves from `edge0` to
+/// `edge1`, like GLSL's `smoothstep`. The edges can be given in either order.
+pub fn smoothstep(edge0: f32, edge1: f32, value: f32) -> f32 {
+	let t = saturate((value - edge0) / (edge1 - edge0));
+	t * t * (3.0 - 2.0 * t)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{clamp, saturate, lerp, smoothstep, Handedness};
+
+	use cgmath::Vector3;
+
+	#[test]
+	fn clamp_edges() {
+		assert_eq!(clamp(5, 1, 3), 3);
+		assert_eq!(clamp(-5, 1, 3), 1);
+		assert_eq!(clamp(2, 1, 3), 2);
+		assert_eq!(clamp(1.0, 1.0, 1.0), 1.0);
+		assert_eq!(saturate(-0.0), 0.0);
+		assert_eq!(saturate(1.5), 1.0);
+		assert_eq!(saturate(0.25), 0.25);
+	}
+
+	#[test]
+	fn lerp_scalars_and_vectors() {
+		assert_eq!(lerp(2.0, 4.0, 0.0), 2.0);
+		assert_eq!(lerp(2.0, 4.0, 1.0), 4.0);
+		assert_eq!(lerp(2.0, 4.0, 1.5), 5.0);
+		let a = Vector3::new(0.0, 1.0, -2.0);
+		let b = Vector3::new(4.0, 1.0, 2.0);
+		assert_eq!(lerp(a, b, 0.25), Vector3::new(1.0, 1.0, -1.0));
+	}
+
+	#[test]
+	fn smoothstep_edges() {
+		assert_eq!(smoothstep(0.0, 1.0, -1.0), 0.0);
+		assert_eq!(smoothstep(0.0, 1.0, 2.0), 1.0);
+		assert_eq!(smoothstep(0.0, 1.0, 0.5), 0.5);
+
+		// Reversed edges fall from 1 to 0
+		assert_eq!(smoothstep(1.0, 0.0, 0.0), 1.0);
+		assert_eq!(smoothstep(1.0, 0.0, 1.0), 0.0);
+		assert!(smoothstep(0.0, 2.0, 0.5) < 0.25);
+	}
+
+	#[test]
+	fn handedness_conversion_round_trips() {
+		let v = Vector3::new(1.0, 2.0, 3.0);
+		assert_eq!(Handedness::Right.convert(v), v);
+		assert_eq!(Handedness::Left.convert(v), Vector3::new(1.0, 2.0, -3.0));
+		for &handedness in &[Handedness::Right, Handedness::Left] {
+			assert_eq!(handedness.convert(handedness.convert(v)), v);
+			assert_eq!(handedness.mirror() * v, handedness.convert(v));
+		}
+	}
+}
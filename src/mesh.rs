@@ -0,0 +1,163 @@
+
+//
+//  Meshes
+//
+
+use gl;
+use gl::types::*;
+
+use std::{mem, ptr};
+
+
+/// A piece of geometry, owning the vertex array and buffers it's drawn from.
+/// Each vertex has a single attribute made up of a number of floats, or none
+/// at all if the vertex shader generates them.
+pub struct Mesh {
+	/// The vertex array describing the vertex layout.
+	vao: GLuint,
+	/// The buffer holding the vertices, or 0 if there's no vertex data.
+	vbo: GLuint,
+	/// The buffer holding the indices, or 0 if the vertices are drawn in
+	/// order.
+	ibo: GLuint,
+	/// The kind of primitive the vertices form, like `gl::TRIANGLES`.
+	mode: GLenum,
+	/// The number of floats making up each vertex, or 0 if there's no vertex
+	/// data.
+	components: i32,
+	/// The number of vertices (or indices, if there are any) to draw.
+	count: GLsizei,
+}
+
+impl Mesh {
+	/// Creates a mesh which draws its vertices in order. Every `components`
+	/// floats make up a vertex, which is passed to the attribute at `location`.
+	pub fn new(mode: GLenum, vertices: &[GLfloat], components: i32,
+			location: GLuint) -> Mesh {
+		let mut mesh = Mesh::with_vertices(mode, vertices, components, location);
+		mesh.count = (vertices.len() / components as usize) as GLsizei;
+		mesh.finish();
+		mesh
+	}
+
+	/// Creates a mesh without any vertex data, for vertex shaders which work
+	/// out their vertices from `gl_VertexID`, like the fullscreen triangle. A
+	/// vertex array still has to be bound to draw them.
+	pub fn empty(mode: GLenum, count: GLsizei) -> Mesh {
+		let mut mesh = Mesh {
+			vao: 0,
+			vbo: 0,
+			ibo: 0,
+			mode: mode,
+			components: 0,
+			count: count,
+		};
+		unsafe { gl::GenVertexArrays(1, &mut mesh.vao); }
+		mesh
+	}
+
+	/// Creates a mesh which draws its vertices in the order given by a list
+	/// of indices. The vertices are laid out as for `new`.
+	pub fn indexed(mode: GLenum, vertices: &[GLfloat], indices: &[GLushort],
+			components: i32, location: GLuint) -> Mesh {
+		let mut mesh = Mesh::with_vertices(mode, vertices, components, location);
+		mesh.count = indices.len() as GLsizei;
+		unsafe {
+			gl::GenBuffers(1, &mut mesh.ibo);
+			gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo);
+			gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, byte_size(indices),
+				indices.as_ptr() as *const GLvoid, gl::STATIC_DRAW);
+		}
+		mesh.finish();
+		mesh
+	}
+
+	/// Creates the vertex array and uploads the vertices, leaving the vertex
+	/// array bound.
+	fn with_vertices(mode: GLenum, vertices: &[GLfloat], components: i32,
+			location: GLuint) -> Mesh {
+		let mut mesh = Mesh {
+			vao: 0,
+			vbo: 0,
+			ibo: 0,
+			mode: mode,
+			components: components,
+			count: 0,
+		};
+
+		unsafe {
+			gl::GenVertexArrays(1, &mut mesh.vao);
+			gl::BindVertexArray(mesh.vao);
+
+			gl::GenBuffers(1, &mut mesh.vbo);
+			gl::BindBuffer(gl::ARRAY_BUFFER, mesh.vbo);
+			gl::BufferData(gl::ARRAY_BUFFER, byte_size(vertices),
+				vertices.as_ptr() as *const GLvoid, gl::STATIC_DRAW);
+
+			gl::EnableVertexAttribArray(location);
+			gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, 0,
+				ptr::null());
+		}
+		mesh
+	}
+
+	/// Unbinds the vertex array once it's set up, so later attribute setup
+	/// doesn't modify it.
+	fn finish(&self) {
+		unsafe { gl::BindVertexArray(0); }
+	}
+
+	/// Replaces the vertices of a mesh drawn in order, for geometry that's
+	/// rebuilt every frame. The buffer's reallocated to fit, so the number of
+	/// vertices can change.
+	///
+	/// Panics if the mesh has no vertex data or is indexed.
+	pub fn update(&mut self, vertices: &[GLfloat]) {
+		assert!(self.vbo != 0 && self.ibo == 0, "only unindexed meshes with \
+			vertex data can be updated");
+		self.count = (vertices.len() / self.components as usize) as GLsizei;
+		unsafe {
+			gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+			gl::BufferData(gl::ARRAY_BUFFER, byte_size(vertices),
+				vertices.as_ptr() as *const GLvoid, gl::DYNAMIC_DRAW);
+		}
+	}
+
+	/// Binds the mesh's vertex array, for code which issues its own draw
+	/// calls.
+	pub fn bind(&self) {
+		unsafe { gl::BindVertexArray(self.vao); }
+	}
+
+	/// Draws the mesh with whichever shader program is bound.
+	pub fn draw(&self) {
+		self.bind();
+		unsafe {
+			if self.ibo == 0 {
+				gl::DrawArrays(self.mode, 0, self.count);
+			} else {
+				gl::DrawElements(self.mode, self.count, gl::UNSIGNED_SHORT, ptr::null());
+			}
+		}
+	}
+}
+
+impl Drop for Mesh {
+	fn drop(&mut self) {
+		unsafe {
+			if self.ibo != 0 {
+				gl::DeleteBuffers(1, &self.ibo);
+			}
+			if self.vbo != 0 {
+				gl::DeleteBuffers(1, &self.vbo);
+			}
+			gl::DeleteVertexArrays(1, &self.vao);
+		}
+	}
+}
+
+
+/// Returns the size of a slice in bytes, as OpenGL expects it.
+fn byte_size<T>(data: &[T]) -> GLsizeiptr {
+	mem::size_of_val(data) as GLsizeiptr
+}
@@ -4,9 +4,14 @@
 //
 
 use camera::Camera;
+use config::Config;
+use gamepad::GamepadState;
 use input::{Input, Key};
+use math::clamp;
+use viewpoint::Viewpoint;
 
 use glutin::VirtualKeyCode;
+use cgmath::{Vector2, Vector3, Zero, InnerSpace};
 
 
 /// A mapping between a key code and axis of movement.
@@ -18,43 +23,171 @@ pub struct KeyMap {
 }
 
 /// A map of keys to which axis of movement they control.
-const KEY_MAPPINGS: [KeyMap; 7] = [
+const KEY_MAPPINGS: [KeyMap; 6] = [
 	KeyMap { key: VirtualKeyCode::W,      x:  0, y:  0, z:  1 }, // Forward
 	KeyMap { key: VirtualKeyCode::S,      x:  0, y:  0, z: -1 }, // Back
 	KeyMap { key: VirtualKeyCode::A,      x: -1, y:  0, z:  0 }, // Left
 	KeyMap { key: VirtualKeyCode::D,      x:  1, y:  0, z:  0 }, // Right
-	KeyMap { key: VirtualKeyCode::Space,  x:  0, y:  1, z:  0 }, // Up
-	KeyMap { key: VirtualKeyCode::LShift, x:  0, y: -1, z:  0 }, // Down
-	KeyMap { key: VirtualKeyCode::RShift, x:  0, y: -1, z:  0 }, // Down
+	KeyMap { key: VirtualKeyCode::Space,  x:  0, y:  1, z:  0 }, // Fly up
+	KeyMap { key: VirtualKeyCode::LShift, x:  0, y: -1, z:  0 }, // Fly down
 ];
 
+/// The key held down to crouch.
+const CROUCH_KEY: Key = VirtualKeyCode::Z;
+
+/// How far crouching lowers the camera, in units.
+const CROUCH_DEPTH: f32 = 0.5;
+
+/// How fast the camera lowers into a crouch and rises back up, in units per
+/// second. Slower than flying, so it reads as ducking rather than descending.
+const CROUCH_SPEED: f32 = 3.0;
+
+/// The smallest allowed mouse sensitivity. Stops the camera from being locked
+/// in place, or having its controls reversed.
+const MIN_SENSITIVITY: f32 = 0.05;
+
+/// The largest allowed mouse sensitivity.
+const MAX_SENSITIVITY: f32 = 20.0;
+
+/// How quickly the player reaches full speed when a movement key is held, as
+/// a rate per second. Higher values feel snappier.
+pub const DEFAULT_ACCELERATION: f32 = 10.0;
+
+/// How quickly the player coasts to a stop when no movement keys are held, as
+/// a rate per second. Higher values stop sooner.
+pub const DEFAULT_DAMPING: f32 = 6.0;
+
+/// The speed below which a coasting player is brought to a complete stop, as a
+/// fraction of full speed.
+const STOP_SPEED: f32 = 0.01;
+
+/// How fast a gamepad stick pushed all the way turns the camera, in the same
+/// units as mouse movement (pixels) per second.
+const GAMEPAD_LOOK_RATE: f32 = 1000.0;
+
 
 /// The player, controlling the camera by handling user input.
 pub struct Player {
 	/// The underlying camera the player controls.
 	pub camera: Camera,
+	/// A multiplier applied to mouse movement before it's used to look around.
+	sensitivity: f32,
+	/// True if moving the mouse up should look down, and vice versa.
+	invert_y: bool,
+	/// True if moving the mouse looks around. Turned off while the mouse is
+	/// being used for something else.
+	mouse_look: bool,
+	/// The player's velocity along the camera's right, up, and forward axes,
+	/// as a fraction of full speed.
+	velocity: Vector3<f32>,
+	/// How quickly the velocity approaches full speed while moving.
+	pub acceleration: f32,
+	/// How quickly the velocity decays when no movement keys are held.
+	pub damping: f32,
 }
 
 impl Player {
 	/// Create a new player object.
-	pub fn new(camera: Camera) -> Player {
+	pub fn new(camera: Camera, config: &Config) -> Player {
 		Player {
 			camera: camera,
+			sensitivity: clamp(config.sensitivity, MIN_SENSITIVITY, MAX_SENSITIVITY),
+			invert_y: config.invert_y,
+			mouse_look: true,
+			velocity: Vector3::zero(),
+			acceleration: DEFAULT_ACCELERATION,
+			damping: DEFAULT_DAMPING,
 		}
 	}
 
-	/// Called every frame to update the player's motion.
-	pub fn update(&mut self, input: &Input, delta: f32) {
-		// Movement
+	/// Sets the mouse sensitivity, clamped to a sensible positive range.
+	pub fn set_sensitivity(&mut self, sensitivity: f32) {
+		self.sensitivity = clamp(sensitivity, MIN_SENSITIVITY, MAX_SENSITIVITY);
+	}
+
+	/// Returns the mouse sensitivity.
+	pub fn sensitivity(&self) -> f32 {
+		self.sensitivity
+	}
+
+	/// Sets whether vertical mouse movement is inverted.
+	pub fn set_invert_y(&mut self, invert_y: bool) {
+		self.invert_y = invert_y;
+	}
+
+	/// Returns true if vertical mouse movement is inverted.
+	pub fn invert_y(&self) -> bool {
+		self.invert_y
+	}
+
+	/// Moves the camera to a viewpoint, updating its matrices, and stops the
+	/// player so they don't drift away from it. The sky's set separately, with
+	/// `Viewpoint::apply_sky`.
+	pub fn teleport_to_viewpoint(&mut self, viewpoint: &Viewpoint) {
+		self.velocity = Vector3::zero();
+		self.camera.set_state(viewpoint.camera);
+	}
+
+	/// Sets whether moving the mouse looks around.
+	pub fn set_mouse_look(&mut self, mouse_look: bool) {
+		self.mouse_look = mouse_look;
+	}
+
+	/// Called every frame to update the player's motion, where `delta` is the
+	/// time since the last frame, in seconds. The keyboard, mouse, and gamepad
+	/// can all be used at once.
+	pub fn update(&mut self, input: &Input, gamepad: &GamepadState, delta: f32) {
+		// Movement, combining the keys with the gamepad's analog input
 		let (x, y, z) = self.motion_vector(input);
-		if x != 0 || y != 0 || z != 0 {
-			self.camera.walk(x as f32, y as f32, z as f32, delta);
+		let target = Vector3::new(
+			clamp(x as f32 + gamepad.movement.x, -1.0, 1.0),
+			clamp(y as f32 + gamepad.movement.y, -1.0, 1.0),
+			clamp(z as f32 + gamepad.movement.z, -1.0, 1.0),
+		);
+		if target != Vector3::zero() {
+			// Ease towards the velocity the input asks for. Using an
+			// exponential keeps this stable no matter how long the frame took
+			let t = 1.0 - (-self.acceleration * delta).exp();
+			self.velocity += (target - self.velocity) * t;
+		} else {
+			// Coast to a stop
+			self.velocity *= (-self.damping * delta).exp();
+			if self.velocity.magnitude() < STOP_SPEED {
+				self.velocity = Vector3::zero();
+			}
+		}
+		if self.velocity != Vector3::zero() {
+			self.camera.walk(self.velocity.x, self.velocity.y, self.velocity.z,
+				delta);
+		}
+
+		// Crouch while the key is held, and stand back up when it's released
+		let target = if input.is_key_down(CROUCH_KEY) { CROUCH_DEPTH } else { 0.0 };
+		let crouch = self.camera.crouch();
+		if crouch != target {
+			let step = CROUCH_SPEED * delta;
+			self.camera.set_crouch(clamp(target, crouch - step, crouch + step));
 		}
 
 		// Look
 		let (dx, dy) = input.mouse_delta();
-		if dx != 0.0 || dy != 0.0 {
-			self.camera.look(dx, dy, delta);
+		if self.mouse_look && (dx != 0.0 || dy != 0.0) {
+			let dx = dx * self.sensitivity;
+			let mut dy = dy * self.sensitivity;
+			if self.invert_y {
+				dy = -dy;
+			}
+
+			// The mouse delta is already the total movement over the frame, so
+			// it doesn't need scaling by the frame time
+			self.camera.look(dx, dy, 1.0);
+		}
+
+		// The stick's position is a turning speed, so it's scaled by the
+		// frame time
+		if gamepad.look != Vector2::zero() {
+			self.camera.look(gamepad.look.x * GAMEPAD_LOOK_RATE,
+				gamepad.look.y * GAMEPAD_LOOK_RATE, delta);
 		}
 	}
 
@@ -80,13 +213,3 @@ impl Player {
 	}
 }
 
-/// Restrict a value to a range.
-fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
-	if value < min {
-		min
-	} else if value > max {
-		max
-	} else {
-		value
-	}
-}
@@ -0,0 +1,220 @@
+
+//
+//  Input Recording
+//
+
+use gamepad::GamepadState;
+use input::InputFrame;
+
+use cgmath::{Vector2, Vector3};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::vec;
+
+
+/// The first line of a recording, describing the fields on each line after it.
+const HEADER: &'static str = "# frame_time mouse_x mouse_y keys_down keys_pressed \
+	buttons_down buttons_pressed move_x move_y move_z look_x look_y";
+
+/// The number of fields on each line of a recording.
+const FIELDS: usize = 12;
+
+/// Written in place of an empty list, so every line has the same number of
+/// fields.
+const EMPTY_LIST: &'static str = "-";
+
+
+/// Everything the player and the sky's controls read in a single frame. Played
+/// back with the same frame times, a recording makes the same run every time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+	/// The time since the last frame, in seconds.
+	pub frame_time: f32,
+	/// The keyboard and mouse state.
+	pub input: InputFrame,
+	/// The game controller state.
+	pub gamepad: GamepadState,
+}
+
+
+/// Writes each frame's input to a file, one line per frame.
+pub struct Recorder {
+	/// The file being written to.
+	writer: BufWriter<File>,
+}
+
+impl Recorder {
+	/// Creates a recording, replacing the file if it already exists.
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		writeln!(writer, "{}", HEADER)?;
+		Ok(Recorder { writer: writer })
+	}
+
+	/// Adds a frame to the end of the recording.
+	pub fn record(&mut self, frame: &RecordedFrame) -> io::Result<()> {
+		writeln!(self.writer, "{}", format_frame(frame))
+	}
+
+	/// Writes out any frames still buffered.
+	pub fn finish(mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+
+/// Feeds the frames of a recording back in order.
+pub struct Playback {
+	/// The frames left to play.
+	frames: vec::IntoIter<RecordedFrame>,
+}
+
+impl Playback {
+	/// Loads a whole recording.
+	///
+	/// Fails if the file can't be read, or any line can't be parsed.
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Playback> {
+		let mut source = String::new();
+		File::open(path)?.read_to_string(&mut source)?;
+		parse_recording(&source)
+			.map(|frames| Playback { frames: frames.into_iter() })
+			.map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+	}
+}
+
+impl Iterator for Playback {
+	type Item = RecordedFrame;
+
+	fn next(&mut self) -> Option<RecordedFrame> {
+		self.frames.next()
+	}
+}
+
+
+/// Formats a frame as a line of a recording. Numbers are written in full, so
+/// they're read back exactly.
+fn format_frame(frame: &RecordedFrame) -> String {
+	let input = &frame.input;
+	let movement = frame.gamepad.movement;
+	let look = frame.gamepad.look;
+	format!("{} {} {} {} {} {} {} {} {} {} {} {}", frame.frame_time,
+		input.mouse_delta.0, input.mouse_delta.1, format_list(&input.keys_down),
+		format_list(&input.keys_pressed), format_list(&input.mouse_buttons_down),
+		format_list(&input.mouse_buttons_pressed), movement.x, movement.y,
+		movement.z, look.x, look.y)
+}
+
+/// Formats a list of indices separated by commas.
+fn format_list(indices: &[usize]) -> String {
+	if indices.is_empty() {
+		return EMPTY_LIST.to_string();
+	}
+	indices.iter()
+		.map(|index| index.to_string())
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// Parses every frame of a recording, skipping blank lines and comments.
+fn parse_recording(source: &str) -> Result<Vec<RecordedFrame>, String> {
+	source.lines()
+		.enumerate()
+		.map(|(index, line)| (index + 1, line.trim()))
+		.filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'))
+		.map(|(line_number, line)| parse_frame(line)
+			.map_err(|message| format!("line {}: {}", line_number, message)))
+		.collect()
+}
+
+/// Parses a single line of a recording.
+fn parse_frame(line: &str) -> Result<RecordedFrame, String> {
+	let fields: Vec<&str> = line.split_whitespace().collect();
+	if fields.len() != FIELDS {
+		return Err(format!("expected {} fields, found {}", FIELDS, fields.len()));
+	}
+	let number = |index: usize| fields[index].parse::<f32>()
+		.map_err(|_| format!("`{}` is not a number", fields[index]));
+
+	Ok(RecordedFrame {
+		frame_time: number(0)?,
+		input: InputFrame {
+			mouse_delta: (number(1)?, number(2)?),
+			keys_down: parse_list(fields[3])?,
+			keys_pressed: parse_list(fields[4])?,
+			mouse_buttons_down: parse_list(fields[5])?,
+			mouse_buttons_pressed: parse_list(fields[6])?,
+		},
+		gamepad: GamepadState {
+			movement: Vector3::new(number(7)?, number(8)?, number(9)?),
+			look: Vector2::new(number(10)?, number(11)?),
+		},
+	})
+}
+
+/// Parses a list of indices separated by commas.
+fn parse_list(field: &str) -> Result<Vec<usize>, String> {
+	if field == EMPTY_LIST {
+		return Ok(Vec::new());
+	}
+	field.split(',')
+		.map(|index| index.parse()
+			.map_err(|_| format!("`{}` is not a key or button", index)))
+		.collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{RecordedFrame, format_frame, parse_recording, HEADER};
+	use gamepad::GamepadState;
+	use input::InputFrame;
+
+	use cgmath::{Vector2, Vector3};
+
+	#[test]
+	fn frames_read_back_exactly() {
+		let frames = vec![
+			RecordedFrame {
+				frame_time: 1.0 / 60.0,
+				input: InputFrame {
+					keys_down: vec![2, 22],
+					keys_pressed: vec![22],
+					mouse_buttons_down: vec![0],
+					mouse_buttons_pressed: Vec::new(),
+					mouse_delta: (-3.5, 0.1),
+				},
+				gamepad: GamepadState {
+					movement: Vector3::new(0.0, 0.3, -1.0),
+					look: Vector2::new(0.1, 0.2),
+				},
+			},
+			RecordedFrame {
+				frame_time: 0.0123,
+				input: InputFrame {
+					keys_down: Vec::new(),
+					keys_pressed: Vec::new(),
+					mouse_buttons_down: Vec::new(),
+					mouse_buttons_pressed: Vec::new(),
+					mouse_delta: (0.0, 0.0),
+				},
+				gamepad: GamepadState::idle(),
+			},
+		];
+
+		let mut source = format!("{}\n", HEADER);
+		for frame in &frames {
+			source.push_str(&format_frame(frame));
+			source.push('\n');
+		}
+		assert_eq!(parse_recording(&source), Ok(frames));
+	}
+
+	#[test]
+	fn malformed_lines_rejected() {
+		assert_eq!(parse_recording("# comment\n\n0.1 0 0 - - - -"),
+			Err("line 3: expected 12 fields, found 7".to_string()));
+		assert_eq!(parse_recording("0.1 0 0 4,x - - - 0 0 0 0 0"),
+			Err("line 1: `x` is not a key or button".to_string()));
+	}
+}
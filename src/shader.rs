@@ -5,48 +5,133 @@
 
 use gl;
 use gl::types::*;
+use cgmath::{Matrix, Matrix3, Matrix4, Vector3};
 
+use std::fmt;
 use std::ptr;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::ffi::CString;
 
+/// The directory shader sources are read from during development.
+const SHADER_DIR: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
 /// The possible shader types.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ShaderType {
 	Vertex = gl::VERTEX_SHADER as isize,
 	Fragment = gl::FRAGMENT_SHADER as isize,
+	Geometry = gl::GEOMETRY_SHADER as isize,
+	Compute = gl::COMPUTE_SHADER as isize,
+}
+
+impl ShaderType {
+	/// Works out a shader's type from its file name: either the extension,
+	/// like `sky.frag`, or a suffix before a `.glsl` extension, like
+	/// `sky_frag.glsl`. Returns None if the name doesn't say.
+	pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ShaderType> {
+		let path = path.as_ref();
+		let kind = match path.extension().and_then(|extension| extension.to_str()) {
+			Some("glsl") => path.file_stem()
+				.and_then(|stem| stem.to_str())
+				.and_then(|stem| stem.rsplit('_').next()),
+			extension => extension,
+		};
+		match kind {
+			Some("vert") => Some(ShaderType::Vertex),
+			Some("frag") => Some(ShaderType::Fragment),
+			Some("geom") => Some(ShaderType::Geometry),
+			Some("comp") => Some(ShaderType::Compute),
+			_ => None,
+		}
+	}
+}
+
+
+/// The ways loading a shader can fail.
+#[derive(Debug)]
+pub enum ShaderError {
+	/// The file couldn't be read.
+	Io { path: PathBuf, error: io::Error },
+	/// The shader's type couldn't be worked out from its file name.
+	UnknownType(PathBuf),
+	/// An `#include` couldn't be resolved.
+	Preprocess { name: String, message: String },
+	/// The driver rejected the source.
+	Compile { name: String, message: String },
 }
 
+impl fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ShaderError::Io { ref path, ref error } =>
+				write!(f, "Failed to read shader {}: {}", path.display(), error),
+			ShaderError::UnknownType(ref path) =>
+				write!(f, "Can't tell the type of shader {} from its extension \
+					(expected .vert, .frag, .geom or .comp)", path.display()),
+			ShaderError::Preprocess { ref name, ref message } =>
+				write!(f, "Failed to preprocess shader {}:\n{}", name, message),
+			ShaderError::Compile { ref name, ref message } =>
+				write!(f, "Failed to compile shader {}:\n{}", name, message),
+		}
+	}
+}
+
+
 /// A single OpenGL shader.
 pub struct Shader(GLuint);
 
 impl Shader {
-	/// Create a new shader, compiling the given source code.
-	///
-	/// Panics if shader compilation fails.
-	// TODO: Not panic when compilation fails, but create a custom error object
-	// and return that instead
-	pub fn new(kind: ShaderType, source: &str) -> Shader {
+	/// Loads and compiles a shader file, working out its type from the file
+	/// name with `ShaderType::from_path`. Includes are resolved relative to
+	/// the file's directory, and errors name the file they occurred in.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Shader, ShaderError> {
+		let path = path.as_ref();
+		let (kind, source) = read_file(path)?;
+		let directory = path.parent().unwrap_or_else(|| Path::new(""));
+		let name = path.file_name().map_or_else(|| path.to_string_lossy(),
+			|name| name.to_string_lossy());
+		Shader::try_with_includes(kind, &name, &source, &Includes::Directory(directory))
+	}
+
+	/// Resolves a shader's includes and compiles it. The name is used to
+	/// identify the shader in error messages.
+	fn try_with_includes(kind: ShaderType, name: &str, source: &str,
+			includes: &Includes) -> Result<Shader, ShaderError> {
+		let preprocessed = preprocess(name, source, includes)
+			.map_err(|message| ShaderError::Preprocess {
+				name: name.to_string(),
+				message: message,
+			})?;
+
+		let shader = Shader::create(kind, &preprocessed.source);
+		match shader.compile() {
+			Some(message) => Err(ShaderError::Compile {
+				name: name.to_string(),
+				message: preprocessed.annotate(&message),
+			}),
+			None => Ok(shader),
+		}
+	}
+
+	/// Creates a shader object and attaches the source to it.
+	fn create(kind: ShaderType, source: &str) -> Shader {
 		let id = unsafe { gl::CreateShader(kind as GLenum) };
 
 		// Attach the source to the shader
 		let c_str = CString::new(source.as_bytes()).unwrap();
 		unsafe { gl::ShaderSource(id, 1, &c_str.as_ptr(), ptr::null()); }
-
-		// Compile the shader
-		let shader = Shader(id);
-		shader.compile();
-		shader
+		Shader(id)
 	}
 
-	/// Compiles the shader.
-	fn compile(&self) {
+	/// Compiles the shader, returning the error message if compilation failed.
+	fn compile(&self) -> Option<String> {
 		// Compile the shader
 		unsafe { gl::CompileShader(self.0); }
 
 		// Check for a compilation error
-		match self.error_message() {
-			Some(message) => panic!("Failed to compile shader :\n{}", message),
-			None => {},
-		}
+		self.error_message()
 	}
 
 	/// Returns true if an error occurred during compilation.
@@ -70,15 +155,19 @@ impl Shader {
 				gl::GetShaderiv(self.0, gl::INFO_LOG_LENGTH, &mut length);
 			}
 
+			// Some drivers report an empty log even when compilation fails
+			if length <= 1 {
+				return Some(String::from("(no error message)"));
+			}
+
 			// Get the contents of the message
-			// Set the length of the buffer to skip the NULL terminator at the
-			// end of the string
-			let mut buffer = Vec::with_capacity(length as usize);
+			// Leave room for the NULL terminator, then strip it off
+			let mut buffer = vec![0u8; length as usize];
 			unsafe {
-				buffer.set_len(length as usize - 1);
 				let ptr = buffer.as_mut_ptr() as *mut GLchar;
 				gl::GetShaderInfoLog(self.0, length, ptr::null_mut(), ptr);
 			}
+			buffer.truncate(length as usize - 1);
 
 			// Convert message to a string
 			Some(String::from_utf8(buffer)
@@ -145,13 +234,18 @@ impl ShaderProgram {
 				gl::GetProgramiv(self.0, gl::INFO_LOG_LENGTH, &mut length);
 			}
 
-			// Get the message
-			let mut buffer = Vec::with_capacity(length as usize);
+			// Some drivers report an empty log even when linking fails
+			if length <= 1 {
+				return Some(String::from("(no error message)"));
+			}
+
+			// Get the message, stripping off the NULL terminator
+			let mut buffer = vec![0u8; length as usize];
 			unsafe {
-				buffer.set_len(length as usize - 1);
 				let ptr = buffer.as_mut_ptr() as *mut GLchar;
 				gl::GetProgramInfoLog(self.0, length, ptr::null_mut(), ptr);
 			}
+			buffer.truncate(length as usize - 1);
 
 			// Convert to string
 			Some(String::from_utf8(buffer)
@@ -178,6 +272,52 @@ impl ShaderProgram {
 		let c_str = CString::new(name).unwrap();
 		unsafe { gl::GetUniformLocation(self.0, c_str.as_ptr()) }
 	}
+
+	/// Attaches a uniform block to a binding point, so it reads from whichever
+	/// uniform buffer is bound there. Does nothing if the program doesn't use
+	/// the block.
+	pub fn bind_uniform_block(&self, name: &str, binding: GLuint) {
+		let c_str = CString::new(name).unwrap();
+		unsafe {
+			let index = gl::GetUniformBlockIndex(self.0, c_str.as_ptr());
+			if index != gl::INVALID_INDEX {
+				gl::UniformBlockBinding(self.0, index, binding);
+			}
+		}
+	}
+
+	// The uniform setters below apply to the currently bound program, so this
+	// program must be bound before calling them.
+
+	/// Sets an integer (or sampler) uniform.
+	pub fn set_uniform_i32(&self, loc: GLint, value: i32) {
+		unsafe { gl::Uniform1i(loc, value); }
+	}
+
+	/// Sets a float uniform.
+	pub fn set_uniform_f32(&self, loc: GLint, value: f32) {
+		unsafe { gl::Uniform1f(loc, value); }
+	}
+
+	/// Sets a vec2 uniform.
+	pub fn set_uniform_vec2(&self, loc: GLint, x: f32, y: f32) {
+		unsafe { gl::Uniform2f(loc, x, y); }
+	}
+
+	/// Sets a vec3 uniform.
+	pub fn set_uniform_vec3(&self, loc: GLint, value: Vector3<f32>) {
+		unsafe { gl::Uniform3f(loc, value.x, value.y, value.z); }
+	}
+
+	/// Sets a mat3 uniform from a column major matrix.
+	pub fn set_uniform_mat3(&self, loc: GLint, value: &Matrix3<f32>) {
+		unsafe { gl::UniformMatrix3fv(loc, 1, gl::FALSE, value.as_ptr()); }
+	}
+
+	/// Sets a mat4 uniform from a column major matrix.
+	pub fn set_uniform_mat4(&self, loc: GLint, value: &Matrix4<f32>) {
+		unsafe { gl::UniformMatrix4fv(loc, 1, gl::FALSE, value.as_ptr()); }
+	}
 }
 
 impl Drop for ShaderProgram {
@@ -186,19 +326,391 @@ impl Drop for ShaderProgram {
 	}
 }
 
-/// Set a vertex attribute.
-/// Assumes the appropriate VAO, VBO, and shader are bound.
-pub fn set_attr(location: GLuint, values: i32, kind: GLenum, start: i32,
-		size: i32) {
-	unsafe {
-		gl::EnableVertexAttribArray(location);
-		gl::VertexAttribPointer(
-			location,
-			values,
-			kind,
-			gl::FALSE,
-			size,
-			start as *const GLvoid,
-		);
+
+/// The shader source files, read from disk so they can be edited without
+/// rebuilding, or taken from copies embedded in the executable if the files
+/// aren't available.
+pub struct ShaderSources {
+	/// The directory to read shaders from, or None to only use the embedded
+	/// copies.
+	directory: Option<PathBuf>,
+	/// The embedded copy of every shader file, by file name.
+	embedded: &'static [(&'static str, &'static str)],
+}
+
+impl ShaderSources {
+	/// Creates a new set of shader sources. Debug builds read shaders from
+	/// the source tree when it exists, while release builds always use the
+	/// embedded copies.
+	pub fn new(embedded: &'static [(&'static str, &'static str)]) -> ShaderSources {
+		let directory = Path::new(SHADER_DIR);
+		let directory = if cfg!(debug_assertions) && directory.is_dir() {
+			println!("Loading shaders from {}", directory.display());
+			Some(directory.to_path_buf())
+		} else {
+			println!("Using embedded shaders");
+			None
+		};
+
+		ShaderSources {
+			directory: directory,
+			embedded: embedded,
+		}
+	}
+
+	/// Loads and compiles a shader, working out its type from its file name
+	/// and resolving its includes. It's read from disk if possible, falling
+	/// back to the embedded copy if the file can't be read.
+	pub fn load(&self, name: &str) -> Result<Shader, ShaderError> {
+		if let Some(ref directory) = self.directory {
+			match Shader::from_file(directory.join(name)) {
+				Err(ShaderError::Io { .. }) =>
+					println!("Couldn't read shader {} from disk, using embedded copy", name),
+				result => return result,
+			}
+		}
+
+		let (kind, source) = self.embedded_source(name)?;
+		Shader::try_with_includes(kind, name, &source, &Includes::Embedded(self.embedded))
+	}
+
+	/// Returns the type and embedded copy of a shader file.
+	fn embedded_source(&self, name: &str) -> Result<(ShaderType, String), ShaderError> {
+		let kind = ShaderType::from_path(name)
+			.ok_or_else(|| ShaderError::UnknownType(PathBuf::from(name)))?;
+		let source = Includes::Embedded(self.embedded).load(name)
+			.ok_or_else(|| ShaderError::Io {
+				path: PathBuf::from(name),
+				error: io::Error::new(io::ErrorKind::NotFound, "no embedded copy"),
+			})?;
+		Ok((kind, source))
+	}
+
+	/// Loads and compiles a shader with `load`.
+	///
+	/// Panics if the shader can't be loaded or compiled.
+	pub fn shader(&self, name: &str) -> Shader {
+		self.load(name).unwrap_or_else(|err| panic!("{}", err))
+	}
+
+	/// Loads, compiles, and links a program from a vertex and fragment shader.
+	pub fn program(&self, vert: &str, frag: &str) -> ShaderProgram {
+		let program = ShaderProgram::new();
+		program.attach(self.shader(vert));
+		program.attach(self.shader(frag));
+		program.link();
+		program
+	}
+}
+
+
+/// Where the files named by `#include` directives are loaded from.
+pub enum Includes<'a> {
+	/// Included files are read from a directory on disk.
+	Directory(&'a Path),
+	/// Included files are looked up by name in a table of embedded sources.
+	Embedded(&'a [(&'a str, &'a str)]),
+}
+
+impl<'a> Includes<'a> {
+	/// Returns the source of an included file, or None if it can't be found.
+	fn load(&self, name: &str) -> Option<String> {
+		match *self {
+			Includes::Directory(base) => {
+				let mut source = String::new();
+				File::open(base.join(name))
+					.and_then(|mut file| file.read_to_string(&mut source))
+					.ok()
+					.map(|_| source)
+			},
+			Includes::Embedded(table) => table.iter()
+				.find(|&&(file, _)| file == name)
+				.map(|&(_, source)| source.to_string()),
+		}
+	}
+}
+
+/// Shader source with all its includes resolved.
+pub struct Preprocessed {
+	/// The combined source code.
+	pub source: String,
+	/// The name of each file that makes up the source, indexed by the source
+	/// string number used in its `#line` directives.
+	files: Vec<String>,
+}
+
+impl Preprocessed {
+	/// Replaces the source string numbers at the start of each line in a
+	/// compiler log with the name of the file they refer to.
+	///
+	/// Drivers format their logs differently (e.g. `0:12(5): error`,
+	/// `0(12) : error`, `ERROR: 0:12: ...`), but they all start with the
+	/// source string number followed by the line.
+	pub fn annotate(&self, log: &str) -> String {
+		log.lines().map(|line| {
+			// Skip past any severity prefix
+			let prefix_len = ["ERROR: ", "WARNING: "].iter()
+				.find(|prefix| line.starts_with(*prefix))
+				.map_or(0, |prefix| prefix.len());
+			let (prefix, rest) = line.split_at(prefix_len);
+
+			// Replace the source string number with its file name
+			let digits = rest.chars().take_while(|c| c.is_digit(10)).count();
+			let file = rest[.. digits].parse::<usize>().ok()
+				.and_then(|index| self.files.get(index));
+			match (file, rest[digits ..].chars().next()) {
+				(Some(file), Some(':')) | (Some(file), Some('(')) =>
+					format!("{}{}{}", prefix, file, &rest[digits ..]),
+				_ => line.to_string(),
+			}
+		}).collect::<Vec<_>>().join("\n")
+	}
+}
+
+/// Reads a shader file, working out its type from the file name.
+fn read_file(path: &Path) -> Result<(ShaderType, String), ShaderError> {
+	let kind = ShaderType::from_path(path)
+		.ok_or_else(|| ShaderError::UnknownType(path.to_path_buf()))?;
+
+	let mut source = String::new();
+	File::open(path)
+		.and_then(|mut file| file.read_to_string(&mut source))
+		.map_err(|error| ShaderError::Io { path: path.to_path_buf(), error: error })?;
+	Ok((kind, source))
+}
+
+/// Resolves the `#include "file"` directives in a shader's source,
+/// recursively. `#line` directives are inserted around each included file so
+/// compile errors can be traced back to the right file and line.
+pub fn preprocess(name: &str, source: &str, includes: &Includes)
+		-> Result<Preprocessed, String> {
+	let mut output = Preprocessed {
+		source: String::new(),
+		files: Vec::new(),
+	};
+	let mut stack = Vec::new();
+	expand(name, source, includes, &mut stack, &mut output)?;
+	Ok(output)
+}
+
+/// Appends a file's source to the output, expanding its includes. The stack
+/// holds the names of the files currently being expanded, to detect cycles.
+fn expand(name: &str, source: &str, includes: &Includes,
+		stack: &mut Vec<String>, output: &mut Preprocessed)
+		-> Result<(), String> {
+	// Check the file doesn't (indirectly) include itself
+	if stack.iter().any(|file| file == name) {
+		return Err(format!("Include cycle: {} -> {}", stack.join(" -> "), name));
+	}
+	stack.push(name.to_string());
+	let index = output.files.len();
+	output.files.push(name.to_string());
+
+	for (number, line) in source.lines().enumerate() {
+		let trimmed = line.trim();
+		if !trimmed.starts_with("#include") {
+			output.source.push_str(line);
+			output.source.push('\n');
+			continue;
+		}
+
+		// Get the file name from between the quotes
+		let file = trimmed["#include".len() ..].trim();
+		if file.len() < 2 || !file.starts_with('"') || !file.ends_with('"') {
+			return Err(format!("{}:{}: malformed include: {}", name, number + 1,
+				trimmed));
+		}
+		let file = &file[1 .. file.len() - 1];
+		let included = match includes.load(file) {
+			Some(included) => included,
+			None => return Err(format!("{}:{}: can't find included file \"{}\"",
+				name, number + 1, file)),
+		};
+
+		// Expand the included file, then pick up where we left off
+		push_line_directive(&mut output.source, 1, output.files.len());
+		expand(file, &included, includes, stack, output)?;
+		push_line_directive(&mut output.source, number + 2, index);
+	}
+
+	stack.pop();
+	Ok(())
+}
+
+/// Appends a `#line` directive so that the next line is numbered `line`.
+/// Before GLSL 3.30, the line following the directive is numbered one more
+/// than the value given.
+fn push_line_directive(source: &mut String, line: usize, file: usize) {
+	source.push_str(&format!("#line {} {}\n", line - 1, file));
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{ShaderType, ShaderError, ShaderSources, Includes, SHADER_DIR,
+		preprocess, read_file};
+
+	use std::io;
+	use std::path::Path;
+
+	/// A set of files that include each other.
+	const FILES: [(&'static str, &'static str); 7] = [
+		("main.frag", "void main() {\n#include \"a.glsl\"\n}"),
+		("a.glsl", "float a;\n  #include \"b.glsl\"\nfloat a2;"),
+		("b.glsl", "float b;"),
+		("self.glsl", "#include \"self.glsl\""),
+		("ping.glsl", "#include \"pong.glsl\""),
+		("pong.glsl", "\n#include \"ping.glsl\""),
+		("broken.frag", "#include \"missing.glsl\""),
+	];
+
+	/// Works out which file and line each line of preprocessed source came
+	/// from, following its `#line` directives the way a pre-3.30 compiler
+	/// does.
+	fn origins(source: &str) -> Vec<(usize, usize, &str)> {
+		let (mut file, mut line) = (0, 1);
+		let mut lines = Vec::new();
+		for text in source.lines() {
+			if text.starts_with("#line ") {
+				let numbers: Vec<usize> = text.split(' ').skip(1)
+					.map(|number| number.parse().unwrap())
+					.collect();
+				line = numbers[0] + 1;
+				file = numbers[1];
+				continue;
+			}
+			lines.push((file, line, text));
+			line += 1;
+		}
+		lines
+	}
+
+	#[test]
+	fn type_from_file_name() {
+		assert_eq!(ShaderType::from_path("sky.vert"), Some(ShaderType::Vertex));
+		assert_eq!(ShaderType::from_path("shaders/sky.frag"), Some(ShaderType::Fragment));
+		assert_eq!(ShaderType::from_path("a.b.geom"), Some(ShaderType::Geometry));
+		assert_eq!(ShaderType::from_path("blur.comp"), Some(ShaderType::Compute));
+		assert_eq!(ShaderType::from_path("tonemap_frag.glsl"), Some(ShaderType::Fragment));
+		assert_eq!(ShaderType::from_path("post_vert.glsl"), Some(ShaderType::Vertex));
+		assert_eq!(ShaderType::from_path("sky.glsl"), None);
+		assert_eq!(ShaderType::from_path("sky.txt"), None);
+		assert_eq!(ShaderType::from_path("vert"), None);
+	}
+
+	#[test]
+	fn nested_includes_keep_their_line_numbers() {
+		let includes = Includes::Embedded(&FILES);
+		let preprocessed = preprocess("main.frag", FILES[0].1, &includes).unwrap();
+		assert_eq!(preprocessed.files, vec!["main.frag", "a.glsl", "b.glsl"]);
+
+		// Every line comes out in order, numbered as it is in its own file
+		let lines: Vec<_> = origins(&preprocessed.source).into_iter()
+			.map(|(file, line, text)| (preprocessed.files[file].as_str(), line, text))
+			.collect();
+		assert_eq!(lines, vec![
+			("main.frag", 1, "void main() {"),
+			("a.glsl", 1, "float a;"),
+			("b.glsl", 1, "float b;"),
+			("a.glsl", 3, "float a2;"),
+			("main.frag", 3, "}"),
+		]);
+	}
+
+	#[test]
+	fn missing_and_malformed_includes() {
+		let includes = Includes::Embedded(&FILES);
+		assert_eq!(preprocess("test.frag", "\n#include \"nope.glsl\"", &includes).err(),
+			Some("test.frag:2: can't find included file \"nope.glsl\"".to_string()));
+		assert_eq!(preprocess("test.frag", "#include b.glsl", &includes).err(),
+			Some("test.frag:1: malformed include: #include b.glsl".to_string()));
+	}
+
+	#[test]
+	fn include_cycles_are_errors() {
+		let includes = Includes::Embedded(&FILES);
+		assert_eq!(preprocess("self.glsl", FILES[3].1, &includes).err(),
+			Some("Include cycle: self.glsl -> self.glsl".to_string()));
+		assert_eq!(preprocess("ping.glsl", FILES[4].1, &includes).err(),
+			Some("Include cycle: ping.glsl -> pong.glsl -> ping.glsl".to_string()));
+	}
+
+	#[test]
+	fn compile_errors_name_their_file() {
+		let includes = Includes::Embedded(&FILES);
+		let preprocessed = preprocess("main.frag", FILES[0].1, &includes).unwrap();
+		let log = "0:3(1): error: syntax error\n\
+			ERROR: 2:1: 'b' : redefinition\n\
+			1(3) : warning C7050: unused\n\
+			7:1: error: out of range\n\
+			linker error";
+		assert_eq!(preprocessed.annotate(log), "main.frag:3(1): error: syntax error\n\
+			ERROR: b.glsl:1: 'b' : redefinition\n\
+			a.glsl(3) : warning C7050: unused\n\
+			7:1: error: out of range\n\
+			linker error");
+	}
+
+	#[test]
+	fn shader_files_are_read_with_their_type() {
+		let path = Path::new(SHADER_DIR).join("post_vert.glsl");
+		let (kind, source) = read_file(&path).unwrap();
+		assert_eq!(kind, ShaderType::Vertex);
+		assert!(source.starts_with("#version"));
+
+		match read_file(Path::new("shaders/sky.txt")) {
+			Err(ShaderError::UnknownType(ref path)) => assert_eq!(path, Path::new("shaders/sky.txt")),
+			other => panic!("unexpected result {:?}", other.map(|(kind, _)| kind)),
+		}
+		match read_file(&Path::new(SHADER_DIR).join("missing_frag.glsl")) {
+			Err(ShaderError::Io { ref error, .. }) =>
+				assert_eq!(error.kind(), io::ErrorKind::NotFound),
+			other => panic!("unexpected result {:?}", other.map(|(kind, _)| kind)),
+		}
+	}
+
+	#[test]
+	fn embedded_shaders_are_found_by_name() {
+		let sources = ShaderSources { directory: None, embedded: &FILES };
+		let (kind, source) = sources.embedded_source("main.frag").unwrap();
+		assert_eq!(kind, ShaderType::Fragment);
+		assert_eq!(source, FILES[0].1);
+
+		match sources.embedded_source("a.glsl") {
+			Err(ShaderError::UnknownType(ref path)) => assert_eq!(path, Path::new("a.glsl")),
+			other => panic!("unexpected result {:?}", other.map(|(kind, _)| kind)),
+		}
+		match sources.embedded_source("other.frag") {
+			Err(ShaderError::Io { ref error, .. }) =>
+				assert_eq!(error.kind(), io::ErrorKind::NotFound),
+			other => panic!("unexpected result {:?}", other.map(|(kind, _)| kind)),
+		}
+
+		// Includes are resolved before anything's handed to OpenGL
+		match sources.load("broken.frag") {
+			Err(ShaderError::Preprocess { ref name, ref message }) => {
+				assert_eq!(name, "broken.frag");
+				assert_eq!(message, "broken.frag:1: can't find included file \"missing.glsl\"");
+			},
+			Err(err) => panic!("unexpected error {}", err),
+			Ok(_) => panic!("broken.frag loaded"),
+		}
+	}
+
+	#[test]
+	fn errors_describe_the_problem() {
+		let missing = ShaderError::Io {
+			path: Path::new("sky.frag").to_path_buf(),
+			error: io::Error::new(io::ErrorKind::NotFound, "not found"),
+		};
+		assert_eq!(missing.to_string(), "Failed to read shader sky.frag: not found");
+		assert_eq!(ShaderError::UnknownType(Path::new("sky.txt").to_path_buf()).to_string(),
+			"Can't tell the type of shader sky.txt from its extension \
+			(expected .vert, .frag, .geom or .comp)");
+		let compile = ShaderError::Compile {
+			name: "sky.frag".to_string(),
+			message: "sky.frag:3(1): error".to_string(),
+		};
+		assert_eq!(compile.to_string(), "Failed to compile shader sky.frag:\nsky.frag:3(1): error");
 	}
 }
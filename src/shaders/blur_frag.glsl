@@ -0,0 +1,23 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform vec2 direction;
+uniform int radius;
+
+// One direction of a separable Gaussian blur, where `direction` is the size
+// of one texel along the axis being blurred
+void main(void) {
+	float sigma = max(float(radius) / 2.0, 1.0);
+	vec3 sum = vec3(0.0);
+	float total = 0.0;
+	for (int i = -radius; i <= radius; i++) {
+		float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+		sum += texture(source, uv + direction * float(i)).rgb * weight;
+		total += weight;
+	}
+	color = vec4(sum / total, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform float threshold;
+
+// Keeps only the radiance above the threshold, in linear units before tone
+// mapping, so mostly just the sun disk contributes to bloom
+void main(void) {
+	vec3 radiance = texture(source, uv).rgb;
+	color = vec4(max(radiance - vec3(threshold), vec3(0.0)), 1.0);
+}
@@ -0,0 +1,36 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+// The cube map face being rendered, numbered +X, -X, +Y, -Y, +Z, -Z
+uniform int face;
+
+#include "sky.glsl"
+
+// Returns the direction through a point on a cube map face, following the
+// face orientations in the OpenGL specification. `st` runs from -1 to 1 across
+// the face, with -1 at its first texel
+vec3 cube_direction(int face, vec2 st) {
+	if (face == 0) {
+		return vec3(1.0, -st.y, -st.x);
+	} else if (face == 1) {
+		return vec3(-1.0, -st.y, st.x);
+	} else if (face == 2) {
+		return vec3(st.x, 1.0, st.y);
+	} else if (face == 3) {
+		return vec3(st.x, -1.0, -st.y);
+	} else if (face == 4) {
+		return vec3(st.x, -st.y, 1.0);
+	} else {
+		return vec3(-st.x, -st.y, -1.0);
+	}
+}
+
+// Renders the sky's radiance into one face of a cube map. The sun disk and
+// stars are left out, since they're drawn at the screen's resolution
+void main(void) {
+	vec3 V = normalize(cube_direction(face, uv * 2.0 - 1.0));
+	color = vec4(sky_radiance(V), 1.0);
+}
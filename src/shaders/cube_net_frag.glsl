@@ -0,0 +1,53 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform samplerCube sky_cube;
+
+// Size of the viewport, in pixels, used to keep the faces square
+uniform vec2 viewport_size;
+
+// Radiance shown around the net, where there's no face
+const vec3 BACKGROUND = vec3(0.05);
+
+// The forward, right and up directions of each face in the net, as seen from
+// inside the cube. The middle row turns right from +X round to -Z, with +Y
+// above and -Y below +Z. Neighbouring faces share an edge, so any seam in the
+// baked cube map shows up as a discontinuity
+const vec3 FORWARD[6] = vec3[](
+	vec3(1, 0, 0), vec3(0, 0, 1), vec3(-1, 0, 0), vec3(0, 0, -1),
+	vec3(0, 1, 0), vec3(0, -1, 0));
+const vec3 RIGHT[6] = vec3[](
+	vec3(0, 0, 1), vec3(-1, 0, 0), vec3(0, 0, -1), vec3(1, 0, 0),
+	vec3(-1, 0, 0), vec3(-1, 0, 0));
+const vec3 UP[6] = vec3[](
+	vec3(0, 1, 0), vec3(0, 1, 0), vec3(0, 1, 0), vec3(0, 1, 0),
+	vec3(0, 0, -1), vec3(0, 0, 1));
+
+// Unwraps the baked cube map into a cross, 4 faces wide and 3 tall, centred
+// on the screen
+void main(void) {
+	float cell_size = min(viewport_size.x / 4.0, viewport_size.y / 3.0);
+	vec2 origin = (viewport_size - cell_size * vec2(4.0, 3.0)) * 0.5;
+	vec2 position = (uv * viewport_size - origin) / cell_size;
+	vec2 cell = floor(position);
+
+	int index = -1;
+	if (cell.y == 1.0 && cell.x >= 0.0 && cell.x < 4.0) {
+		index = int(cell.x);
+	} else if (cell.x == 1.0 && cell.y == 2.0) {
+		index = 4;
+	} else if (cell.x == 1.0 && cell.y == 0.0) {
+		index = 5;
+	}
+	if (index < 0) {
+		color = vec4(BACKGROUND, 1.0);
+		return;
+	}
+
+	vec2 st = (position - cell) * 2.0 - 1.0;
+	vec3 V = FORWARD[index] + RIGHT[index] * st.x + UP[index] * st.y;
+	color = vec4(texture(sky_cube, V).rgb, 1.0);
+}
@@ -0,0 +1,19 @@
+// Ordered dithering, applied to the final display colour
+
+uniform float dither_amount;
+
+// 4x4 Bayer matrix, used to offset each pixel by a fraction of one LSB
+const float bayer[16] = float[16](
+	 0.0,  8.0,  2.0, 10.0,
+	12.0,  4.0, 14.0,  6.0,
+	 3.0, 11.0,  1.0,  9.0,
+	15.0,  7.0, 13.0,  5.0
+);
+
+// Breaks up banding in smooth gradients. Must be applied to the final display
+// colour, since one LSB only corresponds to 1/255 in the output encoding
+vec3 dither(vec3 color) {
+	ivec2 pixel = ivec2(gl_FragCoord.xy) % 4;
+	float threshold = (bayer[pixel.y * 4 + pixel.x] + 0.5) / 16.0 - 0.5;
+	return color + vec3(threshold * dither_amount / 255.0);
+}
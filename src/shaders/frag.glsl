@@ -4,36 +4,94 @@ out vec4 color;
 
 in vec3 frag_pos;
 
-uniform vec3 params[10];
-uniform vec3 sun_direction;
-
-vec3 HosekWilkie(float cos_theta, float gamma, float cos_gamma) {
-	vec3 A = params[0];
-	vec3 B = params[1];
-	vec3 C = params[2];
-	vec3 D = params[3];
-	vec3 E = params[4];
-	vec3 F = params[5];
-	vec3 G = params[6];
-	vec3 H = params[7];
-	vec3 I = params[8];
-	vec3 Z = params[9];
-	vec3 chi = (1 + cos_gamma * cos_gamma) / pow(1 + H * H - 2 * cos_gamma * H, vec3(1.5));
-    return (1 + A * exp(B / (cos_theta + 0.01))) * (C + D * exp(E * gamma) + F * (cos_gamma * cos_gamma) + G * chi + I * sqrt(cos_theta));
+// Angular radius of the sun's disk, in radians
+uniform float sun_radius;
+
+// Extra colour the sun's disk is multiplied by, warming it near the horizon.
+// See `SkyModel::sun_tint`
+uniform vec3 sun_tint;
+
+// How much of the sun's disk is blocked, from 0 for none to 1 for all of it.
+// See `SkyModel::set_sun_occlusion`
+uniform float sun_occlusion;
+
+// Vertical field of view, in radians, and size of the viewport, in pixels,
+// used to work out how much of the sky a single pixel covers
+uniform float fov;
+uniform vec2 viewport_size;
+
+// 0 to render the sky through the camera, or 1 to unwrap the whole sky into
+// an equirectangular (latitude-longitude) projection filling the screen
+uniform int view_mode;
+
+// 1 for right-handed coordinates, or -1 for left-handed ones, where z is
+// flipped. See `Handedness` in math.rs
+uniform float z_sign;
+
+// 0 to render the sky normally, or the index of a single term of the model to
+// show on its own, as listed in `HosekWilkieTerm`
+uniform int debug_term;
+
+const float PI = 3.14159265;
+
+// Radiance of the sun's disk. Keep in sync with SUN_DISK_RADIANCE in sky.rs
+const float SUN_DISK_RADIANCE = 20.0;
+
+#include "sky.glsl"
+
+// Returns the direction shown at a point on the screen in the equirectangular
+// view. Azimuth runs from -pi to pi across the screen, with 0 in the middle,
+// and elevation from straight down to straight up. Directions are built the
+// same way as the sun direction in `recalc_sun`, so the sun lands in the right
+// place.
+vec3 equirectangular_direction(vec2 uv) {
+	float azimuth = (uv.x - 0.5) * 2.0 * PI;
+	float elevation = (uv.y - 0.5) * PI;
+	return vec3(cos(elevation) * sin(azimuth), sin(elevation),
+		z_sign * cos(elevation) * cos(azimuth));
 }
 
 void main(void) {
-	vec3 V = normalize(frag_pos);
-	float cos_theta = clamp(V.y, 0, 1);
-	float cos_gamma = dot(V, sun_direction);
-	float gamma = acos(cos_gamma);
+	vec3 V;
+	float pixel_angle;
+	if (view_mode == 1) {
+		V = equirectangular_direction(gl_FragCoord.xy / viewport_size);
+		pixel_angle = PI / viewport_size.y;
+	} else {
+		V = normalize(frag_pos);
+		pixel_angle = 2.0 * tan(fov / 2.0) / viewport_size.y;
+	}
+	if (debug_term > 0) {
+		// Terms can be negative, and vary a lot in size, so show their
+		// magnitude squashed into [0, 1)
+		float cos_theta = clamp(V.y, 0, 1);
+		float cos_gamma = clamp(dot(V, sun_direction), -1, 1);
+		vec3 T = abs(HosekWilkieTerm(debug_term, cos_theta, acos(cos_gamma), cos_gamma));
+		color = vec4(T / (1.0 + T), 1.0);
+		return;
+	}
 
-	vec3 Z = params[9];
-	vec3 R = Z * HosekWilkie(cos_theta, gamma, cos_gamma);
+	vec3 R = sky_radiance(V);
+
+	float cos_gamma = dot(V, sun_direction);
 	if (cos_gamma > 0) {
 		// Only positive values of dot product, so we don't end up creating two
-		// spots of light 180 degrees apart
-		R = R + pow(vec3(cos_gamma), vec3(256)) * 0.5;
+		// spots of light 180 degrees apart. The glow's blocked along with the
+		// disk
+		R = R + pow(vec3(cos_gamma), vec3(256)) * 0.5 * (1.0 - night_blend) *
+			(1.0 - sun_occlusion);
 	}
+
+	// Fade the edge of the sun's disk over about one pixel so it doesn't
+	// alias. The angle to the sun is found from the sine and cosine together,
+	// since acos alone loses too much precision this close to the sun
+	float gamma = atan(length(cross(V, sun_direction)), cos_gamma);
+	float disk = 1.0 - smoothstep(sun_radius - pixel_angle * 0.5,
+		sun_radius + pixel_angle * 0.5, gamma);
+	R = R + vec3(SUN_DISK_RADIANCE) * sun_tint * disk * (1.0 - night_blend) *
+		(1.0 - sun_occlusion);
+
+	// Stars only come out at night
+	R = R + night_stars(V) * night_blend;
 	color = vec4(R, 1.0);
 }
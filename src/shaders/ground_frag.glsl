@@ -0,0 +1,68 @@
+#version 150
+
+out vec4 color;
+
+in vec3 world_pos;
+in float view_depth;
+
+uniform vec3 albedo;
+uniform vec3 camera_position;
+uniform float fog_density;
+
+// The distance between grid lines, or 0 to hide the grid
+uniform float grid_spacing;
+
+// Light reaching the ground from the sky, besides direct sunlight
+uniform vec3 sky_light;
+
+// 1 / log2(1 + far) to write logarithmic depth, or 0 to keep the projection's
+uniform float log_depth_scale;
+
+#include "sky.glsl"
+
+// The width of a grid line, in pixels
+const float GRID_LINE_WIDTH = 1.0;
+
+// How much lighter than the ground the grid lines are. Kept faint so the grid
+// helps with orientation without hiding the ground's colour
+const float GRID_STRENGTH = 0.25;
+
+// Returns how much of this pixel is covered by a grid line. The distance to
+// the nearest line is measured in pixels using the screen-space derivatives,
+// so the lines stay the same width at any distance. Towards the horizon the
+// cells shrink to a few pixels, where the lines would alias into moire, so
+// they're faded out before that happens
+float grid_coverage(vec2 position) {
+	vec2 cell = position / grid_spacing;
+	vec2 cells_per_pixel = fwidth(cell);
+	vec2 pixels = abs(fract(cell - 0.5) - 0.5) / cells_per_pixel;
+	float line = min(pixels.x, pixels.y);
+	float coverage = 1.0 - clamp(line - 0.5 * GRID_LINE_WIDTH + 0.5, 0.0, 1.0);
+	float fade = 1.0 - smoothstep(0.1, 0.3, max(cells_per_pixel.x, cells_per_pixel.y));
+	return coverage * fade;
+}
+
+void main(void) {
+	// A flat diffuse surface, lit by the sun and the sky above it
+	vec3 light = sky_light + vec3(max(sun_direction.y, 0.0));
+	vec3 surface = albedo * light;
+	if (grid_spacing > 0.0) {
+		surface *= 1.0 + GRID_STRENGTH * grid_coverage(world_pos.xz);
+	}
+
+	// Aerial perspective: light scattered towards the camera along the view
+	// ray fades the ground into the sky behind it. The fog colour is the sky
+	// in the same direction, so the ground meets the sky seamlessly at the
+	// horizon
+	vec3 ray = world_pos - camera_position;
+	float ray_length = length(ray);
+	float fog = 1.0 - exp(-fog_density * ray_length);
+	vec3 R = mix(surface, sky_radiance(ray / ray_length), fog);
+	color = vec4(R, 1.0);
+
+	if (log_depth_scale > 0.0) {
+		gl_FragDepth = log2(1.0 + max(view_depth, 0.0)) * log_depth_scale;
+	} else {
+		gl_FragDepth = gl_FragCoord.z;
+	}
+}
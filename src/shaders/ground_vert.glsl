@@ -0,0 +1,21 @@
+#version 150
+
+in vec2 position;
+out vec3 world_pos;
+out float view_depth;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform vec3 offset;
+uniform float extent;
+
+void main(void) {
+	// Stretch the unit quad out along the horizontal plane
+	world_pos = offset + vec3(position.x, 0.0, position.y) * extent;
+	gl_Position = projection * view * vec4(world_pos, 1.0);
+
+	// The distance in front of the eye, for logarithmic depth. It has to be
+	// interpolated and converted per fragment, since the log isn't linear
+	// across the quad
+	view_depth = gl_Position.w;
+}
@@ -0,0 +1,49 @@
+// Hosek-Wilkie sky model, evaluated using the coefficients computed on the
+// CPU for the current sun position. Expects `coefficients` to be declared by
+// the includer, in the SkyData block
+
+// The smallest cosine of the view angle from the zenith the model is evaluated
+// at. Keep in sync with MIN_COS_THETA in sky.rs
+const float MIN_COS_THETA = 0.001;
+
+vec3 HosekWilkie(float cos_theta, float gamma, float cos_gamma) {
+	vec3 A = coefficients.A;
+	vec3 B = coefficients.B;
+	vec3 C = coefficients.C;
+	vec3 D = coefficients.D;
+	vec3 E = coefficients.E;
+	vec3 F = coefficients.F;
+	vec3 G = coefficients.G;
+	vec3 H = coefficients.H;
+	vec3 I = coefficients.I;
+
+	// The model isn't defined below the horizon, where the exponential blows
+	// up and the square root goes negative
+	cos_theta = max(cos_theta, MIN_COS_THETA);
+	vec3 chi = (1 + cos_gamma * cos_gamma) / pow(1 + H * H - 2 * cos_gamma * H, vec3(1.5));
+    return (1 + A * exp(B / (cos_theta + 0.01))) * (C + D * exp(E * gamma) + F * (cos_gamma * cos_gamma) + G * chi + I * sqrt(cos_theta));
+}
+
+// Returns a single term of the model, for checking it term by term against
+// the paper: 1 for the A/B gradient towards the horizon, 2 for C, 3 for the
+// D/E aureole around the sun, 4 for the F Rayleigh term, 5 for the G Mie term,
+// and 6 for the I zenith term
+vec3 HosekWilkieTerm(int term, float cos_theta, float gamma, float cos_gamma) {
+	cos_theta = max(cos_theta, MIN_COS_THETA);
+	vec3 H = coefficients.H;
+	if (term == 1) {
+		return coefficients.A * exp(coefficients.B / (cos_theta + 0.01));
+	} else if (term == 2) {
+		return coefficients.C;
+	} else if (term == 3) {
+		return coefficients.D * exp(coefficients.E * gamma);
+	} else if (term == 4) {
+		return coefficients.F * (cos_gamma * cos_gamma);
+	} else if (term == 5) {
+		vec3 chi = (1 + cos_gamma * cos_gamma) / pow(1 + H * H - 2 * cos_gamma * H, vec3(1.5));
+		return coefficients.G * chi;
+	} else if (term == 6) {
+		return coefficients.I * sqrt(cos_theta);
+	}
+	return vec3(0.0);
+}
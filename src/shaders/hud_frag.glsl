@@ -0,0 +1,13 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform sampler2D font;
+uniform vec3 text_color;
+
+// Text in a flat colour, blended over the screen using the font's alpha
+void main(void) {
+	color = vec4(text_color, texture(font, uv).a);
+}
@@ -0,0 +1,15 @@
+#version 150
+
+// Position on screen in pixels from the top left, followed by the texture
+// coordinates in the font atlas
+in vec4 vertex;
+out vec2 uv;
+
+uniform vec2 viewport_size;
+uniform vec2 offset;
+
+void main(void) {
+	vec2 position = (vertex.xy + offset) / viewport_size;
+	uv = vertex.zw;
+	gl_Position = vec4(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0, 0.0, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform float max_luminance;
+// The weights giving the luminance of a linear RGB colour, shared with the sky
+// model's normalisation
+uniform vec3 luminance_weights;
+
+// Writes the log luminance of the HDR image, so averaging it down the mip
+// chain gives the geometric mean, which small bright areas skew much less than
+// a plain mean. The luminance is clamped first so the sun's disk can't
+// dominate, and kept above zero so the log is defined
+void main(void) {
+	vec3 radiance = texture(source, uv).rgb;
+	float luminance = dot(radiance, luminance_weights);
+	color = vec4(log(clamp(luminance, 1e-4, max_luminance)), 0.0, 0.0, 1.0);
+}
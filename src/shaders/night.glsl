@@ -0,0 +1,58 @@
+// Night sky, faded in once the sun is far enough below the horizon that the
+// Hosek-Wilkie model no longer applies. Expects `night_blend` to be declared by
+// the includer, in the SkyData block
+
+// Rotates the star field into view space
+uniform mat3 star_rotation;
+
+uniform vec3 night_zenith;
+uniform vec3 night_horizon;
+
+// Number of grid cells across the unit sphere the stars are scattered in
+const float star_density = 300.0;
+
+// Fraction of grid cells that contain a star
+const float star_fraction = 0.03;
+
+// Radiance of the brightest stars
+const float star_brightness = 2.0;
+
+float hash(vec3 p) {
+	return fract(sin(dot(p, vec3(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+// Scatters stars randomly across the sphere, by giving some of the cells in a
+// grid a single star at a random position
+vec3 stars(vec3 direction) {
+	vec3 p = direction * star_density;
+	vec3 cell = floor(p);
+	if (hash(cell) > star_fraction) {
+		return vec3(0.0);
+	}
+
+	// Move the star onto the sphere, so the distance to it is measured along
+	// the sky rather than through it
+	vec3 offset = vec3(hash(cell + 1.0), hash(cell + 2.0), hash(cell + 3.0));
+	vec3 centre = normalize(cell + offset) * star_density;
+	float falloff = 1.0 - smoothstep(0.0, 0.3, length(p - centre));
+
+	// Vary the brightness and colour temperature of each star
+	vec3 tint = mix(vec3(1.0, 0.8, 0.6), vec3(0.7, 0.8, 1.0), hash(cell + 4.0));
+	return tint * star_brightness * hash(cell + 5.0) * falloff;
+}
+
+// The colour of the night sky, without any stars
+vec3 night_gradient(vec3 direction) {
+	float cos_theta = clamp(direction.y, 0.0, 1.0);
+	return mix(night_horizon, night_zenith, sqrt(cos_theta));
+}
+
+// The star field, as seen in a direction
+vec3 night_stars(vec3 direction) {
+	// Multiplying a row vector applies the inverse (transposed) rotation,
+	// taking the view direction into the star field's space. Fade the stars
+	// out towards the horizon, where they'd be lost in the haze
+	vec3 star_direction = direction * star_rotation;
+	float visibility = smoothstep(0.0, 0.1, direction.y);
+	return stars(star_direction) * visibility;
+}
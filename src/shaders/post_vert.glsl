@@ -0,0 +1,10 @@
+#version 150
+
+out vec2 uv;
+
+// Draws a single triangle covering the whole screen, without any vertex data
+void main(void) {
+	vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+	uv = position;
+	gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
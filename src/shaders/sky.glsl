@@ -0,0 +1,52 @@
+// Radiance of the sky in a direction, excluding the sun disc and stars. This
+// is the GLSL counterpart of `SkyModel::sample`, shared by everything that
+// needs to match the colour of the sky
+
+// The Hosek-Wilkie coefficients, matching `SkyCoefficients` in sky.rs. Each
+// vec3 starts on a 16 byte boundary under std140, the same as in an array
+struct SkyCoefficients {
+	vec3 A;
+	vec3 B;
+	vec3 C;
+	vec3 D;
+	vec3 E;
+	vec3 F;
+	vec3 G;
+	vec3 H;
+	vec3 I;
+	// The radiance scale the distribution is multiplied by
+	vec3 radiance;
+};
+
+// The sky model's values, shared between every program that includes this
+// file. Laid out to match `SkyData` in sky_data.rs
+layout(std140) uniform SkyData {
+	SkyCoefficients coefficients;
+	vec3 sun_direction;
+	// 0 during the day, up to 1 at night
+	float night_blend;
+	// How far below the horizon the gradient's extended, as the sine of the
+	// angle. See `SkyModel::set_atmosphere_thickness`
+	float horizon_drop;
+};
+
+#include "hosek_wilkie.glsl"
+#include "night.glsl"
+
+// Directions below the horizon are given the radiance at the horizon, with the
+// gradient above it mirrored through it down to `horizon_drop`, so it carries
+// on past the real one
+vec3 sky_radiance(vec3 V) {
+	float cos_theta = clamp(V.y, 0, 1);
+	float cos_gamma = clamp(dot(V, sun_direction), -1, 1);
+	float gamma = acos(cos_gamma);
+	vec3 model = HosekWilkie(cos_theta, gamma, cos_gamma);
+	if (V.y < 0) {
+		vec3 mirrored = HosekWilkie(min(-V.y, horizon_drop), gamma, cos_gamma);
+		model = max(2 * model - mirrored, vec3(0));
+	}
+	vec3 R = coefficients.radiance * model;
+
+	// The Hosek-Wilkie sky has faded to black by the time night falls
+	return mix(R, night_gradient(V), night_blend);
+}
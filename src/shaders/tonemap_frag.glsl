@@ -0,0 +1,44 @@
+#version 150
+
+out vec4 color;
+
+in vec2 uv;
+
+uniform sampler2D hdr_buffer;
+uniform sampler2D bloom[2];
+uniform float bloom_intensity;
+uniform float exposure;
+
+// 1 to encode the output for an sRGB display, or 0 to write the linear values
+// straight out, for comparison
+uniform int srgb_output;
+
+#include "dither.glsl"
+
+// Maps linear HDR radiance into the displayable [0, 1] range
+vec3 tonemap(vec3 radiance) {
+	return vec3(1.0) - exp(-radiance * exposure);
+}
+
+// The sRGB transfer function. Everything before this pass, including the HDR
+// framebuffer, works in linear values, and GL_FRAMEBUFFER_SRGB is left off,
+// so this is the only place the output's encoded and it's never done twice
+vec3 linear_to_srgb(vec3 linear) {
+	vec3 low = linear * 12.92;
+	vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+	return mix(low, high, step(vec3(0.0031308), linear));
+}
+
+void main(void) {
+	vec3 radiance = texture(hdr_buffer, uv).rgb;
+
+	// Add the blurred bright parts of the image from each bloom level
+	vec3 glow = texture(bloom[0], uv).rgb + texture(bloom[1], uv).rgb;
+	radiance += glow * bloom_intensity;
+
+	vec3 display = tonemap(radiance);
+	if (srgb_output == 1) {
+		display = linear_to_srgb(display);
+	}
+	color = vec4(dither(display), 1.0);
+}
@@ -6,7 +6,29 @@ out vec3 frag_pos;
 uniform mat4 projection;
 uniform mat4 orientation;
 
+// 0 to draw the sky on the cube, or 1 to draw it on a single triangle covering
+// the screen, without any vertex data
+uniform int fullscreen;
+
+// The inverse of `projection * orientation`, taking a point in clip space
+// back to the direction it's seen in
+uniform mat4 inverse_orientation;
+
 void main(void) {
+	if (fullscreen == 1) {
+		// Cover the screen at the far plane, and find the view ray through
+		// each corner. The ray's linear across the screen, so it can be
+		// interpolated and normalised per fragment
+		vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+		vec4 far = inverse_orientation * vec4(position, 1.0, 1.0);
+		frag_pos = far.xyz / far.w;
+		gl_Position = vec4(position, 1.0, 1.0);
+		return;
+	}
+
 	frag_pos = normalize(position);
-	gl_Position = projection * orientation * vec4(position, 1.0);
+
+	// Push the sky out to the far plane, so anything else drawn in the scene
+	// is always in front of it
+	gl_Position = (projection * orientation * vec4(position, 1.0)).xyww;
 }
@@ -0,0 +1,1510 @@
+
+//
+//  Sky Model
+//
+
+use hosek::{DATASETS_RGB, DATASETS_RGB_RAD};
+use math::{clamp, lerp, saturate, smoothstep, Handedness};
+
+use cgmath::{Vector2, Vector3, Matrix3, ElementWise, InnerSpace, Quaternion, Rotation3, Rad, Rotation};
+use std::borrow::Cow;
+use std::error::Error;
+use std::{f32, fmt};
+
+
+/// The lowest turbidity the datasets cover.
+pub const MIN_TURBIDITY: f32 = 1.0;
+
+/// The highest turbidity the datasets cover.
+pub const MAX_TURBIDITY: f32 = 10.0;
+
+/// The sine of the sun elevation below which the sky is fully dark. The sky
+/// model isn't defined below the horizon, so it's faded out over this range.
+const TWILIGHT_SIN: f32 = 0.1;
+
+/// The sine of the sun elevation at which the night sky starts to fade in.
+const NIGHT_START_SIN: f32 = 0.05;
+
+/// The luminance of the sky looking towards the sun, once normalised, with the
+/// sun on the horizon and directly overhead. The brightness rises linearly with
+/// the sine of the sun's elevation between the two, so the sky's still a
+/// little brighter at midday than at sunrise.
+const HORIZON_BRIGHTNESS: f32 = 0.6;
+const ZENITH_BRIGHTNESS: f32 = 0.9;
+
+/// The maximum luminous efficacy, in lumens per watt. The RGB datasets give
+/// spectral radiance integrated against each channel's response, so scaling
+/// by this turns their luminance into candelas per square metre.
+pub const LUMINOUS_EFFICACY: f32 = 683.0;
+
+/// The coefficients used once the sun's fully set. A radiance scale of zero
+/// makes the model black in every direction, leaving only the night sky.
+const NIGHT_COEFFICIENTS: SkyCoefficients = SkyCoefficients {
+	a: ZERO, b: ZERO, c: ZERO, d: ZERO, e: ZERO, f: ZERO, g: ZERO, h: ZERO, i: ZERO,
+	radiance: ZERO,
+};
+
+/// A vector of zeros, for building constants.
+const ZERO: Vector3<f32> = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+
+/// The radiance of the night sky directly overhead.
+pub const NIGHT_ZENITH: [f32; 3] = [0.002, 0.004, 0.012];
+
+/// The radiance of the night sky at the horizon.
+pub const NIGHT_HORIZON: [f32; 3] = [0.008, 0.012, 0.025];
+
+/// The angular radius of the sun as seen from the Earth, in radians.
+pub const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+
+/// The weights giving the luminance of a linear Rec. 709 RGB colour, used to
+/// normalise the sky's brightness, and by the viewer to measure the image's
+/// brightness for its automatic exposure.
+pub const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// The smallest luminance towards the sun the radiance scale is normalised
+/// against. Dividing by anything smaller blows the coefficients up into
+/// infinities and NaNs.
+const MIN_SUN_LUMINANCE: f32 = 1e-6;
+
+/// The smallest cosine of the view angle from the zenith the model is
+/// evaluated at, matching the shader. The model isn't defined below the
+/// horizon, where the exponential term blows up.
+const MIN_COS_THETA: f32 = 0.001;
+
+/// The radiance of the sun's disk before it passes through the atmosphere.
+/// Matches `SUN_DISK_RADIANCE` in the fragment shader.
+pub const SUN_DISK_RADIANCE: f32 = 20.0;
+
+/// The colour sunlight is multiplied by with the sun on the horizon, at the
+/// greatest warmth. An artistic tint layered over the physical reddening.
+const WARM_SUN_TINT: [f32; 3] = [1.0, 0.7, 0.4];
+
+/// The sine of the sun elevation above which the warmth tint has faded out,
+/// so the sun is left untouched for most of the day.
+const SUN_TINT_FADE_SIN: f32 = 0.5;
+
+/// The greatest warmth the sun can be given.
+pub const MAX_SUN_WARMTH: f32 = 1.0;
+
+/// The furthest below the horizon the sky can be extended, in radians.
+pub const MAX_ATMOSPHERE_THICKNESS: f32 = 10.0 * f32::consts::PI / 180.0;
+
+/// The wavelengths of red, green, and blue light, in micrometres.
+const WAVELENGTHS: [f32; 3] = [0.68, 0.55, 0.44];
+
+/// The optical depth of the atmosphere straight up due to Rayleigh scattering,
+/// at each of the wavelengths.
+const RAYLEIGH_DEPTH: [f32; 3] = [0.041, 0.097, 0.236];
+
+/// How strongly aerosol scattering depends on wavelength (the Angstrom
+/// exponent).
+const AEROSOL_EXPONENT: f32 = 1.3;
+
+/// The number of rings and segments the sky dome is divided into when
+/// averaging its radiance.
+const AVERAGE_RINGS: usize = 8;
+const AVERAGE_SEGMENTS: usize = 16;
+
+/// How far the sun has to move, in radians, before the coefficients are
+/// recomputed.
+const SUN_EPSILON: f32 = 1e-5;
+
+/// The layout of the datasets holding the 9 configuration coefficients.
+const CONFIG_LAYOUT: DatasetLayout = DatasetLayout {
+	stride: 9,
+	control_points: 6,
+	turbidities: 10,
+	albedos: 2,
+};
+
+/// The layout of the datasets holding the overall radiance.
+const RADIANCE_LAYOUT: DatasetLayout = DatasetLayout {
+	stride: 1,
+	..CONFIG_LAYOUT
+};
+
+/// The index in the configuration datasets of each coefficient.
+///
+/// The datasets store the coefficients in the order the reference
+/// implementation evaluates them in, which swaps the last two round from the
+/// paper: index 7 is the zenith term I, and index 8 is the Mie scattering
+/// anisotropy H.
+const CONFIG_A: usize = 0;
+const CONFIG_B: usize = 1;
+const CONFIG_C: usize = 2;
+const CONFIG_D: usize = 3;
+const CONFIG_E: usize = 4;
+const CONFIG_F: usize = 5;
+const CONFIG_G: usize = 6;
+const CONFIG_I: usize = 7;
+const CONFIG_H: usize = 8;
+
+
+/// Describes how the spline control points are laid out in a dataset.
+///
+/// For each albedo, for each turbidity, there's a quintic spline over the
+/// sun's elevation. Each control point holds `stride` interleaved values, one
+/// for each coefficient stored in the dataset.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct DatasetLayout {
+	/// The number of values stored at each control point.
+	stride: usize,
+	/// The number of control points in each spline.
+	control_points: usize,
+	/// The number of turbidity levels, starting at a turbidity of 1.
+	turbidities: usize,
+	/// The number of albedo levels, spread evenly from 0 to 1.
+	albedos: usize,
+}
+
+impl DatasetLayout {
+	/// Describes the layout for error messages, breaking the total number of
+	/// values down into its factors.
+	fn describe(&self) -> String {
+		format!("{} control points x {} turbidities x {} albedos x {} values",
+			self.control_points, self.turbidities, self.albedos, self.stride)
+	}
+
+	/// Returns the number of values in a spline.
+	fn spline_len(&self) -> usize {
+		self.stride * self.control_points
+	}
+
+	/// Returns the offset of the spline for an albedo level and turbidity
+	/// level, both counting from 0.
+	fn offset(&self, albedo: usize, turbidity: usize) -> usize {
+		self.spline_len() * (self.turbidities * albedo + turbidity)
+	}
+
+	/// Returns the total number of values in a dataset.
+	fn len(&self) -> usize {
+		self.spline_len() * self.turbidities * self.albedos
+	}
+}
+
+
+/// The tables of spline control points the coefficients are evaluated from,
+/// with a configuration dataset (holding A to I) and a radiance dataset for
+/// each colour channel.
+///
+/// Defaults to the RGB datasets from the reference implementation, which are
+/// built in. `Datasets::load` reads others from a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Datasets {
+	/// The configuration dataset for each colour channel.
+	config: [Cow<'static, [f32]>; 3],
+	/// The radiance dataset for each colour channel.
+	radiance: [Cow<'static, [f32]>; 3],
+}
+
+impl Datasets {
+	/// Returns the built-in datasets, without copying them.
+	pub fn embedded() -> Datasets {
+		Datasets {
+			config: [Cow::Borrowed(&DATASETS_RGB[0][..]), Cow::Borrowed(&DATASETS_RGB[1][..]),
+				Cow::Borrowed(&DATASETS_RGB[2][..])],
+			radiance: [Cow::Borrowed(&DATASETS_RGB_RAD[0][..]),
+				Cow::Borrowed(&DATASETS_RGB_RAD[1][..]), Cow::Borrowed(&DATASETS_RGB_RAD[2][..])],
+		}
+	}
+
+	/// Creates datasets from the values for each colour channel, laid out the
+	/// same way as the built-in ones: for each albedo level, for each
+	/// turbidity level, for each control point, the values stored at that
+	/// point. Configuration datasets hold the 9 coefficients at each point (in
+	/// the order A to G, then I, then H), and radiance datasets hold one.
+	///
+	/// Fails if any dataset has the wrong number of values, or holds a value
+	/// that's infinite or NaN.
+	pub fn new(config: [Vec<f32>; 3], radiance: [Vec<f32>; 3])
+			-> Result<Datasets, DatasetError> {
+		let check = |kind: DatasetKind, datasets: &[Vec<f32>; 3]| {
+			for (channel, dataset) in datasets.iter().enumerate() {
+				if dataset.len() != kind.layout().len() {
+					return Err(DatasetError::Length {
+						kind: kind,
+						channel: channel,
+						len: dataset.len(),
+					});
+				}
+				if let Some(index) = dataset.iter().position(|value| !value.is_finite()) {
+					return Err(DatasetError::NotFinite {
+						kind: kind,
+						channel: channel,
+						index: index,
+					});
+				}
+			}
+			Ok(())
+		};
+		check(DatasetKind::Config, &config)?;
+		check(DatasetKind::Radiance, &radiance)?;
+
+		let [config0, config1, config2] = config;
+		let [radiance0, radiance1, radiance2] = radiance;
+		Ok(Datasets {
+			config: [Cow::Owned(config0), Cow::Owned(config1), Cow::Owned(config2)],
+			radiance: [Cow::Owned(radiance0), Cow::Owned(radiance1), Cow::Owned(radiance2)],
+		})
+	}
+
+	/// Returns the number of values in each configuration dataset.
+	pub fn config_len() -> usize {
+		CONFIG_LAYOUT.len()
+	}
+
+	/// Returns the number of values in each radiance dataset.
+	pub fn radiance_len() -> usize {
+		RADIANCE_LAYOUT.len()
+	}
+
+	/// Returns the configuration dataset for a colour channel.
+	pub fn config(&self, channel: usize) -> &[f32] {
+		&self.config[channel]
+	}
+
+	/// Returns the radiance dataset for a colour channel.
+	pub fn radiance(&self, channel: usize) -> &[f32] {
+		&self.radiance[channel]
+	}
+}
+
+impl Default for Datasets {
+	fn default() -> Datasets {
+		Datasets::embedded()
+	}
+}
+
+
+/// The two kinds of dataset the coefficients are evaluated from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DatasetKind {
+	/// Holds the 9 configuration coefficients, A to I.
+	Config,
+	/// Holds the overall radiance.
+	Radiance,
+}
+
+impl DatasetKind {
+	/// Returns how the values are laid out in this kind of dataset.
+	fn layout(&self) -> DatasetLayout {
+		match *self {
+			DatasetKind::Config => CONFIG_LAYOUT,
+			DatasetKind::Radiance => RADIANCE_LAYOUT,
+		}
+	}
+}
+
+impl fmt::Display for DatasetKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DatasetKind::Config => write!(f, "configuration"),
+			DatasetKind::Radiance => write!(f, "radiance"),
+		}
+	}
+}
+
+
+/// A problem with the values given for a dataset.
+#[derive(Debug, PartialEq)]
+pub enum DatasetError {
+	/// A dataset has the wrong number of values for its layout.
+	Length { kind: DatasetKind, channel: usize, len: usize },
+	/// A dataset holds a value that's infinite or NaN.
+	NotFinite { kind: DatasetKind, channel: usize, index: usize },
+}
+
+impl fmt::Display for DatasetError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DatasetError::Length { kind, channel, len } => {
+				let layout = kind.layout();
+				write!(f, "{} dataset {} has {} values, expected {} ({})", kind, channel,
+					len, layout.len(), layout.describe())
+			},
+			DatasetError::NotFinite { kind, channel, index } =>
+				write!(f, "{} dataset {} has a value that isn't finite at index {}", kind,
+					channel, index),
+		}
+	}
+}
+
+// So it can be carried inside an `io::Error` when a dataset file's loaded
+impl Error for DatasetError {}
+
+
+/// The units the sky's radiance is given in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RadianceUnits {
+	/// Scaled for display, so the sky looking towards the sun has a luminance
+	/// between `HORIZON_BRIGHTNESS` and `ZENITH_BRIGHTNESS` whatever the
+	/// turbidity and sun elevation. Suits a fixed exposure.
+	Normalized,
+	/// The model's own absolute radiance, scaled so the luminance of each
+	/// colour (with the Rec. 709 weights) is in cd/m^2. A clear midday zenith
+	/// is a few thousand cd/m^2, so this needs automatic exposure. Only the
+	/// sky dome is calibrated: the sun's disk and the night sky stay in
+	/// display units.
+	Absolute,
+}
+
+
+/// Light cast onto the scene by the sky, for lighting objects so they match
+/// it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lighting {
+	/// The colour of direct sunlight, in the direction of the sun.
+	pub sun: Vector3<f32>,
+	/// The colour of the ambient light from the rest of the sky.
+	pub ambient: Vector3<f32>,
+}
+
+
+/// The Hosek-Wilkie coefficients for a single sun position, for each colour
+/// channel, named as in the paper. The radiance in a direction is
+/// `radiance * F(theta, gamma)`, where F is the model's distribution built
+/// from A to I.
+///
+/// Laid out as consecutive vectors, in the order the shader's `SkyData` block
+/// expects them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct SkyCoefficients {
+	/// Darkening or brightening towards the horizon.
+	pub a: Vector3<f32>,
+	/// How quickly the horizon gradient falls off.
+	pub b: Vector3<f32>,
+	/// The constant term.
+	pub c: Vector3<f32>,
+	/// The strength of the aureole around the sun.
+	pub d: Vector3<f32>,
+	/// How quickly the aureole falls off with the angle from the sun.
+	pub e: Vector3<f32>,
+	/// Rayleigh scattering.
+	pub f: Vector3<f32>,
+	/// Mie scattering.
+	pub g: Vector3<f32>,
+	/// The anisotropy of the Mie scattering.
+	pub h: Vector3<f32>,
+	/// The zenith gradient.
+	pub i: Vector3<f32>,
+	/// The radiance scale the distribution is multiplied by. Normalised for
+	/// display, or in absolute units, depending on the model's settings.
+	pub radiance: Vector3<f32>,
+}
+
+impl SkyCoefficients {
+	/// The number of vectors the coefficients take up.
+	pub const COUNT: usize = 10;
+
+	/// Builds the coefficients from `COUNT` vectors, in the same order as
+	/// `as_uniform_slice` gives them.
+	///
+	/// Panics if there's the wrong number of vectors.
+	pub fn from_slice(values: &[Vector3<f32>]) -> SkyCoefficients {
+		assert_eq!(values.len(), SkyCoefficients::COUNT,
+			"wrong number of sky coefficients");
+		SkyCoefficients {
+			a: values[0],
+			b: values[1],
+			c: values[2],
+			d: values[3],
+			e: values[4],
+			f: values[5],
+			g: values[6],
+			h: values[7],
+			i: values[8],
+			radiance: values[9],
+		}
+	}
+
+	/// Returns the coefficients as an array of vectors, A to I followed by the
+	/// radiance scale, for uploading to the shader or saving.
+	pub fn as_uniform_slice(&self) -> [Vector3<f32>; SkyCoefficients::COUNT] {
+		[self.a, self.b, self.c, self.d, self.e, self.f, self.g, self.h, self.i,
+			self.radiance]
+	}
+
+	/// Interpolates linearly towards another set of coefficients, where `t`
+	/// is 0 for these and 1 for `other`.
+	pub fn lerp(&self, other: &SkyCoefficients, t: f32) -> SkyCoefficients {
+		SkyCoefficients {
+			a: lerp(self.a, other.a, t),
+			b: lerp(self.b, other.b, t),
+			c: lerp(self.c, other.c, t),
+			d: lerp(self.d, other.d, t),
+			e: lerp(self.e, other.e, t),
+			f: lerp(self.f, other.f, t),
+			g: lerp(self.g, other.g, t),
+			h: lerp(self.h, other.h, t),
+			i: lerp(self.i, other.i, t),
+			radiance: lerp(self.radiance, other.radiance, t),
+		}
+	}
+}
+
+
+/// The values the shader renders the sky from, as a snapshot of a sky model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkyState {
+	/// The direction towards the sun.
+	pub sun_direction: Vector3<f32>,
+	/// The Hosek-Wilkie coefficients.
+	pub coefficients: SkyCoefficients,
+}
+
+impl SkyState {
+	/// Blends towards another sky state, where `t` is 0 for this one and 1 for
+	/// `other`, for tweening between sky settings without recomputing the
+	/// coefficients every frame.
+	///
+	/// The coefficients are interpolated linearly. The model isn't linear in
+	/// its inputs, so part way through this only approximates the sky for
+	/// interpolated turbidity and albedo. For the exact sky, interpolate those
+	/// and recompute the coefficients each frame instead.
+	pub fn lerp(&self, other: &SkyState, t: f32) -> SkyState {
+		if t <= 0.0 {
+			return *self;
+		} else if t >= 1.0 {
+			return *other;
+		}
+
+		// Fall back to the target if the sun has moved to the opposite side of
+		// the sky, where there's no direction half way
+		let direction = lerp(self.sun_direction, other.sun_direction, t);
+		let sun_direction = if direction.magnitude2() > 1e-8 {
+			direction.normalize()
+		} else {
+			other.sun_direction
+		};
+		SkyState {
+			sun_direction: sun_direction,
+			coefficients: self.coefficients.lerp(&other.coefficients, t),
+		}
+	}
+
+	/// Returns how far the sky has faded into night, from 0 while the sun is
+	/// up to 1 once it's far enough below the horizon that only the night sky
+	/// is visible.
+	pub fn night_blend(&self) -> f32 {
+		smoothstep(NIGHT_START_SIN, -TWILIGHT_SIN, self.sun_direction.y)
+	}
+}
+
+
+/// The CPU side of the Hosek-Wilkie sky model, which computes the
+/// coefficients the shader needs to render the sky.
+///
+/// The coefficients are cached, and only recomputed when the inputs change.
+#[derive(Clone)]
+pub struct SkyModel {
+	/// The atmospheric turbidity, between 1 and 10.
+	turbidity: f32,
+	/// The ground albedo for each colour channel.
+	albedo: [f32; 3],
+	/// The sun position the coefficients were last computed for, as an
+	/// (elevation, azimuth) pair.
+	sun: Vector2<f32>,
+
+	/// The angular radius of the sun's disk, in radians.
+	sun_radius: f32,
+	/// How much extra warmth is added to the sun near the horizon, from 0 for
+	/// none up to `MAX_SUN_WARMTH`.
+	sun_warmth: f32,
+	/// How far below the horizon the sky's gradient is extended, in radians.
+	atmosphere_thickness: f32,
+	/// How much of the sun's disk is blocked, from 0 for none to 1 for all of
+	/// it.
+	sun_occlusion: f32,
+	/// True if the sky dims along with the sun as it's occluded.
+	occlusion_dims_sky: bool,
+	/// The units the radiance is given in.
+	units: RadianceUnits,
+	/// The coordinate convention the sun direction is given in.
+	handedness: Handedness,
+	/// The tables the coefficients are evaluated from.
+	datasets: Datasets,
+	/// True if the turbidity's snapped to the nearest level in the datasets,
+	/// rather than interpolated between the two either side. For debugging.
+	nearest_turbidity: bool,
+
+	/// The direction towards the sun.
+	sun_direction: Vector3<f32>,
+	/// The Hosek-Wilkie coefficients.
+	coefficients: SkyCoefficients,
+
+	/// The number of times the coefficients have been computed, used to check
+	/// they aren't recomputed when nothing's changed.
+	recompute_count: u32,
+}
+
+impl SkyModel {
+	/// Creates a new sky model, computing the coefficients for the given sun
+	/// position.
+	pub fn new(turbidity: f32, albedo: [f32; 3], sun: Vector2<f32>) -> SkyModel {
+		let mut model = SkyModel {
+			turbidity: turbidity,
+			albedo: albedo,
+			sun: sun,
+			sun_radius: SUN_ANGULAR_RADIUS,
+			sun_warmth: 0.0,
+			atmosphere_thickness: 0.0,
+			sun_occlusion: 0.0,
+			occlusion_dims_sky: false,
+			units: RadianceUnits::Normalized,
+			handedness: Handedness::Right,
+			datasets: Datasets::embedded(),
+			nearest_turbidity: false,
+			sun_direction: Vector3::new(0.0, 0.0, 0.0),
+			coefficients: NIGHT_COEFFICIENTS,
+			recompute_count: 0,
+		};
+		model.recompute();
+		model
+	}
+
+	/// Moves the sun, recomputing the coefficients only if it's moved by
+	/// more than a tiny amount. Returns true if they were recomputed.
+	pub fn set_sun(&mut self, sun: Vector2<f32>) -> bool {
+		let moved = (sun.x - self.sun.x).abs() > SUN_EPSILON ||
+			(sun.y - self.sun.y).abs() > SUN_EPSILON;
+		if moved {
+			self.sun = sun;
+			self.recompute();
+		}
+		moved
+	}
+
+	/// Sets the atmospheric turbidity, clamped to the range the datasets
+	/// cover. Recomputes the coefficients only if it's changed, and returns
+	/// true if they were recomputed.
+	pub fn set_turbidity(&mut self, turbidity: f32) -> bool {
+		let turbidity = clamp(turbidity, MIN_TURBIDITY, MAX_TURBIDITY);
+		let changed = turbidity != self.turbidity;
+		if changed {
+			self.turbidity = turbidity;
+			self.recompute();
+		}
+		changed
+	}
+
+	/// Returns the atmospheric turbidity.
+	pub fn turbidity(&self) -> f32 {
+		self.turbidity
+	}
+
+	/// Sets the ground albedo for each colour channel. Recomputes the
+	/// coefficients only if it's changed, and returns true if they were
+	/// recomputed.
+	pub fn set_albedo(&mut self, albedo: [f32; 3]) -> bool {
+		let changed = albedo != self.albedo;
+		if changed {
+			self.albedo = albedo;
+			self.recompute();
+		}
+		changed
+	}
+
+	/// Returns the ground albedo for each colour channel.
+	pub fn albedo(&self) -> [f32; 3] {
+		self.albedo
+	}
+
+	/// Sets the units the radiance is given in. Recomputes the coefficients
+	/// only if they've changed, and returns true if they were recomputed.
+	pub fn set_units(&mut self, units: RadianceUnits) -> bool {
+		let changed = units != self.units;
+		if changed {
+			self.units = units;
+			self.recompute();
+		}
+		changed
+	}
+
+	/// Returns the units the radiance is given in.
+	pub fn units(&self) -> RadianceUnits {
+		self.units
+	}
+
+	/// Sets the coordinate convention the sun direction is given in, and the
+	/// directions passed to `sample` are taken to be in. Returns true if it
+	/// changed, moving the sun direction.
+	pub fn set_handedness(&mut self, handedness: Handedness) -> bool {
+		let changed = handedness != self.handedness;
+		if changed {
+			self.handedness = handedness;
+			self.recompute();
+		}
+		changed
+	}
+
+	/// Returns the coordinate convention the sun direction is given in.
+	pub fn handedness(&self) -> Handedness {
+		self.handedness
+	}
+
+	/// Sets the tables the coefficients are evaluated from, and recomputes
+	/// them.
+	pub fn set_datasets(&mut self, datasets: Datasets) {
+		self.datasets = datasets;
+		self.recompute();
+	}
+
+	/// Returns the tables the coefficients are evaluated from.
+	pub fn datasets(&self) -> &Datasets {
+		&self.datasets
+	}
+
+	/// Sets whether the turbidity's snapped to the nearest whole level in the
+	/// datasets, rather than interpolated between the levels either side.
+	/// Returns true if it changed, recomputing the coefficients.
+	///
+	/// This is only for debugging: it shows the raw bands in the datasets, so
+	/// artifacts can be pinned on the interpolation or ruled out. The sky
+	/// jumps as the turbidity crosses each half-way point.
+	pub fn set_nearest_turbidity(&mut self, nearest: bool) -> bool {
+		let changed = nearest != self.nearest_turbidity;
+		if changed {
+			self.nearest_turbidity = nearest;
+			self.recompute();
+		}
+		changed
+	}
+
+	/// Returns true if the turbidity's snapped to the nearest level in the
+	/// datasets.
+	pub fn nearest_turbidity(&self) -> bool {
+		self.nearest_turbidity
+	}
+
+	/// Returns the direction towards the sun.
+	pub fn sun_direction(&self) -> Vector3<f32> {
+		self.sun_direction
+	}
+
+	/// Returns the angular radius of the sun's disk, in radians.
+	pub fn sun_radius(&self) -> f32 {
+		self.sun_radius
+	}
+
+	/// Sets the angular radius of the sun's disk, in radians. Only affects how
+	/// the disk is drawn, so the coefficients aren't recomputed.
+	pub fn set_sun_radius(&mut self, radius: f32) {
+		self.sun_radius = radius;
+	}
+
+	/// Returns how much extra warmth is added to the sun near the horizon.
+	pub fn sun_warmth(&self) -> f32 {
+		self.sun_warmth
+	}
+
+	/// Sets how much extra warmth is added to the sun near the horizon,
+	/// clamped between 0 and `MAX_SUN_WARMTH`. Only affects the sun's colour,
+	/// so the coefficients aren't recomputed.
+	pub fn set_sun_warmth(&mut self, warmth: f32) {
+		self.sun_warmth = clamp(warmth, 0.0, MAX_SUN_WARMTH);
+	}
+
+	/// Returns how far below the horizon the sky's gradient is extended, in
+	/// radians.
+	pub fn atmosphere_thickness(&self) -> f32 {
+		self.atmosphere_thickness
+	}
+
+	/// Sets how far below the horizon the sky's gradient is extended, in
+	/// radians, clamped between 0 and `MAX_ATMOSPHERE_THICKNESS`.
+	///
+	/// The model stops at the horizon, leaving everything below it the same
+	/// flat colour, which shows as a hard line when there's no ground to hide
+	/// it. Extending it carries on the gradient just above the horizon, mirrored
+	/// through the horizon's colour, down to this angle below it, and holds the
+	/// colour reached there further down. The sky above the horizon is left as
+	/// it is. This isn't physical, so it's off by default. It doesn't change the
+	/// coefficients, so they aren't recomputed.
+	pub fn set_atmosphere_thickness(&mut self, thickness: f32) {
+		self.atmosphere_thickness = clamp(thickness, 0.0, MAX_ATMOSPHERE_THICKNESS);
+	}
+
+	/// Returns how far below the horizon the gradient's extended, as the height
+	/// of a view direction at that angle. Matches `horizon_drop` in
+	/// `sky.glsl`.
+	pub fn horizon_drop(&self) -> f32 {
+		self.atmosphere_thickness.sin()
+	}
+
+	/// Returns how much of the sun's disk is blocked, from 0 for none to 1 for
+	/// all of it.
+	pub fn sun_occlusion(&self) -> f32 {
+		self.sun_occlusion
+	}
+
+	/// Sets how much of the sun's disk is blocked, clamped between 0 for none
+	/// and 1 for all of it, to stand in for thin cloud or a partial eclipse.
+	/// The disk and the sunlight it gives are dimmed to match; with no
+	/// occlusion they're exactly as bright as ever. Doesn't change the
+	/// coefficients, so they aren't recomputed.
+	pub fn set_sun_occlusion(&mut self, occlusion: f32) {
+		self.sun_occlusion = saturate(occlusion);
+	}
+
+	/// Returns true if the sky dims along with the sun as it's occluded.
+	pub fn occlusion_dims_sky(&self) -> bool {
+		self.occlusion_dims_sky
+	}
+
+	/// Sets whether the sky dims along with the sun as it's occluded. The sky
+	/// is lit by the sun, so it should darken too once most of the sun's
+	/// covered, but a thin cloud in front of the sun alone wouldn't do that.
+	pub fn set_occlusion_dims_sky(&mut self, dims: bool) {
+		self.occlusion_dims_sky = dims;
+	}
+
+	/// Returns the factor the sun's disk and light are scaled by for the
+	/// occlusion.
+	pub fn sun_visibility(&self) -> f32 {
+		1.0 - self.sun_occlusion
+	}
+
+	/// Returns the factor the sky's radiance is scaled by for the occlusion.
+	/// It falls off with the square of the occlusion, so the sky only darkens
+	/// noticeably once most of the sun's covered.
+	pub fn sky_visibility(&self) -> f32 {
+		if self.occlusion_dims_sky {
+			1.0 - self.sun_occlusion * self.sun_occlusion
+		} else {
+			1.0
+		}
+	}
+
+	/// Returns the colour the sun's light is multiplied by when it's in a
+	/// direction, to warm it up near the horizon. It fades to white as the sun
+	/// rises, so midday sunlight isn't discoloured.
+	pub fn sun_tint(&self, sun_direction: Vector3<f32>) -> Vector3<f32> {
+		let low = 1.0 - smoothstep(0.0, SUN_TINT_FADE_SIN, sun_direction.normalize().y);
+		let white = Vector3::new(1.0, 1.0, 1.0);
+		white + (Vector3::from(WARM_SUN_TINT) - white) * (self.sun_warmth * low)
+	}
+
+	/// Returns true if the sun's below the horizon, where the model isn't
+	/// defined. The sky's faded out through twilight using the coefficients
+	/// for the sun on the horizon, then falls back to the night sky.
+	pub fn is_sun_below_horizon(&self) -> bool {
+		self.sun_direction.y < 0.0
+	}
+
+	/// Returns the coefficients to upload to the shader.
+	pub fn coefficients(&self) -> &SkyCoefficients {
+		&self.coefficients
+	}
+
+	/// Returns how far the sky has faded into night, from 0 while the sun is
+	/// up to 1 once it's far enough below the horizon that only the night sky
+	/// is visible.
+	pub fn night_blend(&self) -> f32 {
+		self.state().night_blend()
+	}
+
+	/// Returns a snapshot of the values the shader renders the sky from.
+	pub fn state(&self) -> SkyState {
+		SkyState {
+			sun_direction: self.sun_direction,
+			coefficients: self.coefficients,
+		}
+	}
+
+	/// Returns the rotation of the star field.
+	///
+	/// The stars turn with the sun along its path, which treats sidereal time
+	/// as solar time and ignores the few minutes a day the two drift apart.
+	pub fn star_rotation(&self) -> Matrix3<f32> {
+		let azimuth = Matrix3::from_axis_angle(Vector3::new(0.0, 1.0, 0.0),
+			Rad(self.sun.y));
+		let elevation = Matrix3::from_axis_angle(Vector3::new(-1.0, 0.0, 0.0),
+			Rad(self.sun.x));
+
+		// The shader multiplies directions on the left, so this takes them
+		// back to right-handed coordinates first
+		self.handedness.mirror() * azimuth * elevation
+	}
+
+	/// Returns the radiance of the sky in a direction, as rendered by the
+	/// shader (excluding the sun disc and stars). Directions below the horizon
+	/// are given the radiance at the horizon, unless the atmosphere's been
+	/// thickened with `set_atmosphere_thickness` to carry the gradient on.
+	pub fn sample(&self, direction: Vector3<f32>) -> Vector3<f32> {
+		let view = direction.normalize();
+		let cos_theta = saturate(view.y);
+		let cos_gamma = clamp(view.dot(self.sun_direction), -1.0, 1.0);
+		let gamma = cos_gamma.acos();
+		let model = |cos_theta| hosek_wilkie(cos_theta, gamma, cos_gamma, &self.coefficients);
+		let mut radiance = model(cos_theta);
+		if view.y < 0.0 {
+			// Mirror the gradient above the horizon through the horizon's colour
+			let mirrored = model(view.y.abs().min(self.horizon_drop()));
+			let extended = radiance * 2.0 - mirrored;
+			radiance = Vector3::new(extended.x.max(0.0), extended.y.max(0.0), extended.z.max(0.0));
+		}
+		let day = radiance.mul_element_wise(self.coefficients.radiance) * self.sky_visibility();
+
+		// Matches the gradient in night.glsl
+		let zenith = Vector3::from(NIGHT_ZENITH);
+		let horizon = Vector3::from(NIGHT_HORIZON);
+		let night = horizon + (zenith - horizon) * cos_theta.sqrt();
+		day + (night - day) * self.night_blend()
+	}
+
+	/// Returns the colour of sunlight arriving from a direction, after it's been
+	/// scattered by the atmosphere on the way. The lower the sun, the more air
+	/// the light passes through, and the redder it gets. Any extra warmth is
+	/// applied on top with `sun_tint`, and it's dimmed by the sun's occlusion.
+	/// Sunlight from below the horizon is black.
+	pub fn sun_light_color(&self, sun_direction: Vector3<f32>) -> Vector3<f32> {
+		let cos_zenith = sun_direction.normalize().y;
+		if cos_zenith <= 0.0 {
+			return Vector3::new(0.0, 0.0, 0.0);
+		}
+
+		// Kasten and Young's air mass formula, which unlike 1 / cos(zenith)
+		// stays finite at the horizon
+		let zenith = cos_zenith.acos().to_degrees();
+		let air_mass = 1.0 / (cos_zenith + 0.50572 * (96.07995 - zenith).powf(-1.6364));
+
+		// Preetham et al.'s fit from turbidity to the Angstrom turbidity
+		// coefficient, giving the aerosol optical depth
+		let beta = 0.04608 * self.turbidity - 0.04586;
+		let mut color = Vector3::new(0.0, 0.0, 0.0);
+		for i in 0 .. 3 {
+			let aerosol_depth = beta * WAVELENGTHS[i].powf(-AEROSOL_EXPONENT);
+			let depth = RAYLEIGH_DEPTH[i] + aerosol_depth;
+			color[i] = SUN_DISK_RADIANCE * (-air_mass * depth).exp();
+		}
+		color.mul_element_wise(self.sun_tint(sun_direction)) *
+			((1.0 - self.night_blend()) * self.sun_visibility())
+	}
+
+	/// Returns the radiance of the sky averaged over the upper hemisphere,
+	/// weighted by the cosine from the zenith. This is the irradiance on an
+	/// upward facing surface divided by pi, so it can be used directly as the
+	/// ambient light.
+	pub fn average_radiance(&self) -> Vector3<f32> {
+		// Choosing rings evenly in cos^2(theta) rather than theta weights each
+		// sample by the cosine already
+		let mut total = Vector3::new(0.0, 0.0, 0.0);
+		for ring in 0 .. AVERAGE_RINGS {
+			let cos_theta = ((ring as f32 + 0.5) / AVERAGE_RINGS as f32).sqrt();
+			let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+			for segment in 0 .. AVERAGE_SEGMENTS {
+				let phi = (segment as f32 + 0.5) / AVERAGE_SEGMENTS as f32 *
+					2.0 * f32::consts::PI;
+				let direction = Vector3::new(sin_theta * phi.sin(), cos_theta,
+					sin_theta * phi.cos());
+				total += self.sample(direction);
+			}
+		}
+		total / (AVERAGE_RINGS * AVERAGE_SEGMENTS) as f32
+	}
+
+	/// Returns the sunlight and ambient light for the current sun position.
+	pub fn lighting(&self) -> Lighting {
+		Lighting {
+			sun: self.sun_light_color(self.sun_direction),
+			ambient: self.average_radiance(),
+		}
+	}
+
+	/// Returns the number of times the coefficients have been computed since
+	/// the count was last reset.
+	pub fn recompute_count(&self) -> u32 {
+		self.recompute_count
+	}
+
+	/// Resets the recompute count to zero.
+	pub fn reset_recompute_count(&mut self) {
+		self.recompute_count = 0;
+	}
+
+	/// Recomputes the coefficients from the current inputs.
+	fn recompute(&mut self) {
+		let turbidity = if self.nearest_turbidity {
+			self.turbidity.round()
+		} else {
+			self.turbidity
+		};
+		let (sun_direction, coefficients) = recalc_sun(&self.datasets, self.sun,
+			turbidity, self.albedo, self.units, self.handedness);
+		self.sun_direction = sun_direction;
+		self.coefficients = coefficients;
+		self.recompute_count += 1;
+	}
+}
+
+
+/// Computes the sun direction and the sky model coefficients for a sun
+/// position, given as an (elevation, azimuth) pair. The sun direction's given
+/// in the coordinate convention `handedness`; an azimuth of zero is towards +z
+/// in right-handed coordinates.
+fn recalc_sun(datasets: &Datasets, sun_pos: Vector2<f32>, turbidity: f32, albedo: [f32; 3],
+		units: RadianceUnits, handedness: Handedness) -> (Vector3<f32>, SkyCoefficients) {
+	let sun_dir = Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), Rad(sun_pos.y)).rotate_vector(Quaternion::from_axis_angle(Vector3::new(-1.0, 0.0, 0.0), Rad(sun_pos.x)).rotate_vector(Vector3::new(0.0, 0.0, 1.0)));
+	let sun_dir = handedness.convert(sun_dir);
+
+	// Nothing's left of the day sky once twilight's over, so there's no need
+	// to evaluate the datasets
+	let brightness = sky_brightness(sun_dir.y);
+	if brightness == 0.0 {
+		return (sun_dir, NIGHT_COEFFICIENTS);
+	}
+
+	// Below the horizon, use the coefficients for the sun on the horizon
+	let sun_theta = saturate(sun_dir.y).acos();
+	let mut coefficients = coefficients(datasets, turbidity, albedo, sun_theta);
+
+	// The model's own radiance already falls off as the sun gets lower, so it
+	// only needs converting, and fading out through twilight
+	if units == RadianceUnits::Absolute {
+		coefficients.radiance *= LUMINOUS_EFFICACY * twilight_fade(sun_dir.y);
+		return (sun_dir, coefficients);
+	}
+
+	// Normalise the radiance towards the sun, then scale it to the brightness
+	// for the sun's elevation
+	let sun_radiance = hosek_wilkie(sun_theta.cos(), 0.0, 1.0, &coefficients)
+		.mul_element_wise(coefficients.radiance);
+	let luminance = sun_radiance.dot(Vector3::from(LUMINANCE_WEIGHTS));
+
+	// With next to no light towards the sun there's nothing to normalise, so
+	// fall back to the night sky
+	if luminance.is_nan() || luminance < MIN_SUN_LUMINANCE {
+		return (sun_dir, NIGHT_COEFFICIENTS);
+	}
+	coefficients.radiance *= brightness / luminance;
+
+	(sun_dir, coefficients)
+}
+
+/// Returns the luminance of the sky looking towards the sun for the sine of
+/// the sun's elevation.
+///
+/// During the day it rises from `HORIZON_BRIGHTNESS` at sunrise to
+/// `ZENITH_BRIGHTNESS` with the sun overhead. Once the sun sets, it fades
+/// linearly from `HORIZON_BRIGHTNESS` to 0 by the time the sun's `TWILIGHT_SIN`
+/// below the horizon, after which it's night.
+fn sky_brightness(sun_sin: f32) -> f32 {
+	if sun_sin >= 0.0 {
+		HORIZON_BRIGHTNESS + (ZENITH_BRIGHTNESS - HORIZON_BRIGHTNESS) * sun_sin.min(1.0)
+	} else {
+		HORIZON_BRIGHTNESS * twilight_fade(sun_sin)
+	}
+}
+
+/// Returns how much of the day sky is left for the sine of the sun's
+/// elevation: 1 while the sun's up, fading linearly to 0 by the time it's
+/// `TWILIGHT_SIN` below the horizon.
+fn twilight_fade(sun_sin: f32) -> f32 {
+	saturate(1.0 + sun_sin / TWILIGHT_SIN)
+}
+
+
+/// Computes the Hosek-Wilkie coefficients for each colour channel, in the
+/// same way as the reference implementation.
+fn coefficients(datasets: &Datasets, turbidity: f32, albedo: [f32; 3], sun_theta: f32)
+		-> SkyCoefficients {
+	let mut coefficients = NIGHT_COEFFICIENTS;
+	for i in 0 .. 3 {
+		// Every coefficient for a channel shares the same inputs
+		let context = EvalContext::new(turbidity, albedo[i], sun_theta);
+		let config = |index: usize| {
+			evaluate(&datasets.config(i)[index ..], CONFIG_LAYOUT, &context)
+		};
+		coefficients.a[i] = config(CONFIG_A);
+		coefficients.b[i] = config(CONFIG_B);
+		coefficients.c[i] = config(CONFIG_C);
+		coefficients.d[i] = config(CONFIG_D);
+		coefficients.e[i] = config(CONFIG_E);
+		coefficients.f[i] = config(CONFIG_F);
+		coefficients.g[i] = config(CONFIG_G);
+		coefficients.h[i] = config(CONFIG_H);
+		coefficients.i[i] = config(CONFIG_I);
+
+		// The overall radiance comes from a dataset of its own
+		coefficients.radiance[i] = evaluate(datasets.radiance(i), RADIANCE_LAYOUT,
+			&context);
+	}
+	coefficients
+}
+
+
+/// The quantities needed to evaluate a coefficient that only depend on the
+/// model's inputs, shared between every coefficient evaluated for the same
+/// turbidity, albedo, and sun position.
+struct EvalContext {
+	/// The weight of each spline control point for the sun's elevation.
+	weights: [f32; 6],
+	/// The lower of the two turbidity levels interpolated between.
+	turbidity0: usize,
+	/// The higher of the two turbidity levels interpolated between.
+	turbidity1: usize,
+	/// How far between the two turbidity levels the turbidity is.
+	turbidity_k: f32,
+	/// The ground albedo, used to interpolate between the albedo levels.
+	albedo: f32,
+}
+
+impl EvalContext {
+	/// Computes the shared quantities for a turbidity, albedo, and sun angle
+	/// from the zenith.
+	fn new(turbidity: f32, albedo: f32, sun_theta: f32) -> EvalContext {
+		// splines are functions of elevation^1/3
+		let elevation_k = (1.0 - sun_theta / f32::consts::FRAC_PI_2).max(0.0).powf(1.0 / 3.0);
+
+		// table has values for turbidity 1..10
+		let turbidities = CONFIG_LAYOUT.turbidities;
+		let turbidity0 = clamp(turbidity as usize, 1, turbidities);
+		let turbidity1 = (turbidity0 + 1).min(turbidities);
+		let turbidity_k = saturate(turbidity - turbidity0 as f32);
+
+		// table only has albedo 0 and 1, and anything outside them would
+		// extrapolate the dataset into nonsense
+		let albedo = saturate(albedo);
+
+		EvalContext {
+			weights: spline_weights(elevation_k),
+			turbidity0: turbidity0,
+			turbidity1: turbidity1,
+			turbidity_k: turbidity_k,
+			albedo: albedo,
+		}
+	}
+}
+
+/// Returns the weight of each control point of a quintic Bezier spline at a
+/// position along it.
+fn spline_weights(value: f32) -> [f32; 6] {
+	[
+		1.0 *  (1.0 - value).powi(5),
+		5.0 *  (1.0 - value).powi(4) * value.powi(1),
+		10.0 * (1.0 - value).powi(3) * value.powi(2),
+		10.0 * (1.0 - value).powi(2) * value.powi(3),
+		5.0 *  (1.0 - value).powi(1) * value.powi(4),
+		1.0 *                          value.powi(5),
+	]
+}
+
+fn evaluate_spline(dataset: &[f32], start: usize, stride: usize, weights: &[f32; 6]) -> f32 {
+	weights[0] * dataset[start] +
+	weights[1] * dataset[start + stride] +
+	weights[2] * dataset[start + 2 * stride] +
+	weights[3] * dataset[start + 3 * stride] +
+	weights[4] * dataset[start + 4 * stride] +
+	weights[5] * dataset[start + 5 * stride]
+}
+
+fn evaluate(dataset: &[f32], layout: DatasetLayout, context: &EvalContext) -> f32 {
+	// The splines are quintic, and the dataset may start part way into the
+	// first control point to select a coefficient
+	debug_assert_eq!(layout.control_points, 6);
+	debug_assert!(dataset.len() > layout.len() - layout.stride);
+	debug_assert!(context.albedo >= 0.0 && context.albedo <= 1.0,
+		"albedo {} outside [0, 1]", context.albedo);
+
+	// Only the lowest and highest albedo levels are interpolated between
+	let (albedo0, albedo1) = (0, layout.albedos - 1);
+	let (turbidity0, turbidity1) = (context.turbidity0, context.turbidity1);
+	let stride = layout.stride;
+	let weights = &context.weights;
+
+	let a0t0 = evaluate_spline(dataset, layout.offset(albedo0, turbidity0 - 1), stride, weights);
+	let a1t0 = evaluate_spline(dataset, layout.offset(albedo1, turbidity0 - 1), stride, weights);
+	let a0t1 = evaluate_spline(dataset, layout.offset(albedo0, turbidity1 - 1), stride, weights);
+	let a1t1 = evaluate_spline(dataset, layout.offset(albedo1, turbidity1 - 1), stride, weights);
+
+	let albedo = context.albedo;
+	let turbidity_k = context.turbidity_k;
+	let t0 = a0t0 * (1.0 - albedo) + a1t0 * albedo;
+	let t1 = a0t1 * (1.0 - albedo) + a1t1 * albedo;
+	t0 * (1.0 - turbidity_k) + t1 * turbidity_k
+}
+
+fn hosek_wilkie(cos_theta: f32, gamma: f32, cos_gamma: f32, coefficients: &SkyCoefficients) -> Vector3<f32> {
+	let a = coefficients.a;
+	let b = coefficients.b;
+	let c = coefficients.c;
+	let d = coefficients.d;
+	let e = coefficients.e;
+	let f = coefficients.f;
+	let g = coefficients.g;
+	let h = coefficients.h;
+	let i = coefficients.i;
+	let cos_theta = cos_theta.max(MIN_COS_THETA);
+
+	let chi = (1.0 + cos_gamma * cos_gamma) / powv(h.mul_element_wise(h)
+		.add_element_wise(1.0) - 2.0 * cos_gamma * h, Vector3::new(1.5, 1.5, 1.5));
+	let gradient = a.mul_element_wise(exp(b / (cos_theta + 0.01))).add_element_wise(1.0);
+	gradient.mul_element_wise(c + d.mul_element_wise(exp(e * gamma))
+		+ f * (cos_gamma * cos_gamma) + g.mul_element_wise(chi)
+		+ i * cos_theta.max(0.0).sqrt())
+}
+
+fn powv(a: Vector3<f32>, b: Vector3<f32>) -> Vector3<f32> {
+	Vector3::new(a.x.powf(b.x), a.y.powf(b.y), a.z.powf(b.z))
+}
+
+fn exp(vec: Vector3<f32>) -> Vector3<f32> {
+	Vector3::new(vec.x.exp(), vec.y.exp(), vec.z.exp())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{DatasetLayout, CONFIG_LAYOUT, RADIANCE_LAYOUT, MAX_SUN_WARMTH,
+		MAX_ATMOSPHERE_THICKNESS, MIN_COS_THETA,
+		HORIZON_BRIGHTNESS, ZENITH_BRIGHTNESS, NIGHT_COEFFICIENTS,
+		LUMINANCE_WEIGHTS, LUMINOUS_EFFICACY, Datasets, RadianceUnits,
+		SkyModel, coefficients, hosek_wilkie, sky_brightness};
+	use hosek::{DATASETS_RGB, DATASETS_RGB_RAD};
+
+	use cgmath::{Vector2, Vector3, ElementWise, InnerSpace};
+
+	/// Radiance computed by the reference C implementation, from
+	/// `tests/hosek_reference.txt`.
+	static REFERENCE: &'static str = include_str!("../tests/hosek_reference.txt");
+
+	/// The largest allowed relative difference from the reference values. The
+	/// reference is computed in double precision, and this in single.
+	const TOLERANCE: f32 = 1e-3;
+
+	/// The offset of a spline, as it was originally hardcoded in `evaluate`.
+	fn hardcoded_offset(stride: usize, albedo: usize, turbidity: usize) -> usize {
+		let dataset = if albedo == 0 { 0 } else { stride * 6 * 10 };
+		dataset + stride * 6 * (turbidity - 1)
+	}
+
+	fn check_offsets(layout: DatasetLayout) {
+		for albedo in 0 .. 2 {
+			for turbidity in 1 .. 11 {
+				assert_eq!(layout.offset(albedo, turbidity - 1),
+					hardcoded_offset(layout.stride, albedo, turbidity));
+			}
+		}
+	}
+
+	#[test]
+	fn config_offsets_match_hardcoded() {
+		check_offsets(CONFIG_LAYOUT);
+	}
+
+	#[test]
+	fn radiance_offsets_match_hardcoded() {
+		check_offsets(RADIANCE_LAYOUT);
+	}
+
+	#[test]
+	fn layouts_cover_datasets() {
+		for i in 0 .. 3 {
+			assert_eq!(CONFIG_LAYOUT.len(), DATASETS_RGB[i].len());
+			assert_eq!(RADIANCE_LAYOUT.len(), DATASETS_RGB_RAD[i].len());
+		}
+	}
+
+	/// Parses the reference table into (turbidity, albedo, sun_theta,
+	/// view_theta, view_gamma, expected RGB) cases.
+	fn reference_cases() -> Vec<([f32; 5], Vector3<f32>)> {
+		REFERENCE.lines()
+			.map(|line| line.trim())
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.map(|line| {
+				let values: Vec<f32> = line.split_whitespace()
+					.map(|value| value.parse().unwrap())
+					.collect();
+				assert_eq!(values.len(), 8, "Malformed reference line: {}", line);
+				let inputs = [values[0], values[1], values[2], values[3], values[4]];
+				(inputs, Vector3::new(values[5], values[6], values[7]))
+			})
+			.collect()
+	}
+
+	fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>, case: &[f32; 5]) {
+		for i in 0 .. 3 {
+			let error = (actual[i] - expected[i]).abs() / expected[i].abs();
+			assert!(error < TOLERANCE, "Channel {} of {:?}: got {}, expected {}",
+				i, case, actual[i], expected[i]);
+		}
+	}
+
+	#[test]
+	fn coefficients_match_reference() {
+		let cases = reference_cases();
+		assert!(!cases.is_empty());
+		for (case, expected) in cases {
+			let [turbidity, albedo, sun_theta, theta, gamma] = case;
+			let coefficients = coefficients(&Datasets::embedded(), turbidity, [albedo; 3],
+				sun_theta);
+			let radiance = hosek_wilkie(theta.cos(), gamma, gamma.cos(), &coefficients)
+				.mul_element_wise(coefficients.radiance);
+			assert_close(radiance, expected, &case);
+		}
+	}
+
+	#[test]
+	fn sample_matches_reference_up_to_scale() {
+		// The model normalises the radiance for display, but only by a single
+		// factor for each sun position, so the ratio between two directions
+		// is unchanged
+		let sun_theta = 0.7;
+		let model = SkyModel::new(5.7, [0.5; 3], Vector2::new(
+			::std::f32::consts::FRAC_PI_2 - sun_theta, 0.0));
+		let sun = model.sun_direction();
+		let zenith = Vector3::new(0.0, 1.0, 0.0);
+		let (_, expected) = reference_cases().into_iter()
+			.find(|&(case, _)| case[0] == 5.7 && case[2] == sun_theta)
+			.unwrap();
+
+		// The reference direction is 1 radian from the zenith, and 1.6 from
+		// the sun. Find it by rotating the zenith towards the sun and around
+		let direction = direction_from(sun, 1.0, 1.6);
+		let reference_zenith = {
+			let coefficients = coefficients(&Datasets::embedded(), 5.7, [0.5; 3], sun_theta);
+			hosek_wilkie(1.0, sun_theta, sun_theta.cos(), &coefficients)
+				.mul_element_wise(coefficients.radiance)
+		};
+		let scale = model.sample(zenith).div_element_wise(reference_zenith);
+		let case = [5.7, 0.5, sun_theta, 1.0, 1.6];
+		assert_close(model.sample(direction), expected.mul_element_wise(scale), &case);
+	}
+
+	#[test]
+	fn sun_light_reddens_near_horizon() {
+		let mut previous_ratio = 0.0;
+		for &elevation in &[60.0f32, 30.0, 10.0, 3.0] {
+			let sky = SkyModel::new(4.0, [0.1; 3],
+				Vector2::new(elevation.to_radians(), 0.0));
+			let color = sky.sun_light_color(sky.sun_direction());
+			let ratio = color.x / color.z;
+			assert!(ratio > previous_ratio,
+				"sun colour {:?} at {} degrees isn't redder than above", color, elevation);
+			assert!(color.x > color.y && color.y > color.z);
+			previous_ratio = ratio;
+		}
+
+		// Nothing reaches the ground once the sun's set
+		let sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(-0.2, 0.0));
+		assert_eq!(sky.lighting().sun, Vector3::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn sun_tint_only_warms_low_sun() {
+		let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.05, 0.0));
+		let untinted = sky.sun_light_color(sky.sun_direction());
+		assert_eq!(sky.sun_tint(sky.sun_direction()), Vector3::new(1.0, 1.0, 1.0));
+
+		// Warmer near the horizon, but clamped to the maximum
+		sky.set_sun_warmth(10.0);
+		assert_eq!(sky.sun_warmth(), MAX_SUN_WARMTH);
+		let tinted = sky.sun_light_color(sky.sun_direction());
+		assert!(tinted.x / tinted.z > untinted.x / untinted.z);
+
+		// Midday sunlight is left alone
+		let overhead = Vector3::new(0.0, 1.0, 0.0);
+		assert_eq!(sky.sun_tint(overhead), Vector3::new(1.0, 1.0, 1.0));
+	}
+
+	#[test]
+	fn occlusion_dims_sun() {
+		let plain = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.4, 0.0));
+		let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.4, 0.0));
+		let direction = Vector3::new(0.3, 0.5, 1.0);
+
+		// No occlusion leaves the sun exactly as bright as before
+		sky.set_sun_occlusion(-1.0);
+		assert_eq!(sky.sun_occlusion(), 0.0);
+		assert_eq!(sky.lighting(), plain.lighting());
+
+		sky.set_sun_occlusion(0.75);
+		let sun = sky.sun_light_color(sky.sun_direction());
+		let expected = plain.sun_light_color(plain.sun_direction()) * 0.25;
+		assert!((sun - expected).magnitude() < 1e-6, "{:?}", sun);
+		assert_eq!(sky.sample(direction), plain.sample(direction));
+
+		// The sky only dims if asked to, and then only a little until most of
+		// the sun's covered
+		sky.set_occlusion_dims_sky(true);
+		let dimmed = sky.sample(direction);
+		assert!((dimmed - plain.sample(direction) * (1.0 - 0.75 * 0.75)).magnitude() < 1e-6);
+		sky.set_sun_occlusion(2.0);
+		assert_eq!(sky.sun_occlusion(), 1.0);
+		assert_eq!(sky.sun_light_color(sky.sun_direction()), Vector3::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn atmosphere_extends_below_horizon() {
+		let plain = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.3, 0.0));
+		let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.3, 0.0));
+		let below = |height: f32| Vector3::new(0.0, height, 1.0);
+
+		// Thickening the atmosphere carries the gradient on down without a
+		// jump at the horizon, and leaves the sky above the horizon alone
+		sky.set_atmosphere_thickness(5.0f32.to_radians());
+		assert!(sky.sample(below(-0.01)) != plain.sample(below(-0.01)));
+		let step = sky.sample(below(-1e-4)) - plain.sample(below(0.0));
+		assert!(step.magnitude() < 1e-3 * plain.sample(below(0.0)).magnitude());
+		for &height in &[0.0, 0.01, 0.3, 1.0] {
+			assert_eq!(sky.sample(below(height)), plain.sample(below(height)));
+		}
+		for &height in &[-1.0, -0.1, -0.05, -0.001, 0.0] {
+			let radiance = sky.sample(below(height));
+			assert!(radiance.x.is_finite() && radiance.y.is_finite() &&
+				radiance.z.is_finite(), "{:?} at {}", radiance, height);
+		}
+
+		// Zero thickness leaves the sky exactly as it was
+		sky.set_atmosphere_thickness(-1.0);
+		assert_eq!(sky.atmosphere_thickness(), 0.0);
+		for &height in &[-0.2, -0.01, 0.0, 0.3] {
+			assert_eq!(sky.sample(below(height)), plain.sample(below(height)));
+		}
+		sky.set_atmosphere_thickness(1.0);
+		assert_eq!(sky.atmosphere_thickness(), MAX_ATMOSPHERE_THICKNESS);
+	}
+
+	#[test]
+	fn albedo_clamped_to_dataset() {
+		// Each channel's clamped on its own
+		let sun = Vector2::new(0.3, 0.0);
+		let clamped = SkyModel::new(4.0, [1.5, -0.5, 0.4], sun);
+		let edges = SkyModel::new(4.0, [1.0, 0.0, 0.4], sun);
+		assert_eq!(clamped.coefficients(), edges.coefficients());
+
+		let radiance = clamped.sample(Vector3::new(0.0, 0.2, 1.0));
+		assert!(radiance.x >= 0.0 && radiance.y >= 0.0 && radiance.z >= 0.0);
+	}
+
+	#[test]
+	fn brightness_follows_sun_elevation() {
+		assert_eq!(sky_brightness(0.0), HORIZON_BRIGHTNESS);
+		assert_eq!(sky_brightness(1.0), ZENITH_BRIGHTNESS);
+		assert!(sky_brightness(0.5) > HORIZON_BRIGHTNESS);
+		assert!(sky_brightness(-0.05) < HORIZON_BRIGHTNESS);
+
+		// Fully dark once twilight's over, using the night coefficients
+		let mut sky = SkyModel::new(4.0, [0.1; 3], Vector2::new(0.3, 0.0));
+		assert!(!sky.is_sun_below_horizon());
+		sky.set_sun(Vector2::new(-0.5, 0.0));
+		assert!(sky.is_sun_below_horizon());
+		assert_eq!(sky.night_blend(), 1.0);
+		assert_eq!(*sky.coefficients(), NIGHT_COEFFICIENTS);
+		let zenith = sky.sample(Vector3::new(0.0, 1.0, 0.0));
+		assert!((zenith - Vector3::from(super::NIGHT_ZENITH)).magnitude() < 1e-6);
+	}
+
+	#[test]
+	fn absolute_radiance_matches_reference_zenith() {
+		// Turbidity 1 and albedo 0 with the sun 0.2 radians from the zenith,
+		// looking straight up
+		let (case, expected) = reference_cases()[0];
+		assert_eq!(case, [1.0, 0.0, 0.2, 0.0, 0.2]);
+		let mut model = SkyModel::new(1.0, [0.0; 3], Vector2::new(
+			::std::f32::consts::FRAC_PI_2 - 0.2, 0.0));
+		assert!(model.set_units(RadianceUnits::Absolute));
+
+		let zenith = model.sample(Vector3::new(0.0, 1.0, 0.0));
+		assert_close(zenith, expected * LUMINOUS_EFFICACY, &case);
+
+		// About 3000 cd/m^2, as expected for a clear sky with a high sun
+		let luminance = zenith.dot(Vector3::from(LUMINANCE_WEIGHTS));
+		assert!((luminance - 3033.0).abs() < 5.0, "zenith luminance {}", luminance);
+	}
+
+	#[test]
+	fn coefficients_finite_for_every_elevation() {
+		for &(turbidity, albedo) in &[(1.0, 0.0), (4.0, 0.3), (10.0, 1.0)] {
+			for step in 0 .. 181 {
+				let elevation = (step as f32 - 90.0).to_radians();
+				let sky = SkyModel::new(turbidity, [albedo; 3], Vector2::new(elevation, 0.0));
+				for param in sky.coefficients().as_uniform_slice() {
+					assert!(param.x.is_finite() && param.y.is_finite() && param.z.is_finite(),
+						"coefficient {:?} at elevation {}\u{b0}, turbidity {}, albedo {}",
+						param, step as f32 - 90.0, turbidity, albedo);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn sample_below_horizon_is_finite() {
+		// Try the sun both high and low, where the exponential term is largest
+		for &elevation in &[0.05, 1.0] {
+			let model = SkyModel::new(5.7, [0.5; 3], Vector2::new(elevation, 0.0));
+			for &direction in &[Vector3::new(0.0, -1.0, 0.0),
+					Vector3::new(0.0, -0.005, 1.0), Vector3::new(1.0, -0.5, -1.0)] {
+				let radiance = model.sample(direction);
+				for &channel in &[radiance.x, radiance.y, radiance.z] {
+					assert!(channel.is_finite() && channel >= 0.0,
+						"radiance {:?} in direction {:?}", radiance, direction);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn model_is_clamped_at_the_horizon() {
+		// `sample` never looks below the horizon, so call the model directly.
+		// Unclamped, the exponential term divides by zero a hundredth below
+		// the horizon and overflows just past it
+		let model = SkyModel::new(5.7, [0.5; 3], Vector2::new(0.05, 0.0));
+		let coefficients = model.coefficients();
+		let at_limit = hosek_wilkie(MIN_COS_THETA, 1.0, 0.5, coefficients);
+		for &cos_theta in &[0.0, -0.01, -0.02, -1.0] {
+			assert_eq!(hosek_wilkie(cos_theta, 1.0, 0.5, coefficients), at_limit,
+				"cos theta {}", cos_theta);
+		}
+		assert!(hosek_wilkie(MIN_COS_THETA * 2.0, 1.0, 0.5, coefficients) != at_limit);
+	}
+
+	#[test]
+	fn nearest_turbidity_snaps_to_levels() {
+		let sun = Vector2::new(0.4, 0.0);
+		let level = |turbidity: f32| *SkyModel::new(turbidity, [0.3; 3], sun).coefficients();
+		let mut model = SkyModel::new(3.4, [0.3; 3], sun);
+		let interpolated = *model.coefficients();
+		assert!(interpolated != level(3.0) && interpolated != level(4.0));
+
+		assert!(model.set_nearest_turbidity(true));
+		assert_eq!(*model.coefficients(), level(3.0));
+		model.set_turbidity(3.6);
+		assert_eq!(*model.coefficients(), level(4.0));
+
+		// Turning it off goes back to interpolating
+		assert!(model.set_nearest_turbidity(false));
+		assert_eq!(*model.coefficients(), level(3.6));
+	}
+
+	#[test]
+	fn state_lerp_ends_at_each_state() {
+		let a = SkyModel::new(2.0, [0.1; 3], Vector2::new(0.3, 0.0)).state();
+		let b = SkyModel::new(8.0, [0.8; 3], Vector2::new(0.9, 1.0)).state();
+		assert_eq!(a.lerp(&b, 0.0), a);
+		assert_eq!(a.lerp(&b, 1.0), b);
+
+		let half = a.lerp(&b, 0.5);
+		assert!((half.sun_direction.magnitude() - 1.0).abs() < 1e-5);
+		let (mid, start, end) = (half.coefficients.as_uniform_slice(),
+			a.coefficients.as_uniform_slice(), b.coefficients.as_uniform_slice());
+		for ((mid, start), end) in mid.iter().zip(start.iter()).zip(end.iter()) {
+			assert!((mid - (start + end) * 0.5).magnitude() <= 1e-5 * end.magnitude().max(1.0));
+		}
+	}
+
+	/// Returns a direction at angle `theta` from the zenith and `gamma` from
+	/// the sun.
+	fn direction_from(sun: Vector3<f32>, theta: f32, gamma: f32) -> Vector3<f32> {
+		// Solve for the azimuth relative to the sun using the spherical law
+		// of cosines
+		let sun_theta = sun.y.acos();
+		let cos_phi = (gamma.cos() - theta.cos() * sun_theta.cos()) /
+			(theta.sin() * sun_theta.sin());
+		let sun_azimuth = sun.x.atan2(sun.z);
+		let azimuth = sun_azimuth + cos_phi.max(-1.0).min(1.0).acos();
+		Vector3::new(theta.sin() * azimuth.sin(), theta.cos(),
+			theta.sin() * azimuth.cos()).normalize()
+	}
+}
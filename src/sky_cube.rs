@@ -0,0 +1,122 @@
+
+//
+//  Baked Sky Cube Map
+//
+
+use gl;
+use gl::types::*;
+use cgmath::Vector3;
+
+use shader::{ShaderProgram, ShaderSources};
+use sky;
+use sky_data;
+use texture::{Filter, Texture, TextureTarget};
+
+
+/// The width and height of each face of the cube map, in pixels.
+const FACE_SIZE: u32 = 256;
+
+
+/// The sky rendered onto the six faces of a cube map, as an engine would bake
+/// it for reflections or a cheaper skybox. It can be unwrapped onto the screen
+/// to check that neighbouring faces meet without a seam.
+pub struct SkyCube {
+	/// The cube map holding the baked sky.
+	texture: Texture,
+	/// The framebuffer each face is attached to in turn while baking.
+	fbo: GLuint,
+	/// Renders the sky into a single face.
+	bake_program: ShaderProgram,
+	/// Unwraps the cube map onto the screen.
+	net_program: ShaderProgram,
+
+	/// The face uniform for baking.
+	face_uniform: GLint,
+	/// The cube map sampler uniform for the net.
+	sky_cube_uniform: GLint,
+	/// The viewport size uniform for the net.
+	viewport_size_uniform: GLint,
+}
+
+impl SkyCube {
+	/// Creates the cube map, leaving its contents undefined until it's baked.
+	pub fn new(sources: &ShaderSources) -> SkyCube {
+		let texture = Texture::new(TextureTarget::Cube, gl::RGBA16F, FACE_SIZE,
+			FACE_SIZE, Filter::Linear);
+		let bake_program = sources.program("post_vert.glsl", "cube_bake_frag.glsl");
+		let net_program = sources.program("post_vert.glsl", "cube_net_frag.glsl");
+
+		// The sky's coefficients come from the same uniform block as the sky
+		// shader's, and the night sky colours never change
+		bake_program.bind_uniform_block(sky_data::BLOCK_NAME, sky_data::BINDING);
+		bake_program.bind();
+		bake_program.set_uniform_vec3(bake_program.uniform("night_zenith"),
+			Vector3::from(sky::NIGHT_ZENITH));
+		bake_program.set_uniform_vec3(bake_program.uniform("night_horizon"),
+			Vector3::from(sky::NIGHT_HORIZON));
+
+		let mut fbo = 0;
+		unsafe {
+			gl::GenFramebuffers(1, &mut fbo);
+
+			// Filter across the edges of faces, rather than clamping to each
+			// face's own texels, so a correct bake has no visible seams
+			gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+		}
+
+		SkyCube {
+			face_uniform: bake_program.uniform("face"),
+			sky_cube_uniform: net_program.uniform("sky_cube"),
+			viewport_size_uniform: net_program.uniform("viewport_size"),
+			texture: texture,
+			fbo: fbo,
+			bake_program: bake_program,
+			net_program: net_program,
+		}
+	}
+
+	/// Renders the sky into every face of the cube map, using the values in
+	/// the SkyData block. Leaves the cube map's framebuffer bound.
+	///
+	/// Panics if a face can't be rendered to.
+	///
+	/// Assumes a VAO suitable for drawing the fullscreen triangle is bound.
+	pub fn bake(&self) {
+		self.bake_program.bind();
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+			gl::Viewport(0, 0, FACE_SIZE as GLsizei, FACE_SIZE as GLsizei);
+		}
+		for (index, &face) in self.texture.target().faces().iter().enumerate() {
+			unsafe {
+				gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, face,
+					self.texture.id(), 0);
+				let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+				if status != gl::FRAMEBUFFER_COMPLETE {
+					panic!("Cube map framebuffer incomplete: {:#x}", status);
+				}
+			}
+			self.bake_program.set_uniform_i32(self.face_uniform, index as GLint);
+			unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3); }
+		}
+	}
+
+	/// Draws the faces of the cube map unwrapped into a cross, into the bound
+	/// framebuffer with the given dimensions, in pixels.
+	///
+	/// Assumes a VAO suitable for drawing the fullscreen triangle is bound.
+	pub fn draw_net(&self, width: u32, height: u32) {
+		self.net_program.bind();
+		self.texture.bind(0);
+		self.net_program.set_uniform_i32(self.sky_cube_uniform, 0);
+		self.net_program.set_uniform_vec2(self.viewport_size_uniform, width as f32,
+			height as f32);
+		unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3); }
+	}
+}
+
+impl Drop for SkyCube {
+	fn drop(&mut self) {
+		unsafe { gl::DeleteFramebuffers(1, &self.fbo); }
+	}
+}
@@ -0,0 +1,81 @@
+
+//
+//  Sky Data Uniform Block
+//
+
+use gl::types::*;
+
+use sky::{SkyCoefficients, SkyModel, SkyState};
+
+
+/// The name of the uniform block declared in `sky.glsl`.
+pub const BLOCK_NAME: &'static str = "SkyData";
+
+/// The binding point the sky data buffer is attached to.
+pub const BINDING: GLuint = 0;
+
+
+/// The sky model's values, laid out to match the `SkyData` block in `sky.glsl`
+/// under `std140` rules: each element of an array of `vec3`s takes up 16
+/// bytes, but a `float` can pack into the space after a lone `vec3`.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct SkyData {
+	/// The Hosek-Wilkie coefficients, in the order of `SkyCoefficients`, each
+	/// padded out to 4 floats.
+	pub coefficients: [[f32; 4]; SkyCoefficients::COUNT],
+	/// The direction towards the sun.
+	pub sun_direction: [f32; 3],
+	/// How far the sky has faded into night.
+	pub night_blend: f32,
+	/// How far below the horizon the gradient's extended. See
+	/// `SkyModel::horizon_drop`.
+	pub horizon_drop: f32,
+	/// Pads the block out to a whole number of `vec4`s.
+	pub padding: [f32; 3],
+}
+
+impl SkyData {
+	/// Gathers the values the shaders need from a sky model and the state of
+	/// the sky to draw, which can differ from the model's own during a
+	/// transition.
+	pub fn new(sky: &SkyModel, state: &SkyState) -> SkyData {
+		// Dimming the sky for the sun's occlusion scales the whole model
+		let mut scaled = state.coefficients;
+		scaled.radiance *= sky.sky_visibility();
+
+		let mut coefficients = [[0.0; 4]; SkyCoefficients::COUNT];
+		let values = scaled.as_uniform_slice();
+		for (padded, value) in coefficients.iter_mut().zip(values.iter()) {
+			*padded = [value.x, value.y, value.z, 0.0];
+		}
+
+		SkyData {
+			coefficients: coefficients,
+			sun_direction: state.sun_direction.into(),
+			night_blend: state.night_blend(),
+			horizon_drop: sky.horizon_drop(),
+			padding: [0.0; 3],
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::SkyData;
+
+	use std::mem;
+
+	#[test]
+	fn matches_std140_layout() {
+		// The 10 padded coefficient vectors, then a vec3 with the float packed
+		// in after it, then a float padded out to a vec4
+		assert_eq!(mem::size_of::<SkyData>(), 10 * 16 + 16 + 16);
+		let data = SkyData::default();
+		let base = &data as *const SkyData as usize;
+		assert_eq!(&data.sun_direction as *const _ as usize - base, 160);
+		assert_eq!(&data.night_blend as *const _ as usize - base, 172);
+		assert_eq!(&data.horizon_drop as *const _ as usize - base, 176);
+	}
+}
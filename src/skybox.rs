@@ -0,0 +1,128 @@
+
+//
+//  Skybox Cube
+//
+
+use gl;
+use gl::types::*;
+
+use mesh::Mesh;
+use math::Handedness;
+
+
+/// The corners of a cube around the camera.
+const VERTEX_DATA: [GLfloat; 24] = [
+	-1.0, -1.0,  1.0, // 0: Left,  bottom, front
+	 1.0, -1.0,  1.0, // 1: Right, bottom, front
+	 1.0,  1.0,  1.0, // 2: Right, top,    front
+	-1.0,  1.0,  1.0, // 3: Left,  top,    front
+	-1.0, -1.0, -1.0, // 4: Left,  bottom, back
+	 1.0, -1.0, -1.0, // 5: Right, bottom, back
+	 1.0,  1.0, -1.0, // 6: Right, top,    back
+	-1.0,  1.0, -1.0, // 7: Left,  top,    back
+];
+
+/// The cube's triangles, each wound anticlockwise seen from outside the cube,
+/// the usual way round for a closed shape. The camera's always inside it, so
+/// `cull_face` decides which side's drawn rather than the winding.
+const INDEX_DATA: [GLushort; 36] = [
+	0, 1, 2, 2, 3, 0, // Front
+	7, 6, 5, 5, 4, 7, // Back
+	4, 0, 3, 3, 7, 4, // Left
+	1, 5, 6, 6, 2, 1, // Right
+	3, 2, 6, 6, 7, 3, // Top
+	5, 1, 0, 0, 4, 5, // Bottom
+];
+
+
+/// A cube the sky's drawn onto from the inside, as the alternative to a single
+/// triangle covering the screen.
+///
+/// Culling's left on while it's drawn, but switched to the faces pointing out
+/// of the cube, so only the insides the camera sees are drawn at any angle.
+pub struct Skybox {
+	/// The cube's triangles.
+	mesh: Mesh,
+	/// The faces culled while the cube's drawn, as passed to `glCullFace`.
+	cull_face: GLenum,
+}
+
+impl Skybox {
+	/// Creates the cube, with positions given in `handedness` and fed to the
+	/// vertex attribute `position`.
+	pub fn new(handedness: Handedness, position: GLuint) -> Skybox {
+		Skybox {
+			mesh: Mesh::indexed(gl::TRIANGLES, &VERTEX_DATA, &INDEX_DATA, 3, position),
+			cull_face: cull_face(handedness),
+		}
+	}
+
+	/// Draws the cube with the currently bound shader, putting back culling of
+	/// back faces afterwards.
+	pub fn draw(&self) {
+		unsafe {
+			gl::CullFace(self.cull_face);
+		}
+		self.mesh.draw();
+		unsafe {
+			gl::CullFace(gl::BACK);
+		}
+	}
+}
+
+
+/// Returns the faces to cull to see the cube from the inside. The outward
+/// faces are the front faces, unless mirroring the coordinate convention into
+/// OpenGL's has turned them inside out.
+fn cull_face(handedness: Handedness) -> GLenum {
+	if handedness.is_mirrored() {
+		gl::BACK
+	} else {
+		gl::FRONT
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{cull_face, INDEX_DATA, VERTEX_DATA};
+	use math::Handedness;
+
+	use gl;
+	use cgmath::{Vector3, InnerSpace};
+
+	/// Returns a corner of the cube.
+	fn corner(index: u16) -> Vector3<f32> {
+		let start = index as usize * 3;
+		Vector3::new(VERTEX_DATA[start], VERTEX_DATA[start + 1], VERTEX_DATA[start + 2])
+	}
+
+	#[test]
+	fn every_face_points_outwards() {
+		// Seen from the middle, where the camera always is, every triangle's
+		// wound the same way, so one cull mode keeps all of them whichever
+		// way the camera's turned
+		for triangle in INDEX_DATA.chunks(3) {
+			let (a, b, c) = (corner(triangle[0]), corner(triangle[1]), corner(triangle[2]));
+			let normal = (b - a).cross(c - a);
+			assert!(normal.dot(a) > 0.0, "triangle {:?} points into the cube", triangle);
+		}
+
+		// Each of the six sides is covered by its two triangles
+		let mut areas = Vec::new();
+		for side in INDEX_DATA.chunks(6) {
+			let area: f32 = side.chunks(3)
+				.map(|t| (corner(t[1]) - corner(t[0])).cross(corner(t[2]) - corner(t[0])))
+				.map(|normal| normal.magnitude() / 2.0)
+				.sum();
+			areas.push(area);
+		}
+		assert_eq!(areas, vec![4.0; 6]);
+	}
+
+	#[test]
+	fn mirroring_culls_the_other_side() {
+		assert_eq!(cull_face(Handedness::Right), gl::FRONT);
+		assert_eq!(cull_face(Handedness::Left), gl::BACK);
+	}
+}
@@ -0,0 +1,175 @@
+
+//
+//  Sun Control
+//
+
+use math::clamp;
+
+use cgmath::Vector2;
+use std::f32;
+
+
+/// The speed the sun moves at as soon as a key's pressed, in radians per
+/// second. Slow enough to make fine adjustments with a tap.
+const BASE_SPEED: f32 = 0.15;
+
+/// The fastest the sun moves after a key's been held for a while, in radians
+/// per second.
+const MAX_SPEED: f32 = 1.5;
+
+/// How quickly the sun speeds up while a key's held, in radians per second
+/// per second.
+const ACCELERATION: f32 = 0.75;
+
+/// How far the sun moves while it's dragged with the mouse, in radians per
+/// point.
+const DRAG_SPEED: f32 = 0.005;
+
+
+/// Moves the sun with the arrow keys. The sun speeds up the longer a key's
+/// held, and moves the same distance each second whatever the frame rate.
+pub struct SunControl {
+	/// How long the keys have been held for, in seconds, or zero if none are.
+	held: f32,
+}
+
+impl SunControl {
+	/// Creates a new controller, with no keys held.
+	pub fn new() -> SunControl {
+		SunControl { held: 0.0 }
+	}
+
+	/// Returns true while the sun's being moved.
+	pub fn is_adjusting(&self) -> bool {
+		self.held > 0.0
+	}
+
+	/// Moves the sun, given as an (elevation, azimuth) pair, in the direction
+	/// of the held keys over a frame delta, in seconds. Each component of
+	/// `direction` is -1, 0 or 1. The elevation stays between the nadir and
+	/// the zenith, and the azimuth wraps around into the range [-pi, pi).
+	pub fn update(&mut self, sun: Vector2<f32>, direction: Vector2<f32>, delta: f32)
+			-> Vector2<f32> {
+		if direction == Vector2::new(0.0, 0.0) {
+			self.held = 0.0;
+			return sun;
+		}
+
+		// Move at the average speed over the frame, so the distance covered
+		// doesn't depend on how the time's split up into frames
+		let start = self.speed();
+		self.held += delta;
+		let step = (start + self.speed()) * 0.5 * delta;
+		move_sun(sun, direction * step)
+	}
+
+	/// Returns the speed the sun's moving at, in radians per second.
+	fn speed(&self) -> f32 {
+		(BASE_SPEED + self.held * ACCELERATION).min(MAX_SPEED)
+	}
+}
+
+
+/// Moves the sun by a mouse drag, given as the distance the mouse moved in
+/// points, with left and up positive. Dragging up raises the sun, and dragging
+/// left turns it towards larger azimuths. The sun stays between the nadir and
+/// the zenith, and its azimuth wraps around.
+pub fn drag_sun(sun: Vector2<f32>, delta: (f32, f32)) -> Vector2<f32> {
+	let (dx, dy) = delta;
+	move_sun(sun, Vector2::new(dy, dx) * DRAG_SPEED)
+}
+
+/// Moves the sun by an (elevation, azimuth) step, in radians, clamping the
+/// elevation and wrapping the azimuth into the range [-pi, pi).
+fn move_sun(sun: Vector2<f32>, step: Vector2<f32>) -> Vector2<f32> {
+	let sun = normalize_sun(sun);
+	Vector2::new(
+		clamp(sun.x + step.x, -f32::consts::FRAC_PI_2, f32::consts::FRAC_PI_2),
+		wrap_angle(sun.y + step.y),
+	)
+}
+
+/// Returns the same sun position with its elevation between the nadir and the
+/// zenith. The day-night cycle carries the elevation on past the zenith, which
+/// is the same as turning the azimuth around.
+fn normalize_sun(sun: Vector2<f32>) -> Vector2<f32> {
+	let elevation = wrap_angle(sun.x);
+	if elevation > f32::consts::FRAC_PI_2 {
+		Vector2::new(f32::consts::PI - elevation, wrap_angle(sun.y + f32::consts::PI))
+	} else if elevation < -f32::consts::FRAC_PI_2 {
+		Vector2::new(-f32::consts::PI - elevation, wrap_angle(sun.y + f32::consts::PI))
+	} else {
+		Vector2::new(elevation, sun.y)
+	}
+}
+
+/// Wraps an angle, in radians, into the range [-pi, pi).
+fn wrap_angle(angle: f32) -> f32 {
+	let turn = 2.0 * f32::consts::PI;
+	let wrapped = (angle + f32::consts::PI) % turn;
+	if wrapped < 0.0 {
+		wrapped + turn - f32::consts::PI
+	} else {
+		wrapped - f32::consts::PI
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{drag_sun, normalize_sun, SunControl, BASE_SPEED, DRAG_SPEED};
+
+	use cgmath::Vector2;
+	use std::f32::consts::{FRAC_PI_2, PI};
+
+	#[test]
+	fn distance_independent_of_frame_rate() {
+		let up = Vector2::new(1.0, 0.0);
+		let mut slow = SunControl::new();
+		let mut fast = SunControl::new();
+		let mut slow_sun = Vector2::new(0.0, 0.0);
+		let mut fast_sun = slow_sun;
+		for _ in 0 .. 30 {
+			slow_sun = slow.update(slow_sun, up, 1.0 / 30.0);
+		}
+		for _ in 0 .. 144 {
+			fast_sun = fast.update(fast_sun, up, 1.0 / 144.0);
+		}
+		assert!((slow_sun.x - fast_sun.x).abs() < 1e-4, "{:?} {:?}", slow_sun, fast_sun);
+
+		// Holding the key for a second covers more ground than a second at
+		// the starting speed
+		assert!(slow_sun.x > BASE_SPEED);
+	}
+
+	#[test]
+	fn elevation_clamped_and_azimuth_wrapped() {
+		let mut control = SunControl::new();
+		let sun = control.update(Vector2::new(1.5, 3.1), Vector2::new(1.0, 1.0), 1.0);
+		assert_eq!(sun.x, FRAC_PI_2);
+		assert!(sun.y < 0.0 && sun.y >= -PI);
+		assert!(control.is_adjusting());
+
+		let sun = control.update(sun, Vector2::new(0.0, 0.0), 1.0);
+		assert!(!control.is_adjusting());
+		assert_eq!(sun.x, FRAC_PI_2);
+	}
+
+	#[test]
+	fn elevation_past_zenith_turns_azimuth() {
+		let sun = normalize_sun(Vector2::new(PI * 0.75, 0.0));
+		assert!((sun.x - PI * 0.25).abs() < 1e-6);
+		assert!((sun.y + PI).abs() < 1e-6);
+	}
+
+	#[test]
+	fn drag_moves_and_wraps_sun() {
+		let sun = drag_sun(Vector2::new(0.2, 0.0), (0.0, 40.0));
+		assert!((sun.x - (0.2 + 40.0 * DRAG_SPEED)).abs() < 1e-6);
+		assert_eq!(sun.y, 0.0);
+
+		let sun = drag_sun(Vector2::new(1.5, 3.1), (20.0, 1000.0));
+		assert_eq!(sun.x, FRAC_PI_2);
+		assert!((sun.y - (3.1 + 20.0 * DRAG_SPEED - 2.0 * PI)).abs() < 1e-5);
+	}
+}
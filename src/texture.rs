@@ -0,0 +1,175 @@
+
+//
+//  Textures
+//
+
+use gl;
+use gl::types::*;
+
+use std::ptr;
+
+
+/// The faces of a cube map, in the order their pixels are given in.
+const CUBE_FACES: [GLenum; 6] = [
+	gl::TEXTURE_CUBE_MAP_POSITIVE_X, gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+	gl::TEXTURE_CUBE_MAP_POSITIVE_Y, gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+	gl::TEXTURE_CUBE_MAP_POSITIVE_Z, gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+];
+
+
+/// The possible texture types.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureTarget {
+	Texture2D = gl::TEXTURE_2D as isize,
+	Cube = gl::TEXTURE_CUBE_MAP as isize,
+}
+
+impl TextureTarget {
+	/// Returns the targets image data is uploaded to, one for each face of a
+	/// cube map or a single one for a 2D texture.
+	pub fn faces(&self) -> &'static [GLenum] {
+		match *self {
+			TextureTarget::Texture2D => &[gl::TEXTURE_2D],
+			TextureTarget::Cube => &CUBE_FACES,
+		}
+	}
+}
+
+
+/// How a texture is sampled between texels. Textures are never mipmapped or
+/// anisotropically filtered, since they're only ever drawn close to their own
+/// size.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+	Nearest = gl::NEAREST as isize,
+	Linear = gl::LINEAR as isize,
+}
+
+
+/// An OpenGL texture, either 2D or a cube map. Coordinates outside the texture
+/// are clamped to the edge.
+pub struct Texture {
+	/// The texture object.
+	id: GLuint,
+	/// The kind of texture.
+	target: TextureTarget,
+	/// The format the texels are stored in on the GPU.
+	internal_format: GLenum,
+	/// The width of the texture (or each cube face), in pixels.
+	width: u32,
+	/// The height of the texture (or each cube face), in pixels.
+	height: u32,
+}
+
+impl Texture {
+	/// Creates a texture with the given internal format, leaving its contents
+	/// undefined.
+	pub fn new(target: TextureTarget, internal_format: GLenum, width: u32,
+			height: u32, filter: Filter) -> Texture {
+		let texture = Texture::create(target, internal_format, width, height, filter);
+		texture.upload(gl::RGBA, gl::FLOAT, ptr::null());
+		texture
+	}
+
+	/// Creates a texture from 8 bit RGBA pixels, stored row by row from the
+	/// bottom. Cube maps take each face's pixels one after the other, in the
+	/// order +X, -X, +Y, -Y, +Z, -Z.
+	///
+	/// Panics if the number of pixels doesn't match the size.
+	pub fn from_rgba8(target: TextureTarget, width: u32, height: u32,
+			pixels: &[u8], filter: Filter) -> Texture {
+		let faces = target.faces().len();
+		assert_eq!(pixels.len(), width as usize * height as usize * 4 * faces,
+			"wrong number of pixels for a {}x{} texture", width, height);
+		let texture = Texture::create(target, gl::RGBA8, width, height, filter);
+		texture.upload(gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const GLvoid);
+		texture
+	}
+
+	/// Creates the texture object and sets its sampling parameters, without
+	/// allocating any storage.
+	fn create(target: TextureTarget, internal_format: GLenum, width: u32,
+			height: u32, filter: Filter) -> Texture {
+		let mut texture = Texture {
+			id: 0,
+			target: target,
+			internal_format: internal_format,
+			width: width,
+			height: height,
+		};
+
+		let kind = target as GLenum;
+		unsafe {
+			gl::GenTextures(1, &mut texture.id);
+			gl::BindTexture(kind, texture.id);
+			gl::TexParameteri(kind, gl::TEXTURE_MIN_FILTER, filter as GLint);
+			gl::TexParameteri(kind, gl::TEXTURE_MAG_FILTER, filter as GLint);
+			gl::TexParameteri(kind, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+			gl::TexParameteri(kind, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+			gl::TexParameteri(kind, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+
+			// Textures are only ever sampled at the top level
+			gl::TexParameteri(kind, gl::TEXTURE_MAX_LEVEL, 0);
+		}
+		texture
+	}
+
+	/// Allocates storage for every face, filling it from `pixels` if it isn't
+	/// null. Each face's pixels follow on from the last.
+	fn upload(&self, format: GLenum, kind: GLenum, pixels: *const GLvoid) {
+		let channels = if format == gl::RGBA { 4 } else { 3 };
+		let size = if kind == gl::FLOAT { 4 } else { 1 };
+		let face_bytes = self.width as usize * self.height as usize * channels * size;
+
+		unsafe {
+			gl::BindTexture(self.target as GLenum, self.id);
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+			for (i, &face) in self.target.faces().iter().enumerate() {
+				let data = if pixels.is_null() {
+					ptr::null()
+				} else {
+					(pixels as *const u8).add(i * face_bytes) as *const GLvoid
+				};
+				gl::TexImage2D(face, 0, self.internal_format as GLint,
+					self.width as GLsizei, self.height as GLsizei, 0, format, kind, data);
+			}
+			gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+		}
+	}
+
+	/// Reallocates the texture at a new size, discarding its contents. Does
+	/// nothing if the size hasn't changed.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		if width == self.width && height == self.height {
+			return;
+		}
+		self.width = width;
+		self.height = height;
+		self.upload(gl::RGBA, gl::FLOAT, ptr::null());
+	}
+
+	/// Returns the OpenGL name of the texture, for attaching it to a
+	/// framebuffer.
+	pub fn id(&self) -> GLuint {
+		self.id
+	}
+
+	/// Returns the kind of texture.
+	pub fn target(&self) -> TextureTarget {
+		self.target
+	}
+
+	/// Binds the texture to a texture unit.
+	pub fn bind(&self, unit: u32) {
+		unsafe {
+			gl::ActiveTexture(gl::TEXTURE0 + unit);
+			gl::BindTexture(self.target as GLenum, self.id);
+		}
+	}
+}
+
+impl Drop for Texture {
+	fn drop(&mut self) {
+		unsafe { gl::DeleteTextures(1, &self.id) };
+	}
+}
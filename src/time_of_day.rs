@@ -0,0 +1,100 @@
+
+//
+//  Time of Day
+//
+
+use math::clamp;
+
+use cgmath::Vector2;
+use std::f32;
+
+
+/// The time of day the animation starts at, as a fraction of a day, where
+/// 0.25 is sunrise and 0.5 is midday.
+const START_TIME: f32 = 0.3;
+
+/// The largest allowed speed multiplier, in either direction.
+const MAX_SPEED: f32 = 64.0;
+
+/// The smallest allowed speed multiplier, in either direction.
+const MIN_SPEED: f32 = 1.0 / 64.0;
+
+
+/// Animates the sun across the sky over the course of a day.
+pub struct TimeOfDay {
+	/// The current time, as a fraction of a day in the range [0, 1).
+	time: f32,
+	/// The length of a full day at normal speed, in seconds.
+	period: f32,
+	/// A multiplier applied to the passage of time. Negative values run the
+	/// day backwards.
+	speed: f32,
+	/// The azimuth of the sun's path across the sky.
+	pub azimuth: f32,
+	/// True if time is stopped.
+	pub paused: bool,
+}
+
+impl TimeOfDay {
+	/// Creates a new animator which takes `period` seconds to complete a full
+	/// day, with the sun travelling along a path at the given azimuth.
+	pub fn new(period: f32, azimuth: f32) -> TimeOfDay {
+		TimeOfDay {
+			time: START_TIME,
+			period: period,
+			speed: 1.0,
+			azimuth: azimuth,
+			paused: false,
+		}
+	}
+
+	/// Advances the time of day by a frame delta, in seconds.
+	pub fn update(&mut self, delta: f32) {
+		if self.paused {
+			return;
+		}
+
+		// Wrap around into the range [0, 1), in both directions
+		self.time = (self.time + delta * self.speed / self.period) % 1.0;
+		if self.time < 0.0 {
+			self.time += 1.0;
+		}
+	}
+
+	/// Doubles the speed at which time passes, up to a limit.
+	pub fn speed_up(&mut self) {
+		self.set_speed(self.speed * 2.0);
+	}
+
+	/// Halves the speed at which time passes, down to a limit.
+	pub fn slow_down(&mut self) {
+		self.set_speed(self.speed / 2.0);
+	}
+
+	/// Reverses the direction in which time passes.
+	pub fn reverse(&mut self) {
+		self.speed = -self.speed;
+	}
+
+	/// Returns the current speed multiplier.
+	pub fn speed(&self) -> f32 {
+		self.speed
+	}
+
+	/// Sets the speed multiplier, keeping its sign but clamping its magnitude.
+	fn set_speed(&mut self, speed: f32) {
+		let magnitude = clamp(speed.abs(), MIN_SPEED, MAX_SPEED);
+		self.speed = magnitude * speed.signum();
+	}
+
+	/// Returns the sun's position for the current time of day, as an
+	/// (elevation, azimuth) pair in the same form `recalc_sun` expects.
+	///
+	/// The sun travels in a full circle, rising at an elevation of 0, passing
+	/// overhead at midday, and setting at an elevation of pi. During the night
+	/// it's below the horizon.
+	pub fn sun_position(&self) -> Vector2<f32> {
+		let elevation = (self.time - 0.25) * 2.0 * f32::consts::PI;
+		Vector2::new(elevation, self.azimuth)
+	}
+}
@@ -0,0 +1,71 @@
+
+//
+//  Sky Transitions
+//
+
+use math::smoothstep;
+use sky::SkyState;
+
+
+/// Tweens the sky shown on screen from an old state towards the sky model's
+/// current one, so changing its settings doesn't make the sky pop.
+pub struct SkyTransition {
+	/// The state being blended away from.
+	from: SkyState,
+	/// How long the transition takes, in seconds.
+	duration: f32,
+	/// How long the transition has been running for, in seconds.
+	elapsed: f32,
+}
+
+impl SkyTransition {
+	/// Starts a transition away from a state, taking `duration` seconds.
+	pub fn new(from: SkyState, duration: f32) -> SkyTransition {
+		SkyTransition {
+			from: from,
+			duration: duration,
+			elapsed: 0.0,
+		}
+	}
+
+	/// Advances the transition by a frame delta, in seconds.
+	pub fn update(&mut self, delta: f32) {
+		self.elapsed += delta;
+	}
+
+	/// Returns true once the transition has reached the target state.
+	pub fn finished(&self) -> bool {
+		self.elapsed >= self.duration
+	}
+
+	/// Returns the state to show part way to a target state. The blend eases in
+	/// and out, so the sky doesn't start or stop changing abruptly.
+	pub fn state(&self, to: &SkyState) -> SkyState {
+		self.from.lerp(to, smoothstep(0.0, self.duration, self.elapsed))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::SkyTransition;
+	use sky::SkyModel;
+
+	use cgmath::Vector2;
+
+	#[test]
+	fn runs_from_start_to_target() {
+		let from = SkyModel::new(2.0, [0.1; 3], Vector2::new(0.4, 0.0)).state();
+		let to = SkyModel::new(6.0, [0.3; 3], Vector2::new(0.4, 0.0)).state();
+		let mut transition = SkyTransition::new(from, 0.5);
+		assert_eq!(transition.state(&to), from);
+
+		transition.update(0.25);
+		assert!(!transition.finished());
+		assert!(transition.state(&to) != from && transition.state(&to) != to);
+
+		transition.update(0.25);
+		assert!(transition.finished());
+		assert_eq!(transition.state(&to), to);
+	}
+}
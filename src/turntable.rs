@@ -0,0 +1,84 @@
+
+//
+//  Turntable Capture
+//
+
+use std::f32;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+
+/// Spins the camera through a full turn, one step per frame, saving each
+/// frame to a numbered image so the sequence can be made into a video.
+pub struct Turntable {
+	/// The number of frames in a full turn.
+	frames: u32,
+	/// The directory the frames are saved into.
+	output: PathBuf,
+	/// The frame about to be saved.
+	frame: u32,
+}
+
+impl Turntable {
+	/// Starts a turntable of the given number of frames, creating the output
+	/// directory if it doesn't exist.
+	pub fn new<P: AsRef<Path>>(frames: u32, output: P) -> io::Result<Turntable> {
+		fs::create_dir_all(&output)?;
+		Ok(Turntable {
+			frames: frames,
+			output: output.as_ref().to_path_buf(),
+			frame: 0,
+		})
+	}
+
+	/// Returns the angle the camera turns by between frames, in radians.
+	pub fn step(&self) -> f32 {
+		2.0 * f32::consts::PI / self.frames as f32
+	}
+
+	/// Returns the path to save the current frame to. The frame number is
+	/// padded so the files sort in order.
+	pub fn frame_path(&self) -> PathBuf {
+		self.output.join(format!("frame-{:04}.png", self.frame))
+	}
+
+	/// Moves on to the next frame.
+	pub fn advance(&mut self) {
+		self.frame += 1;
+	}
+
+	/// Returns true once every frame of the turn has been saved.
+	pub fn finished(&self) -> bool {
+		self.frame >= self.frames
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::Turntable;
+
+	use std::env;
+	use std::f32::consts::PI;
+	use std::fs;
+
+	#[test]
+	fn saves_each_frame_of_a_turn() {
+		let output = env::temp_dir().join("sky-turntable-test");
+		let mut turntable = Turntable::new(4, &output).unwrap();
+		assert!(output.is_dir());
+		assert_eq!(turntable.step(), PI / 2.0);
+
+		let mut paths = Vec::new();
+		while !turntable.finished() {
+			paths.push(turntable.frame_path());
+			turntable.advance();
+		}
+		assert_eq!(paths, vec![
+			output.join("frame-0000.png"), output.join("frame-0001.png"),
+			output.join("frame-0002.png"), output.join("frame-0003.png"),
+		]);
+		fs::remove_dir(&output).unwrap();
+	}
+}
@@ -0,0 +1,60 @@
+
+//
+//  Uniform Buffers
+//
+
+use gl;
+use gl::types::*;
+
+use std::marker::PhantomData;
+use std::mem;
+
+
+/// A uniform buffer holding a single value, bound to a fixed binding point so
+/// any program whose uniform block is attached to that point reads from it.
+///
+/// `T` must be `#[repr(C)]` and laid out to match the block's `std140` layout,
+/// padding included, since its bytes are copied straight into the buffer.
+pub struct UniformBuffer<T: Copy> {
+	/// The buffer object.
+	ubo: GLuint,
+	/// The type of value stored in the buffer.
+	contents: PhantomData<T>,
+}
+
+impl<T: Copy> UniformBuffer<T> {
+	/// Creates a uniform buffer holding an initial value, and attaches it to a
+	/// binding point.
+	pub fn new(binding: GLuint, value: &T) -> UniformBuffer<T> {
+		let mut buffer = UniformBuffer {
+			ubo: 0,
+			contents: PhantomData,
+		};
+
+		unsafe {
+			gl::GenBuffers(1, &mut buffer.ubo);
+			gl::BindBuffer(gl::UNIFORM_BUFFER, buffer.ubo);
+			gl::BufferData(gl::UNIFORM_BUFFER, mem::size_of::<T>() as GLsizeiptr,
+				value as *const T as *const GLvoid, gl::DYNAMIC_DRAW);
+			gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+			gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, buffer.ubo);
+		}
+		buffer
+	}
+
+	/// Replaces the value in the buffer.
+	pub fn update(&self, value: &T) {
+		unsafe {
+			gl::BindBuffer(gl::UNIFORM_BUFFER, self.ubo);
+			gl::BufferSubData(gl::UNIFORM_BUFFER, 0, mem::size_of::<T>() as GLsizeiptr,
+				value as *const T as *const GLvoid);
+			gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+		}
+	}
+}
+
+impl<T: Copy> Drop for UniformBuffer<T> {
+	fn drop(&mut self) {
+		unsafe { gl::DeleteBuffers(1, &self.ubo) };
+	}
+}
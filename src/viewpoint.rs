@@ -0,0 +1,151 @@
+
+//
+//  Viewpoints
+//
+
+use camera::CameraState;
+use sky::SkyModel;
+
+use cgmath::{Vector2, Vector3};
+use std::f32;
+
+
+/// The number of radians in a degree, so the presets can be written in
+/// degrees.
+const DEGREES: f32 = f32::consts::PI / 180.0;
+
+/// The built-in viewpoints, in the order they're cycled through.
+pub const VIEWPOINTS: [Viewpoint; 3] = [
+	Viewpoint {
+		name: "ground-level sunset",
+		camera: CameraState {
+			position: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+			horizontal: 0.0,
+			vertical: 4.0 * DEGREES,
+			fov: 70.0 * DEGREES,
+		},
+		sun: Vector2 { x: 2.0 * DEGREES, y: 0.0 },
+		turbidity: 3.0,
+		albedo: [0.15, 0.25, 0.07],
+	},
+	Viewpoint {
+		name: "high-altitude noon",
+		camera: CameraState {
+			position: Vector3 { x: 0.0, y: 200.0, z: 0.0 },
+			horizontal: 180.0 * DEGREES,
+			vertical: 10.0 * DEGREES,
+			fov: 90.0 * DEGREES,
+		},
+		sun: Vector2 { x: 75.0 * DEGREES, y: 30.0 * DEGREES },
+		turbidity: 1.5,
+		albedo: [0.9, 0.9, 0.9],
+	},
+	Viewpoint {
+		name: "hazy morning",
+		camera: CameraState {
+			position: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+			horizontal: 60.0 * DEGREES,
+			vertical: 15.0 * DEGREES,
+			fov: 70.0 * DEGREES,
+		},
+		sun: Vector2 { x: 12.0 * DEGREES, y: 90.0 * DEGREES },
+		turbidity: 7.0,
+		albedo: [0.3, 0.3, 0.3],
+	},
+];
+
+
+/// A named place to look at the sky from, along with the sky to show there,
+/// so a demo can jump straight to a particular scene.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewpoint {
+	/// The name the viewpoint's chosen by.
+	pub name: &'static str,
+	/// Where the camera is and where it's looking.
+	pub camera: CameraState,
+	/// The sun's position, as an (elevation, azimuth) pair, in radians.
+	pub sun: Vector2<f32>,
+	/// The atmospheric turbidity, between 1 and 10.
+	pub turbidity: f32,
+	/// The ground albedo for each colour channel.
+	pub albedo: [f32; 3],
+}
+
+impl Viewpoint {
+	/// Sets the sky's turbidity, albedo, and sun position to the viewpoint's,
+	/// recomputing its coefficients. Returns true if anything changed.
+	pub fn apply_sky(&self, sky: &mut SkyModel) -> bool {
+		let turbidity = sky.set_turbidity(self.turbidity);
+		let albedo = sky.set_albedo(self.albedo);
+		let sun = sky.set_sun(self.sun);
+		turbidity || albedo || sun
+	}
+}
+
+
+/// Finds a built-in viewpoint by name, ignoring case.
+pub fn find(name: &str) -> Option<&'static Viewpoint> {
+	VIEWPOINTS.iter().find(|viewpoint| viewpoint.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the names of the built-in viewpoints, for listing when a name isn't
+/// recognised.
+pub fn names() -> Vec<&'static str> {
+	VIEWPOINTS.iter().map(|viewpoint| viewpoint.name).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{find, VIEWPOINTS};
+	use camera::Camera;
+	use config::Config;
+	use player::Player;
+	use sky::SkyModel;
+
+	use cgmath::{Vector2, InnerSpace};
+
+	#[test]
+	fn find_ignores_case() {
+		assert_eq!(find("Ground-Level Sunset").map(|viewpoint| viewpoint.name),
+			Some("ground-level sunset"));
+		assert_eq!(find("underwater"), None);
+		for (index, viewpoint) in VIEWPOINTS.iter().enumerate() {
+			assert!(VIEWPOINTS[.. index].iter().all(|other| other.name != viewpoint.name),
+				"`{}` appears twice", viewpoint.name);
+		}
+	}
+
+	#[test]
+	fn teleport_sets_camera_and_sky() {
+		let config = Config::default();
+		let mut player = Player::new(Camera::new(900, 620, &config), &config);
+		let mut sky = SkyModel::new(config.turbidity, config.albedo, Vector2::new(0.5, 0.0));
+		for viewpoint in VIEWPOINTS.iter() {
+			player.teleport_to_viewpoint(viewpoint);
+			let state = player.camera.state();
+			assert!((state.position - viewpoint.camera.position).magnitude() < 1e-4);
+			// The angle around could come back a full turn away
+			let turn = state.horizontal - viewpoint.camera.horizontal;
+			assert!(turn.sin().abs() < 1e-4 && turn.cos() > 0.0);
+			assert!((state.vertical - viewpoint.camera.vertical).abs() < 1e-4);
+			assert!((state.fov - viewpoint.camera.fov).abs() < 1e-6);
+
+			viewpoint.apply_sky(&mut sky);
+			let expected = SkyModel::new(viewpoint.turbidity, viewpoint.albedo,
+				viewpoint.sun);
+			assert_eq!(sky.coefficients(), expected.coefficients());
+			assert_eq!(sky.sun_direction(), expected.sun_direction());
+		}
+	}
+
+	#[test]
+	fn sunset_faces_the_sun() {
+		let config = Config::default();
+		let mut player = Player::new(Camera::new(900, 620, &config), &config);
+		let viewpoint = find("ground-level sunset").unwrap();
+		player.teleport_to_viewpoint(viewpoint);
+		let sky = SkyModel::new(viewpoint.turbidity, viewpoint.albedo, viewpoint.sun);
+		assert!(player.camera.forward().dot(sky.sun_direction()) > 0.99);
+	}
+}